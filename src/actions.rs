@@ -1,9 +1,15 @@
+mod backup_instance_action;
 mod create_instance_action;
+mod import_box_action;
+mod import_oci_action;
 mod load_instance_action;
 mod start_instance_action;
 mod stop_instance_action;
 
+pub use backup_instance_action::BackupInstanceAction;
 pub use create_instance_action::CreateInstanceAction;
+pub use import_box_action::{ImportBoxAction, ImportedBox};
+pub use import_oci_action::{ImportOciAction, ImportedOci};
 pub use load_instance_action::LoadInstanceAction;
 pub use start_instance_action::StartInstanceAction;
 pub use stop_instance_action::StopInstanceAction;
@@ -0,0 +1,271 @@
+use crate::commands::Context;
+use crate::error::{Error, FsOperation, Result};
+use crate::instance::InstanceSerializer;
+use crate::models::{Backup, BackupKind, Instance};
+use crate::qemu::{BACKUP_BITMAP, QemuImg, SYSTEM_DRIVE_ID, Swtpm};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct BackupInstanceAction {
+    instance: Instance,
+}
+
+impl BackupInstanceAction {
+    pub fn new(instance: &Instance) -> Self {
+        Self {
+            instance: instance.clone(),
+        }
+    }
+
+    /// Backs up the system disk into `<dir>/<instance>`. A running instance
+    /// gets an incremental backup if the chain ends with the backup its dirty
+    /// bitmap started at. Anything else takes a full backup, which starts the
+    /// bitmap over.
+    pub fn run(&mut self, context: &Context, dir: &Path) -> Result<Backup> {
+        let env = context.get_env();
+        let system = context.get_system();
+        let instance_store = context.get_instance_store();
+        let name = self.instance.name.clone();
+
+        // Neither path keeps the encryption, so the backup would hold the
+        // data in the clear
+        if self.instance.encryption.is_some() {
+            return Err(Error::EncryptedDiskNotSupported(
+                name,
+                "back up".to_string(),
+            ));
+        }
+
+        let backup_dir = Backup::get_instance_dir(dir, &name);
+        system.create_dir(&backup_dir)?;
+        let chain = Backup::read_chain(system, &backup_dir);
+        let previous = chain.last().filter(|backup| {
+            self.instance.backup_point.as_deref() == Some(&backup.path.to_string_lossy())
+        });
+        let id = Self::next_id(chain.last());
+
+        let qemu_img = QemuImg::new(system);
+        let image = env.get_instance_image_file(&name);
+        let backup = if instance_store.is_running(&self.instance) {
+            let mut monitor = instance_store.get_monitor(&self.instance)?;
+            let previous = match previous {
+                Some(previous) if monitor.has_backup_bitmap(SYSTEM_DRIVE_ID)? => Some(previous),
+                _ => None,
+            };
+
+            let size = self.instance.disk_capacity.get_bytes() as u64;
+            let backup = match previous {
+                Some(previous) => {
+                    let backup = Backup::new(&backup_dir, id, BackupKind::Incremental);
+                    qemu_img.create_overlay(
+                        &backup.path.to_string_lossy(),
+                        &previous.get_file_name(),
+                        size,
+                    )?;
+                    backup
+                }
+                None => {
+                    let backup = Backup::new(&backup_dir, id, BackupKind::Full);
+                    qemu_img.create(&backup.path.to_string_lossy(), size)?;
+                    backup
+                }
+            };
+
+            let result = monitor.backup_drive(
+                &name,
+                SYSTEM_DRIVE_ID,
+                &backup.path.to_string_lossy(),
+                backup.kind == BackupKind::Incremental,
+            );
+            if result.is_err() {
+                system.remove_file(&backup.path).ok();
+            }
+            result?;
+            backup
+        } else {
+            // qemu-img cannot copy by bitmap, so a stopped instance gets a full
+            // copy and a fresh bitmap for the next backup while running
+            let backup = Backup::new(&backup_dir, id, BackupKind::Full);
+            qemu_img.convert(&image, &backup.path.to_string_lossy())?;
+            qemu_img.reset_bitmap(&image, BACKUP_BITMAP)?;
+            backup
+        };
+
+        // The config comes along, so a restore gets the same resources, and
+        // so does a kernel of its own, as of an instance built from a
+        // container image
+        let mut file = system.create_file(&backup.get_config_file())?;
+        InstanceSerializer::new().serialize(&self.instance, &mut file)?;
+        let mut files: Vec<(PathBuf, PathBuf)> = self
+            .instance
+            .get_owned_boot_files()?
+            .into_iter()
+            .map(|file| {
+                (
+                    PathBuf::from(env.get_instance_boot_file(&name, file)),
+                    backup.get_boot_file(file),
+                )
+            })
+            .collect();
+        // Same as a clone, the boot entries of the firmware and the secrets
+        // the guest sealed to the TPM go along
+        let nvram = PathBuf::from(env.get_instance_nvram_file(&name));
+        if system.exists_path(&nvram) {
+            files.push((nvram, backup.get_nvram_file()));
+        }
+        for file in
+            Swtpm::new(system, Path::new(&env.get_instance_tpm_dir(&name))).get_state_files()
+        {
+            if let Some(file_name) = file.file_name() {
+                let to = backup.get_tpm_file(&file_name.to_string_lossy());
+                files.push((file, to));
+            }
+        }
+        for (from, to) in files {
+            io::copy(&mut system.open_file(&from)?, &mut system.create_file(&to)?)
+                .map_err(|error| Error::from_fs(FsOperation::WriteFile, &to, error))?;
+        }
+
+        self.instance.backup_point = Some(backup.path.to_string_lossy().into_owned());
+        instance_store.store(&self.instance)?;
+        Ok(backup)
+    }
+
+    // Ids are creation times, but two backups within a second still need an
+    // order
+    fn next_id(last: Option<&Backup>) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        match last {
+            Some(last) if last.id >= now => last.id + 1,
+            _ => now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{DiskEncryption, Environment, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_env() -> Environment {
+        Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_next_id_follows_a_backup_from_the_future() {
+        let last = Backup::new(Path::new("/backup"), u64::MAX - 1, BackupKind::Full);
+
+        assert_eq!(BackupInstanceAction::next_id(Some(&last)), u64::MAX);
+    }
+
+    #[test]
+    fn test_reject_encrypted_disk() {
+        let instance = Instance {
+            name: "test".to_string(),
+            encryption: Some(DiskEncryption::File),
+            ..Instance::default()
+        };
+        let context = Context::new(
+            Rc::new(SystemMock::new()),
+            build_env(),
+            Box::new(InstanceStoreMock::new(vec![instance.clone()])),
+        );
+
+        assert!(matches!(
+            BackupInstanceAction::new(&instance).run(&context, Path::new("/backup")),
+            Err(Error::EncryptedDiskNotSupported(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_backup_stopped_instance_takes_a_full_copy() {
+        let env = build_env();
+        let image = env.get_instance_image_file("test");
+        // A backup from the future pins the id of the next one
+        let system = SystemMock::new()
+            .add_file("/backup/test/4000000000-full.qcow2", b"")
+            .add_command_output(
+                &format!(
+                    "qemu-img convert -f qcow2 -O qcow2 {image} /backup/test/4000000001-full.qcow2"
+                ),
+                b"",
+            )
+            .add_command_output(
+                &format!("qemu-img bitmap --add {image} {BACKUP_BITMAP}"),
+                b"",
+            );
+        let instance = Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        };
+        let store = InstanceStoreMock::new(vec![instance.clone()]);
+        let stored = store.stored.clone();
+        let context = Context::new(Rc::new(system), env, Box::new(store));
+
+        let backup = BackupInstanceAction::new(&instance)
+            .run(&context, Path::new("/backup"))
+            .unwrap();
+
+        // The earlier backup is not where the bitmap started, so no
+        // incremental can build on it
+        assert_eq!(backup.kind, BackupKind::Full);
+        assert_eq!(
+            stored.lock().unwrap()[0].backup_point,
+            Some(backup.path.to_string_lossy().into_owned())
+        );
+    }
+
+    #[test]
+    fn test_backup_keeps_the_firmware_variables_and_the_tpm_state() {
+        let env = build_env();
+        let image = env.get_instance_image_file("test");
+        let tpm_dir = env.get_instance_tpm_dir("test");
+        let system = Rc::new(
+            SystemMock::new()
+                .add_file("/backup/test/4000000000-full.qcow2", b"")
+                .add_file(&env.get_instance_nvram_file("test"), b"vars")
+                .add_file(&format!("{tpm_dir}/tpm2-00.permall"), b"sealed")
+                .add_command_output(
+                    &format!(
+                        "qemu-img convert -f qcow2 -O qcow2 {image} /backup/test/4000000001-full.qcow2"
+                    ),
+                    b"",
+                )
+                .add_command_output(
+                    &format!("qemu-img bitmap --add {image} {BACKUP_BITMAP}"),
+                    b"",
+                ),
+        );
+        let instance = Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        };
+        let store = InstanceStoreMock::new(vec![instance.clone()]);
+        let context = Context::new(system.clone(), env, Box::new(store));
+
+        BackupInstanceAction::new(&instance)
+            .run(&context, Path::new("/backup"))
+            .unwrap();
+
+        assert_eq!(
+            system.get_written_file("/backup/test/4000000001.nvram.fd"),
+            Some(b"vars".to_vec())
+        );
+        assert_eq!(
+            system.get_written_file("/backup/test/4000000001.tpm.tpm2-00.permall"),
+            Some(b"sealed".to_vec())
+        );
+    }
+}
@@ -1,26 +1,34 @@
 use crate::commands::Context;
 use crate::error::Result;
-use crate::models::Instance;
+use crate::models::{DiskEncryption, Instance};
 use crate::qemu::QemuImg;
 use crate::ssh::SshKeyGenerator;
 use std::path::Path;
 
 #[derive(Default)]
-pub struct CreateInstanceAction;
+pub struct CreateInstanceAction {
+    passphrase: Option<String>,
+}
 
 impl CreateInstanceAction {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Passphrase of the system disk, for instances with encryption
+    pub fn set_passphrase(&mut self, passphrase: &str) {
+        self.passphrase = Some(passphrase.to_string());
     }
 
+    /// Copies the image into the system disk of the instance, or starts out
+    /// with an empty disk without an image
     pub fn run(
         &mut self,
         context: &Context,
-        image_path: &str,
-        mut instance: Instance,
+        image_path: Option<&str>,
+        instance: Instance,
     ) -> Result<()> {
         let system = context.get_system();
-        let instance_name = instance.name.clone();
         let target_dir = &context.get_env().get_instance_dir2(&instance.name);
         let tmp_dir = &format!("{target_dir}.tmp");
         let tmp_image = &format!("{tmp_dir}/machine.img");
@@ -33,11 +41,47 @@ impl CreateInstanceAction {
 
         let qemu_img = QemuImg::new(system);
 
-        // Create virtual machine instance image file
-        qemu_img.convert(image_path, tmp_image)?;
+        let size = instance.disk_capacity.get_bytes() as u64;
+        let Some(image_path) = image_path else {
+            qemu_img.create(tmp_image, size)?;
+            return self.finish(context, instance, tmp_dir, target_dir);
+        };
+        if let Some(encryption) = instance.encryption {
+            let secret_file = &format!("{tmp_dir}/disk_secret");
+            system.write_secret_file(
+                Path::new(secret_file),
+                self.passphrase.as_deref().unwrap_or_default().as_bytes(),
+            )?;
+
+            let result = qemu_img
+                .convert_encrypted(image_path, tmp_image, secret_file)
+                .and_then(|_| qemu_img.resize_encrypted(tmp_image, size, secret_file));
+
+            // A prompted passphrase is never stored
+            if encryption == DiskEncryption::Prompt || result.is_err() {
+                system.remove_file(Path::new(secret_file))?;
+            }
+            result?;
+        } else {
+            // Create virtual machine instance image file
+            qemu_img.convert(image_path, tmp_image)?;
+
+            // Set disk capacity
+            qemu_img.resize(tmp_image, size)?;
+        }
 
-        // Set disk capacity
-        qemu_img.resize(tmp_image, instance.disk_capacity.get_bytes() as u64)?;
+        self.finish(context, instance, tmp_dir, target_dir)
+    }
+
+    fn finish(
+        &self,
+        context: &Context,
+        mut instance: Instance,
+        tmp_dir: &str,
+        target_dir: &str,
+    ) -> Result<()> {
+        let system = context.get_system();
+        let instance_name = instance.name.clone();
 
         // Write configuration file
         instance.name = format!("{instance_name}.tmp");
@@ -0,0 +1,284 @@
+use crate::commands::Context;
+use crate::error::{Error, FsOperation, Result};
+use crate::models::{HashAlg, VagrantBox, VagrantBoxMetadata};
+use crate::platform::System;
+use crate::qemu::QemuImg;
+use crate::vagrant::{self, BoxUnpacker};
+use crate::view::{Console, Spinner, TransferView};
+use crate::web::WebClient;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A box unpacked into a directory of its own, ready to become an instance
+pub struct ImportedBox {
+    pub dir: PathBuf,
+    /// The system disk as qcow2
+    pub image: PathBuf,
+    /// The insecure private key for the first login
+    pub key: PathBuf,
+    pub metadata: VagrantBoxMetadata,
+    pub vagrant_box: VagrantBox,
+}
+
+pub struct ImportBoxAction {
+    source: String,
+}
+
+impl ImportBoxAction {
+    /// `source` is the path or the http(s) URL of a .box file
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+        }
+    }
+
+    fn is_url(&self) -> bool {
+        self.source.starts_with("https://") || self.source.starts_with("http://")
+    }
+
+    fn invalid(&self, reason: &str) -> Error {
+        Error::InvalidBox(self.source.clone(), reason.to_string())
+    }
+
+    /// Unpacks the box into `<cache>/boxes/<instance>.tmp`. The caller
+    /// removes the directory once the instance is created.
+    pub fn run(
+        &self,
+        console: &mut Console<'_>,
+        context: &Context,
+        instance: &str,
+    ) -> Result<ImportedBox> {
+        let system = context.get_system();
+        let dir = Path::new(context.get_env().get_cache_dir())
+            .join("boxes")
+            .join(format!("{instance}.tmp"));
+        if system.exists_path(&dir) {
+            system.remove_dir(&dir)?;
+        }
+        system.create_dir(&dir)?;
+
+        let result = self.unpack(console, system, &dir);
+        if result.is_err() {
+            system.remove_dir(&dir).ok();
+        }
+        result
+    }
+
+    fn unpack(
+        &self,
+        console: &mut Console<'_>,
+        system: &dyn System,
+        dir: &Path,
+    ) -> Result<ImportedBox> {
+        let box_file = if self.is_url() {
+            let box_file = dir.join("download.box");
+            let view = Arc::new(Mutex::new(TransferView::new("Downloading box")));
+            console.play(view.clone());
+            let result = WebClient::new().and_then(|client| {
+                client.download_file(system, &self.source, &box_file, view, HashAlg::Sha256)
+            });
+            console.stop();
+            result?;
+            box_file
+        } else {
+            PathBuf::from(&self.source)
+        };
+
+        console.play(Arc::new(Mutex::new(Spinner::new(
+            "Unpacking box".to_string(),
+        ))));
+        let result = self.unpack_box(system, &box_file, dir);
+        console.stop();
+        let metadata = result?;
+
+        let vagrant_box = VagrantBox::from_source(&self.source, &metadata.provider);
+        let (disk, format) = match metadata.provider.as_str() {
+            "libvirt" => {
+                let disk = dir.join("box.img");
+                if !system.exists_path(&disk) {
+                    return Err(self.invalid("box.img is missing"));
+                }
+                (disk, metadata.format.as_deref().unwrap_or("qcow2"))
+            }
+            "virtualbox" => {
+                let mut files = system.read_dir(dir)?;
+                files.sort();
+                let disk = files
+                    .into_iter()
+                    .find(|file| file.extension().is_some_and(|ext| ext == "vmdk"))
+                    .ok_or_else(|| self.invalid("no VMDK disk found"))?;
+                (disk, "vmdk")
+            }
+            provider => {
+                return Err(Error::UnsupportedBoxProvider(
+                    self.source.clone(),
+                    provider.to_string(),
+                ));
+            }
+        };
+
+        // libvirt boxes usually carry a qcow2 image already
+        let image = if format == "qcow2" {
+            disk
+        } else {
+            let image = dir.join("box.qcow2");
+            console.play(Arc::new(Mutex::new(Spinner::new(
+                "Converting box disk".to_string(),
+            ))));
+            let result = QemuImg::new(system).import(
+                &disk.to_string_lossy(),
+                format,
+                &image.to_string_lossy(),
+            );
+            console.stop();
+            result?;
+            image
+        };
+
+        let key = dir.join("vagrant_insecure_key");
+        system.write_secret_file(&key, vagrant::INSECURE_KEY.as_bytes())?;
+
+        Ok(ImportedBox {
+            dir: dir.to_path_buf(),
+            image,
+            key,
+            metadata,
+            vagrant_box,
+        })
+    }
+
+    fn unpack_box(
+        &self,
+        system: &dyn System,
+        box_file: &Path,
+        dir: &Path,
+    ) -> Result<VagrantBoxMetadata> {
+        BoxUnpacker::new()
+            .unpack(system.open_file(box_file)?, &mut |name, content| {
+                let path = dir.join(name);
+                let mut file = system
+                    .create_file(&path)
+                    .map_err(|_| io::Error::other(format!("cannot write {}", path.display())))?;
+                io::copy(content, &mut file).map(|_| ())
+            })
+            .map_err(|error| Error::from_fs(FsOperation::ReadFile, box_file, error))?;
+
+        let metadata = system
+            .read_file_to_string(&dir.join("metadata.json"))
+            .map_err(|_| self.invalid("metadata.json is missing"))?;
+        serde_json::from_str(&metadata)
+            .map_err(|error| self.invalid(&format!("metadata.json is not valid: {error}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+    use tar::{Builder, Header};
+
+    fn build_box(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for (name, content) in files {
+            let mut header = Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn build_context(system: SystemMock) -> Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        Context::new(
+            Rc::new(system),
+            env,
+            Box::new(InstanceStoreMock::new(Vec::new())),
+        )
+    }
+
+    #[test]
+    fn test_import_box_of_unsupported_provider() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(SystemMock::new().add_file(
+            "/boxes/dev.box",
+            &build_box(&[("metadata.json", br#"{"provider": "hyperv"}"#)]),
+        ));
+
+        let result = ImportBoxAction::new("/boxes/dev.box").run(console, &context, "test");
+
+        assert!(
+            matches!(result, Err(Error::UnsupportedBoxProvider(_, provider)) if provider == "hyperv")
+        );
+    }
+
+    #[test]
+    fn test_import_box_without_metadata() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(
+            SystemMock::new().add_file("/boxes/dev.box", &build_box(&[("box.img", b"disk")])),
+        );
+
+        let result = ImportBoxAction::new("/boxes/dev.box").run(console, &context, "test");
+
+        assert!(matches!(result, Err(Error::InvalidBox(..))));
+    }
+
+    #[test]
+    fn test_import_libvirt_box_without_disk() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(SystemMock::new().add_file(
+            "/boxes/dev.box",
+            &build_box(&[("metadata.json", br#"{"provider": "libvirt"}"#)]),
+        ));
+
+        let result = ImportBoxAction::new("/boxes/dev.box").run(console, &context, "test");
+
+        assert!(
+            matches!(result, Err(Error::InvalidBox(_, reason)) if reason == "box.img is missing")
+        );
+    }
+
+    #[test]
+    fn test_import_libvirt_box() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(SystemMock::new().add_file(
+            "/boxes/dev.box",
+            &build_box(&[
+                (
+                    "metadata.json",
+                    br#"{"provider": "libvirt", "format": "qcow2"}"#,
+                ),
+                ("box.img", b"disk"),
+            ]),
+        ));
+
+        let imported = ImportBoxAction::new("/boxes/dev.box")
+            .run(console, &context, "test")
+            .unwrap();
+
+        assert_eq!(imported.image, Path::new("/cache/boxes/test.tmp/box.img"));
+        assert_eq!(imported.metadata.provider, "libvirt");
+        // The key comes with cubic, so an import works offline
+        assert_eq!(
+            context
+                .get_system()
+                .read_file_to_string(&imported.key)
+                .unwrap(),
+            vagrant::INSECURE_KEY
+        );
+    }
+}
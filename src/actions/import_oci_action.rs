@@ -0,0 +1,387 @@
+use crate::commands::Context;
+use crate::error::{Error, FsOperation, Result};
+use crate::models::Arch;
+use crate::oci::{
+    Ext4Image, ImageLayout, ImageReference, ImageSource, LayerFlattener, Manifest, RegistryClient,
+};
+use crate::platform::System;
+use crate::qemu::QemuImg;
+use crate::view::{Console, Spinner, TransferView};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// An index may point to another index, but not endlessly
+const MAX_MANIFEST_DEPTH: usize = 4;
+
+/// A container image turned into a bootable disk, ready to become an instance
+pub struct ImportedOci {
+    pub dir: PathBuf,
+    /// The system disk as qcow2, a single ext4 filesystem
+    pub image: PathBuf,
+    pub kernel: PathBuf,
+    pub initrd: Option<PathBuf>,
+    /// Name of the image, a reference or the layout directory
+    pub name: String,
+}
+
+pub struct ImportOciAction {
+    source: String,
+    arch: Arch,
+    size: u64,
+    kernel: Option<PathBuf>,
+    initrd: Option<PathBuf>,
+}
+
+impl ImportOciAction {
+    /// `source` is an image reference or the path of an OCI layout directory.
+    /// The disk gets a filesystem of `size` bytes.
+    pub fn new(source: &str, arch: Arch, size: u64) -> Self {
+        Self {
+            source: source.to_string(),
+            arch,
+            size,
+            kernel: None,
+            initrd: None,
+        }
+    }
+
+    /// Boots the image with this kernel instead of the one it carries
+    pub fn set_kernel(&mut self, kernel: Option<PathBuf>, initrd: Option<PathBuf>) {
+        self.kernel = kernel;
+        self.initrd = initrd;
+    }
+
+    /// Builds the disk in `<cache>/oci/<instance>.tmp`. The caller removes the
+    /// directory once the instance is created.
+    pub fn run(
+        &self,
+        console: &mut Console<'_>,
+        context: &Context,
+        instance: &str,
+    ) -> Result<ImportedOci> {
+        let system = context.get_system();
+        let cache_dir = Path::new(context.get_env().get_cache_dir()).join("oci");
+        let dir = cache_dir.join(format!("{instance}.tmp"));
+        if system.exists_path(&dir) {
+            system.remove_dir(&dir)?;
+        }
+        system.create_dir(&dir)?;
+
+        let mut source: Box<dyn ImageSource> = if system.exists_dir(Path::new(&self.source)) {
+            Box::new(ImageLayout::new(Path::new(&self.source)))
+        } else {
+            let reference = ImageReference::from_str(&self.source)
+                .map_err(|error| Error::InvalidOciImage(self.source.clone(), error))?;
+            Box::new(RegistryClient::new(&reference, &cache_dir)?)
+        };
+
+        let result = self.import(console, system, source.as_mut(), &dir);
+        if result.is_err() {
+            system.remove_dir(&dir).ok();
+        }
+        result
+    }
+
+    fn import(
+        &self,
+        console: &mut Console<'_>,
+        system: &dyn System,
+        source: &mut dyn ImageSource,
+        dir: &Path,
+    ) -> Result<ImportedOci> {
+        let name = source.get_name();
+        let invalid = |reason: &str| Error::InvalidOciImage(name.clone(), reason.to_string());
+
+        let manifest = self.resolve_manifest(system, source)?;
+        if manifest.layers.is_empty() {
+            return Err(invalid("the image has no layers"));
+        }
+        if manifest.layers.iter().any(|layer| layer.is_zstd()) {
+            return Err(invalid("zstd compressed layers are not supported"));
+        }
+
+        let count = manifest.layers.len();
+        let mut layers = Vec::new();
+        for (index, layer) in manifest.layers.iter().enumerate() {
+            let view = Arc::new(Mutex::new(TransferView::new(&format!(
+                "Downloading layer {}/{count}",
+                index + 1
+            ))));
+            console.play(view.clone());
+            let result = source.fetch_blob(system, &layer.digest, layer.size, view);
+            console.stop();
+            layers.push(result?);
+        }
+
+        console.play(Arc::new(Mutex::new(Spinner::new(
+            "Unpacking layers".to_string(),
+        ))));
+        let result = self.unpack(system, &name, &layers, dir);
+        console.stop();
+        let (kernel, initrd, rootfs) = result?;
+
+        if !rootfs.has_init() {
+            console.warn("The image has no init system, the instance may not boot.");
+        }
+        if !rootfs.has_cloud_init() {
+            console.warn(
+                "The image has no cloud-init, so it sets up neither the user nor the SSH key for `cubic ssh`.",
+            );
+        }
+
+        console.play(Arc::new(Mutex::new(Spinner::new(
+            "Building disk".to_string(),
+        ))));
+        let result = self.build_disk(system, dir);
+        console.stop();
+
+        Ok(ImportedOci {
+            dir: dir.to_path_buf(),
+            image: result?,
+            kernel,
+            initrd,
+            name,
+        })
+    }
+
+    /// Follows indexes down to the image manifest of the arch
+    fn resolve_manifest(
+        &self,
+        system: &dyn System,
+        source: &mut dyn ImageSource,
+    ) -> Result<Manifest> {
+        let name = source.get_name();
+        let parse = |content: Vec<u8>| -> Result<Manifest> {
+            serde_json::from_slice(&content).map_err(|error| {
+                Error::InvalidOciImage(name.clone(), format!("invalid manifest: {error}"))
+            })
+        };
+
+        let mut manifest = parse(source.get_root_manifest(system)?)?;
+        for _ in 0..MAX_MANIFEST_DEPTH {
+            if !manifest.is_index() {
+                return Ok(manifest);
+            }
+            let digest = manifest
+                .select_platform(self.arch)
+                .map(|descriptor| descriptor.digest.clone())
+                .ok_or_else(|| {
+                    Error::InvalidOciImage(
+                        name.clone(),
+                        format!("no image for linux/{}", self.arch.as_vendor_str()),
+                    )
+                })?;
+            manifest = parse(source.get_manifest(system, &digest)?)?;
+        }
+        Err(Error::InvalidOciImage(
+            name.clone(),
+            "too many nested indexes".to_string(),
+        ))
+    }
+
+    /// Merges the layers into `rootfs.tar` and takes the kernel out of the
+    /// image, unless one was given
+    fn unpack(
+        &self,
+        system: &dyn System,
+        name: &str,
+        layers: &[PathBuf],
+        dir: &Path,
+    ) -> Result<(PathBuf, Option<PathBuf>, crate::oci::Rootfs)> {
+        let open = |index: usize| -> io::Result<Box<dyn Read>> {
+            system
+                .open_file(&layers[index])
+                .map_err(|error| io::Error::other(error.to_string()))
+        };
+        let flattener = LayerFlattener::new(layers.len(), &open);
+        let tar = dir.join("rootfs.tar");
+        let read_error = |error| Error::from_fs(FsOperation::ReadFile, &tar, error);
+
+        let mut rootfs = flattener.scan().map_err(read_error)?;
+        if self.kernel.is_some() {
+            // The given kernel wins, so the one of the image stays in place
+            rootfs.kernel = None;
+            rootfs.initrd = None;
+        } else if rootfs.kernel.is_none() {
+            return Err(Error::OciKernelNotFound(name.to_string()));
+        }
+
+        let kernel = dir.join("vmlinuz");
+        let initrd = dir.join("initrd.img");
+        let image_kernel = rootfs.kernel.clone();
+        flattener
+            .write(&rootfs, system.create_file(&tar)?, &mut |path, content| {
+                let target = if Some(path) == image_kernel.as_deref() {
+                    &kernel
+                } else {
+                    &initrd
+                };
+                system
+                    .write_file(target, content)
+                    .map_err(|error| io::Error::other(error.to_string()))
+            })
+            .map_err(|error| Error::from_fs(FsOperation::WriteFile, &tar, error))?;
+
+        if let Some(given) = &self.kernel {
+            Self::copy(system, given, &kernel)?;
+        }
+        let initrd = match (&self.kernel, &self.initrd, &rootfs.initrd) {
+            (Some(_), Some(given), _) => {
+                Self::copy(system, given, &initrd)?;
+                Some(initrd)
+            }
+            (None, _, Some(_)) => Some(initrd),
+            _ => None,
+        };
+        Ok((kernel, initrd, rootfs))
+    }
+
+    fn copy(system: &dyn System, from: &Path, to: &Path) -> Result<()> {
+        io::copy(&mut system.open_file(from)?, &mut system.create_file(to)?)
+            .map(|_| ())
+            .map_err(|error| Error::from_fs(FsOperation::WriteFile, to, error))
+    }
+
+    fn build_disk(&self, system: &dyn System, dir: &Path) -> Result<PathBuf> {
+        let tar = dir.join("rootfs.tar");
+        let raw = dir.join("rootfs.raw");
+        let image = dir.join("rootfs.qcow2");
+
+        Ext4Image::new(system).create(&tar, &raw, self.size)?;
+        // The raw disk goes with the directory, the archive is not needed to
+        // convert it
+        system.remove_file(&tar)?;
+        QemuImg::new(system).import(&raw.to_string_lossy(), "raw", &image.to_string_lossy())?;
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, UserName};
+    use crate::platform::{FileSystem, SystemMock};
+    use crate::util::hex_encode;
+    use sha2::{Digest, Sha256};
+    use std::rc::Rc;
+    use tar::{Builder, Header};
+
+    fn build_layer(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for (name, content) in files {
+            let mut header = Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o755);
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// OCI layout with an index for both arches and a single layer
+    fn build_layout(system: SystemMock, layer: &[u8]) -> SystemMock {
+        let digest = |content: &[u8]| hex_encode(&Sha256::digest(content));
+        let layer_digest = digest(layer);
+        let manifest = format!(
+            r#"{{"schemaVersion": 2, "layers": [{{"mediaType": "application/vnd.oci.image.layer.v1.tar", "digest": "sha256:{layer_digest}", "size": {}}}]}}"#,
+            layer.len()
+        );
+        let manifest_digest = digest(manifest.as_bytes());
+        let index = format!(
+            r#"{{"schemaVersion": 2, "manifests": [
+                {{"digest": "sha256:{manifest_digest}", "size": 1, "platform": {{"architecture": "amd64", "os": "linux"}}}},
+                {{"digest": "sha256:{manifest_digest}", "size": 1, "platform": {{"architecture": "arm64", "os": "linux"}}}}
+            ]}}"#
+        );
+
+        system
+            .add_file("/layout/oci-layout", br#"{"imageLayoutVersion": "1.0.0"}"#)
+            .add_file("/layout/index.json", index.as_bytes())
+            .add_file(
+                &format!("/layout/blobs/sha256/{manifest_digest}"),
+                manifest.as_bytes(),
+            )
+            .add_file(&format!("/layout/blobs/sha256/{layer_digest}"), layer)
+    }
+
+    fn build_context(system: Rc<SystemMock>) -> Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        Context::new(system, env, Box::new(InstanceStoreMock::new(Vec::new())))
+    }
+
+    #[test]
+    fn test_import_layout_with_kernel() {
+        let layer = build_layer(&[
+            ("sbin/init", b""),
+            ("usr/bin/cloud-init", b""),
+            ("boot/vmlinuz-6.1.0-amd64", b"kernel"),
+            ("boot/initrd.img-6.1.0-amd64", b"initrd"),
+        ]);
+        let system = Rc::new(
+            build_layout(SystemMock::new(), &layer)
+                .add_command_output("mke2fs -V", b"mke2fs 1.47.2 (1-Jan-2025)\n")
+                .add_command_output(
+                    "mke2fs -q -F -t ext4 -L cubic-root -d /cache/oci/test.tmp/rootfs.tar /cache/oci/test.tmp/rootfs.raw 1024k",
+                    b"",
+                )
+                .add_command_output(
+                    "qemu-img convert -f raw -O qcow2 /cache/oci/test.tmp/rootfs.raw /cache/oci/test.tmp/rootfs.qcow2",
+                    b"",
+                ),
+        );
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(system.clone());
+
+        let imported = ImportOciAction::new("/layout", Arch::AMD64, 1024 * 1024)
+            .run(console, &context, "test")
+            .unwrap();
+
+        assert_eq!(
+            imported.image,
+            Path::new("/cache/oci/test.tmp/rootfs.qcow2")
+        );
+        assert_eq!(
+            system
+                .get_written_file("/cache/oci/test.tmp/vmlinuz")
+                .unwrap(),
+            b"kernel"
+        );
+        assert_eq!(
+            imported.initrd.as_deref(),
+            Some(Path::new("/cache/oci/test.tmp/initrd.img"))
+        );
+        assert_eq!(system.get_output(), "");
+    }
+
+    #[test]
+    fn test_import_image_without_kernel_fails() {
+        let layer = build_layer(&[("bin/sh", b"")]);
+        let system = Rc::new(build_layout(SystemMock::new(), &layer));
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(system.clone());
+
+        let result = ImportOciAction::new("/layout", Arch::AMD64, 1024 * 1024)
+            .run(console, &context, "test");
+
+        assert!(matches!(result, Err(Error::OciKernelNotFound(name)) if name == "/layout"));
+        assert!(!system.exists_path(Path::new("/cache/oci/test.tmp")));
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_reference() {
+        let system = Rc::new(SystemMock::new());
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(system.clone());
+
+        let result = ImportOciAction::new("Not A Reference", Arch::AMD64, 1024)
+            .run(console, &context, "test");
+
+        assert!(matches!(result, Err(Error::InvalidOciImage(..))));
+    }
+}
@@ -1,15 +1,48 @@
 use crate::cloudinit::CloudInitImageFactory;
 use crate::commands::{Accel, Context};
-use crate::error::{Error, Result};
+use crate::error::{Error, FsOperation, Result};
 use crate::instance::InstanceCertGenerator;
-use crate::models::{Arch, Instance};
+use crate::models::{Arch, DiskEncryption, Environment, Instance, PortForward, ResourceAllocator};
 use crate::platform::System;
 use crate::qemu::{
-    QemuAcceleratorProbe, QemuFirmware, QemuInstall, QemuPathBuilder, QemuSystem, SOFTWARE_ACCEL,
+    FirmwareFiles, QemuAcceleratorProbe, QemuFirmware, QemuInstall, QemuPathBuilder, QemuSystem,
+    SOFTWARE_ACCEL, SPARE_HOTPLUG_PORTS, Swtpm, VNC_BASE_PORT,
 };
 use crate::ssh::PortChecker;
 use crate::view::Console;
-use std::path::PathBuf;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// How long a prompted disk passphrase may stay on disk for QEMU to read it
+const SECRET_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Passphrase file for QEMU, removed when it goes out of scope, so a prompted
+/// passphrase never outlives the start that asked for it
+struct SecretFile<'a> {
+    system: &'a dyn System,
+    path: PathBuf,
+}
+
+impl<'a> SecretFile<'a> {
+    fn write(system: &'a dyn System, path: &Path, secret: &str) -> Result<Self> {
+        // Owned from the start, so a write that fails halfway is cleaned up too
+        let file = Self {
+            system,
+            path: path.to_path_buf(),
+        };
+        system.write_secret_file(path, secret.as_bytes())?;
+        Ok(file)
+    }
+}
+
+impl Drop for SecretFile<'_> {
+    fn drop(&mut self) {
+        self.system.remove_file(&self.path).ok();
+    }
+}
 
 pub struct StartInstanceAction {
     instance: Instance,
@@ -38,6 +71,7 @@ impl StartInstanceAction {
 
         let env = context.get_env();
         let system = context.get_system();
+        self.check_boot_files(system, env)?;
         CloudInitImageFactory.create(system, env, &self.instance)?;
 
         let instance_dir = PathBuf::from(env.get_instance_dir2(&self.instance.name));
@@ -48,6 +82,12 @@ impl StartInstanceAction {
 
         self.instance.monitor_port = Some(system.bind_port()?);
         self.instance.console_port = Some(system.bind_port()?);
+        self.instance.events_port = Some(system.bind_port()?);
+        self.instance.display_port = if self.instance.display {
+            Some(Self::get_display_port(system, system.bind_port()?)?)
+        } else {
+            None
+        };
         context.get_instance_store().store(&self.instance)?;
 
         let mut qemu_system = QemuSystem::from(system, self.instance.arch)?;
@@ -71,10 +111,55 @@ impl StartInstanceAction {
             None => console.debug("No QEMU install found"),
         }
 
-        let firmware = QemuFirmware::locate(system, path_builder.get_dirs(), self.instance.arch)
-            .ok_or(Error::QemuNotFound)?;
-        console.debug(&format!("Using firmware '{}'", firmware.display()));
-        qemu_system.set_firmware(&firmware);
+        let firmware = match &self.instance.firmware {
+            Some(firmware) => FirmwareFiles {
+                code: PathBuf::from(env.get_instance_boot_file(&self.instance.name, firmware)),
+                nvram_template: None,
+            },
+            None => QemuFirmware::locate(
+                system,
+                path_builder.get_dirs(),
+                self.instance.arch,
+                self.instance.secure_boot,
+            )
+            .ok_or_else(|| {
+                if self.instance.secure_boot {
+                    Error::SecureBootFirmwareNotFound(self.instance.name.clone())
+                } else {
+                    Error::QemuNotFound
+                }
+            })?,
+        };
+        console.debug(&format!("Using firmware '{}'", firmware.code.display()));
+        qemu_system.set_firmware(&firmware.code);
+        if let Some(template) = &firmware.nvram_template {
+            let nvram = PathBuf::from(env.get_instance_nvram_file(&self.instance.name));
+            self.prepare_nvram(system, template, &nvram)?;
+            console.debug(&format!("Using NVRAM '{}'", nvram.display()));
+            qemu_system.set_nvram(&nvram);
+        }
+        if self.instance.secure_boot {
+            qemu_system.set_secure_boot();
+        }
+        let swtpm = Swtpm::new(
+            system,
+            Path::new(&env.get_instance_tpm_dir(&self.instance.name)),
+        );
+        if self.instance.tpm {
+            qemu_system.set_tpm(&swtpm.get_socket());
+        }
+        if let Some(kernel) = &self.instance.kernel {
+            let name = &self.instance.name;
+            qemu_system.set_kernel(
+                &env.get_instance_boot_file(name, kernel),
+                self.instance
+                    .initrd
+                    .as_ref()
+                    .map(|initrd| env.get_instance_boot_file(name, initrd))
+                    .as_deref(),
+                self.instance.append.as_deref(),
+            );
+        }
 
         let module_dir = install
             .as_ref()
@@ -98,7 +183,7 @@ impl StartInstanceAction {
         let probe = QemuAcceleratorProbe::new(
             system,
             self.instance.arch,
-            &firmware,
+            &firmware.code,
             module_dir.as_deref(),
             datadir.as_deref(),
         );
@@ -125,11 +210,64 @@ impl StartInstanceAction {
         }
         qemu_system.set_accelerator(accelerator);
 
-        qemu_system.set_cpus(self.instance.cpus);
-        qemu_system.set_memory(self.instance.mem.get_bytes() as u64);
-        qemu_system.set_console(self.instance.console_port.unwrap(), &instance_dir);
-        qemu_system.add_drive(&env.get_instance_image_file(&self.instance.name), "qcow2");
+        let (max_cpus, max_mem) = ResourceAllocator::read_from_host(system).get_hotplug_limits();
+        qemu_system.set_cpus(self.instance.cpus, max_cpus);
+        qemu_system.set_memory(
+            self.instance.mem.get_bytes() as u64,
+            max_mem.get_bytes() as u64,
+        );
+        if self.instance.reclaim_memory {
+            qemu_system.set_memory_reclaim();
+        }
+        let console_log = PathBuf::from(env.get_instance_console_log_file(&self.instance.name));
+        Self::rotate_console_log(system, &console_log)?;
+        qemu_system.set_console(
+            self.instance.console_port.unwrap(),
+            &instance_dir,
+            &console_log,
+        );
+        if let Some(port) = self.instance.display_port {
+            qemu_system.set_display(port, &instance_dir);
+        }
+        let image_file = env.get_instance_image_file(&self.instance.name);
+        let secret_file = env.get_instance_disk_secret_file(&self.instance.name);
+        let settings = &self.instance.disk_settings;
+        if settings.iothread {
+            qemu_system.set_iothread();
+        }
+        let prompted_secret = if self.instance.encryption == Some(DiskEncryption::Prompt) {
+            let passphrase = console
+                .prompt_secret(&format!(
+                    "Enter disk passphrase for {}: ",
+                    self.instance.name
+                ))
+                .map_err(|_| Error::PassphraseCancelled(self.instance.name.clone()))?;
+            Some(SecretFile::write(
+                system,
+                Path::new(&secret_file),
+                &passphrase,
+            )?)
+        } else {
+            None
+        };
+        if let Some(iso) = &self.instance.iso {
+            qemu_system.add_cdrom(&env.get_instance_boot_file(&self.instance.name, iso));
+        }
+        qemu_system.add_system_drive(
+            &image_file,
+            self.instance.encryption.map(|_| secret_file.as_str()),
+            settings,
+        );
         qemu_system.add_drive(&env.get_cloud_init_file(&self.instance.name), "raw");
+        qemu_system.add_hotplug_ports(self.instance.disks.len() + SPARE_HOTPLUG_PORTS);
+        for (port, disk) in self.instance.disks.iter().enumerate() {
+            qemu_system.add_disk(
+                disk,
+                &disk.get_path(env, &self.instance.name),
+                port,
+                settings,
+            );
+        }
         qemu_system.set_network(
             &self.instance.hostfwd,
             self.instance.ssh_port,
@@ -141,12 +279,62 @@ impl StartInstanceAction {
         qemu_system.set_pid_file(&env.get_qemu_pid_file(&self.instance.name));
 
         qemu_system.set_monitor(self.instance.monitor_port.unwrap(), &instance_dir);
+        qemu_system.set_event_monitor(self.instance.events_port.unwrap());
+
+        self.launch(system, console, qemu_system, &swtpm, prompted_secret)?;
+
+        // The running VM keeps the ISO across the reboots of the installer,
+        // and the next start boots the installed system
+        if self.instance.iso.take().is_some() {
+            context.get_instance_store().store(&self.instance)?;
+        }
+        Ok(())
+    }
+
+    // Takes the prompted passphrase file along, so it is gone however the
+    // launch ends
+    fn launch(
+        &self,
+        system: &dyn System,
+        console: &mut Console<'_>,
+        qemu_system: QemuSystem,
+        swtpm: &Swtpm,
+        prompted_secret: Option<SecretFile>,
+    ) -> Result<()> {
+        // QEMU connects to the socket on startup, so swtpm has to be ready
+        // before. It follows QEMU out when the connection closes.
+        if self.instance.tpm {
+            swtpm.start()?;
+        }
 
         let command = qemu_system.build_command();
         console.debug(&command.get_command());
-        system
+        let result = system
             .spawn_command(&command)
-            .map_err(QemuSystem::map_error)
+            .map_err(QemuSystem::map_error);
+        if result.is_err() && self.instance.tpm {
+            swtpm.stop().ok();
+        }
+
+        if prompted_secret.is_some() && result.is_ok() {
+            self.wait_for_monitor(system);
+        }
+        drop(prompted_secret);
+        result
+    }
+
+    // QEMU reads secrets before it opens the monitor, so a monitor that accepts
+    // connections is done with the passphrase file
+    fn wait_for_monitor(&self, system: &dyn System) {
+        let Some(port) = self.instance.monitor_port else {
+            return;
+        };
+        let deadline = Instant::now() + SECRET_READ_TIMEOUT;
+        while system.connect_port(port, Duration::from_secs(1)).is_err()
+            && Instant::now() < deadline
+        {
+            sleep(Duration::from_millis(100));
+        }
     }
 
     // An accelerator runs guest code on the host CPU, so it needs both archs to
@@ -162,6 +350,60 @@ impl StartInstanceAction {
         Ok(())
     }
 
+    // Every instance writes a variable store of its own, which starts out as
+    // a copy of the template of the firmware
+    fn prepare_nvram(&self, system: &dyn System, template: &Path, nvram: &Path) -> Result<()> {
+        if system.exists_path(nvram) {
+            return Ok(());
+        }
+        io::copy(
+            &mut system.open_file(template)?,
+            &mut system.create_file(nvram)?,
+        )
+        .map(|_| ())
+        .map_err(|error| Error::from_fs(FsOperation::WriteFile, nvram, error))
+    }
+
+    // Keeps the log of the previous run, which holds why it ended, and drops
+    // the one before. QEMU holds the current log open for the whole run, so
+    // it only gets moved aside here and grows until the next start.
+    fn rotate_console_log(system: &dyn System, log: &Path) -> Result<()> {
+        if !system.exists_path(log) {
+            return Ok(());
+        }
+        let previous = PathBuf::from(format!("{}.1", log.display()));
+        if system.exists_path(&previous) {
+            system.remove_file(&previous)?;
+        }
+        system.rename_file(log, &previous)
+    }
+
+    // QEMU runs in the background, where a missing file would only show up
+    // as a start timeout
+    fn check_boot_files(&self, system: &dyn System, env: &Environment) -> Result<()> {
+        let instance = &self.instance;
+        let files = [
+            ("kernel", &instance.kernel),
+            ("initrd", &instance.initrd),
+            ("firmware", &instance.firmware),
+            ("iso", &instance.iso),
+        ];
+        for (kind, file) in files {
+            if let Some(file) = file {
+                let path = env.get_instance_boot_file(&instance.name, file);
+                if !system.exists_path(Path::new(&path)) {
+                    return Err(Error::BootFileNotFound(
+                        instance.name.clone(),
+                        kind.to_string(),
+                        path,
+                    ));
+                }
+            }
+        }
+        PortForward::check_count(&instance.name, &instance.hostfwd)?;
+        instance.check_kernel()
+    }
+
     // `on` and `off` overrule the probe and go straight to QEMU. A guest arch
     // that differs from the host arch rules out every hardware accelerator, so
     // it needs no probe.
@@ -196,13 +438,64 @@ impl StartInstanceAction {
     pub fn is_done(&self, system: &dyn System) -> bool {
         PortChecker::new().is_open(system, self.instance.ssh_port)
     }
+
+    // VNC listens on a display number, which counts from port 5900. A free
+    // port below that is swapped for the first free one above.
+    fn get_display_port(system: &dyn System, port: u16) -> Result<u16> {
+        if port >= VNC_BASE_PORT {
+            return Ok(port);
+        }
+        (VNC_BASE_PORT..=u16::MAX)
+            .find_map(|port| {
+                system
+                    .bind_tcp_address(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port))
+                    .ok()
+            })
+            .ok_or(Error::NoPortAvailable)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::platform::SystemMock;
-    use std::path::Path;
+    use crate::models::UserName;
+    use crate::platform::{FileSystem, SystemMock};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_failed_swtpm_start_removes_the_prompted_passphrase() {
+        let system = SystemMock::new();
+        let mut console = Console::new(&system);
+        let action = StartInstanceAction::new(&Instance {
+            name: "test".to_string(),
+            tpm: true,
+            ..Instance::default()
+        });
+        let qemu_system = QemuSystem::from(&system, Arch::AMD64).unwrap();
+        let swtpm = Swtpm::new(&system, Path::new("/data/machines/test/tpm"));
+        let secret_file = Path::new("/data/machines/test/disk_secret");
+        let secret = SecretFile::write(&system, secret_file, "secret").unwrap();
+        assert!(system.exists_path(secret_file));
+
+        let result = action.launch(&system, &mut console, qemu_system, &swtpm, Some(secret));
+
+        assert!(matches!(result, Err(Error::SwtpmNotFound)));
+        assert!(!system.exists_path(secret_file));
+    }
+
+    #[test]
+    fn test_display_port_below_vnc_base_is_reallocated() {
+        let system = SystemMock::new().add_open_port(VNC_BASE_PORT);
+
+        assert_eq!(
+            StartInstanceAction::get_display_port(&system, 5000).unwrap(),
+            VNC_BASE_PORT + 1
+        );
+        assert_eq!(
+            StartInstanceAction::get_display_port(&system, 45901).unwrap(),
+            45901
+        );
+    }
 
     fn build_action(guest_arch: Arch) -> StartInstanceAction {
         StartInstanceAction::new(&Instance {
@@ -233,6 +526,118 @@ mod tests {
         select_for(system, accel, Arch::AMD64, Arch::AMD64)
     }
 
+    fn build_env() -> Environment {
+        Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_check_boot_files_passes_without_boot_files() {
+        let system = SystemMock::new();
+
+        assert!(
+            build_action(Arch::AMD64)
+                .check_boot_files(&system, &build_env())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_check_boot_files_resolves_relative_paths_in_the_instance_dir() {
+        let system = SystemMock::new()
+            .add_file("/data/machines/test/vmlinuz", b"")
+            .add_file("/srv/OVMF.fd", b"");
+        let action = StartInstanceAction::new(&Instance {
+            name: "test".to_string(),
+            kernel: Some("vmlinuz".to_string()),
+            append: Some("console=ttyS0".to_string()),
+            firmware: Some("/srv/OVMF.fd".to_string()),
+            ..Instance::default()
+        });
+
+        assert!(action.check_boot_files(&system, &build_env()).is_ok());
+    }
+
+    #[test]
+    fn test_check_boot_files_reports_missing_file() {
+        let system = SystemMock::new().add_file("/srv/bzImage", b"");
+        let action = StartInstanceAction::new(&Instance {
+            name: "test".to_string(),
+            kernel: Some("/srv/bzImage".to_string()),
+            initrd: Some("/srv/initrd.img".to_string()),
+            ..Instance::default()
+        });
+
+        let result = action.check_boot_files(&system, &build_env());
+
+        assert!(matches!(
+            result,
+            Err(Error::BootFileNotFound(_, ref kind, ref path))
+                if kind == "initrd" && path == "/srv/initrd.img"
+        ));
+    }
+
+    #[test]
+    fn test_check_boot_files_rejects_initrd_without_kernel() {
+        let system = SystemMock::new().add_file("/srv/initrd.img", b"");
+        let action = StartInstanceAction::new(&Instance {
+            name: "test".to_string(),
+            initrd: Some("/srv/initrd.img".to_string()),
+            ..Instance::default()
+        });
+
+        let result = action.check_boot_files(&system, &build_env());
+
+        assert!(matches!(result, Err(Error::KernelRequired(_, ref what)) if what == "initrd"));
+    }
+
+    #[test]
+    fn test_rotate_console_log_keeps_the_previous_run() {
+        let system = SystemMock::new()
+            .add_file("/data/machines/test/console.log", b"second")
+            .add_file("/data/machines/test/console.log.1", b"first");
+
+        StartInstanceAction::rotate_console_log(
+            &system,
+            Path::new("/data/machines/test/console.log"),
+        )
+        .unwrap();
+
+        assert!(!system.exists_path(Path::new("/data/machines/test/console.log")));
+        assert_eq!(
+            system.get_written_file("/data/machines/test/console.log.1"),
+            Some(b"second".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_prepare_nvram_copies_the_template_once() {
+        let system = SystemMock::new().add_file("/usr/share/OVMF/OVMF_VARS_4M.fd", b"vars");
+        let action = build_action(Arch::AMD64);
+        let nvram = Path::new("/data/machines/test/nvram.fd");
+
+        action
+            .prepare_nvram(&system, Path::new("/usr/share/OVMF/OVMF_VARS_4M.fd"), nvram)
+            .unwrap();
+        assert_eq!(
+            system.get_written_file("/data/machines/test/nvram.fd"),
+            Some(b"vars".to_vec())
+        );
+
+        // The guest owns the store from now on
+        system.write_file(nvram, b"boot entries").unwrap();
+        action
+            .prepare_nvram(&system, Path::new("/usr/share/OVMF/OVMF_VARS_4M.fd"), nvram)
+            .unwrap();
+        assert_eq!(
+            system.get_written_file("/data/machines/test/nvram.fd"),
+            Some(b"boot entries".to_vec())
+        );
+    }
+
     #[test]
     fn test_accel_off_runs_on_tcg_without_asking_qemu() {
         let system = SystemMock::new();
@@ -1,15 +1,44 @@
 use crate::error::{Error, Result};
 use crate::instance::InstanceStore;
 use crate::models::Instance;
+use crate::qemu::QemuMonitorClient;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// The escalation steps of a stop, from the gentlest to the most forceful.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopStage {
+    Powerdown,
+    Quit,
+    Kill,
+}
+
+impl fmt::Display for StopStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopStage::Powerdown => write!(f, "guest shutdown"),
+            StopStage::Quit => write!(f, "QEMU quit"),
+            StopStage::Kill => write!(f, "process kill"),
+        }
+    }
+}
 
 pub struct StopInstanceAction {
     instance: Instance,
+    stage: Option<StopStage>,
+    // Kept open after the powerdown request to catch the SHUTDOWN event
+    monitor: Option<QemuMonitorClient>,
 }
 
 impl StopInstanceAction {
     pub fn new(instance: &Instance) -> Self {
         Self {
             instance: instance.clone(),
+            stage: None,
+            monitor: None,
         }
     }
 
@@ -21,14 +50,76 @@ impl StopInstanceAction {
         if instance_dao.is_running(&self.instance) {
             if kill {
                 instance_dao.kill(&self.instance)?;
+                self.stage = Some(StopStage::Kill);
             } else {
-                instance_dao.get_monitor(&self.instance)?.shutdown()?;
+                let mut monitor = instance_dao.get_monitor(&self.instance)?;
+                monitor.shutdown()?;
+                self.monitor = Some(monitor);
+                self.stage = Some(StopStage::Powerdown);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Waits until the instance has stopped or the deadline has passed. A
+    // SHUTDOWN event counts as stopped, since QEMU exits right after it.
+    pub fn wait(&mut self, instance_dao: &dyn InstanceStore, deadline: Instant) -> bool {
+        loop {
+            if self.is_done(instance_dao) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+
+            match self.monitor.as_mut() {
+                Some(monitor) => match monitor.wait_for_event("SHUTDOWN", POLL_INTERVAL) {
+                    Ok(true) => return true,
+                    Ok(false) => {}
+                    // The monitor is gone, so fall back to watching the pid
+                    Err(_) => self.monitor = None,
+                },
+                None => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+
+    // Moves on to the next, more forceful stage. Asking QEMU to quit needs a
+    // working monitor, so without one it goes straight to the kill.
+    pub fn escalate(&mut self, instance_dao: &dyn InstanceStore) -> Result<()> {
+        if self.stage == Some(StopStage::Kill) || self.is_done(instance_dao) {
+            return Ok(());
+        }
+
+        if self.stage != Some(StopStage::Quit) {
+            let quit = match self.monitor.take() {
+                Some(mut monitor) => monitor.quit(),
+                None => instance_dao
+                    .get_monitor(&self.instance)
+                    .and_then(|mut monitor| monitor.quit()),
+            };
+            if quit.is_ok() {
+                self.stage = Some(StopStage::Quit);
+                return Ok(());
             }
         }
 
+        instance_dao.kill(&self.instance)?;
+        self.stage = Some(StopStage::Kill);
         Ok(())
     }
 
+    // The last stage that was applied, which is the one that ended the
+    // instance once it has stopped
+    pub fn get_stage(&self) -> Option<StopStage> {
+        self.stage
+    }
+
+    pub fn get_instance(&self) -> &Instance {
+        &self.instance
+    }
+
     pub fn is_done(&self, instance_dao: &dyn InstanceStore) -> bool {
         !instance_dao.is_running(&self.instance)
     }
@@ -110,4 +201,79 @@ mod tests {
 
         assert!(!StopInstanceAction::new(&instance).is_done(&store));
     }
+
+    #[test]
+    fn test_stop_records_kill_stage() {
+        let instance = build_instance("test");
+        let store = InstanceStoreMock::new_with_running(vec![instance.clone()], &["test"]);
+
+        let mut action = StopInstanceAction::new(&instance);
+        action.run(&store, true).unwrap();
+
+        assert_eq!(action.get_stage(), Some(StopStage::Kill));
+    }
+
+    #[test]
+    fn test_stop_has_no_stage_for_stopped_instance() {
+        let instance = build_instance("test");
+        let store = InstanceStoreMock::new(vec![instance.clone()]);
+
+        let mut action = StopInstanceAction::new(&instance);
+        action.run(&store, false).unwrap();
+
+        assert_eq!(action.get_stage(), None);
+    }
+
+    #[test]
+    fn test_escalate_kills_when_monitor_is_unavailable() {
+        let instance = build_instance("test");
+        let store = InstanceStoreMock::new_with_running(vec![instance.clone()], &["test"]);
+
+        // The mock has no monitor, so the quit stage cannot be reached and
+        // the escalation goes straight to the kill.
+        let mut action = StopInstanceAction::new(&instance);
+        action.escalate(&store).unwrap();
+
+        assert_eq!(action.get_stage(), Some(StopStage::Kill));
+        assert_eq!(*store.killed.lock().unwrap(), ["test"]);
+    }
+
+    #[test]
+    fn test_escalate_does_not_kill_twice() {
+        let instance = build_instance("test");
+        let store = InstanceStoreMock::new_with_running(vec![instance.clone()], &["test"]);
+
+        let mut action = StopInstanceAction::new(&instance);
+        action.run(&store, true).unwrap();
+        action.escalate(&store).unwrap();
+
+        assert_eq!(*store.killed.lock().unwrap(), ["test"]);
+    }
+
+    #[test]
+    fn test_escalate_skips_stopped_instance() {
+        let instance = build_instance("test");
+        let store = InstanceStoreMock::new(vec![instance.clone()]);
+
+        let mut action = StopInstanceAction::new(&instance);
+        action.escalate(&store).unwrap();
+
+        assert!(store.killed.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wait_returns_once_instance_is_stopped() {
+        let instance = build_instance("test");
+        let store = InstanceStoreMock::new(vec![instance.clone()]);
+
+        assert!(StopInstanceAction::new(&instance).wait(&store, Instant::now()));
+    }
+
+    #[test]
+    fn test_wait_gives_up_at_deadline() {
+        let instance = build_instance("test");
+        let store = InstanceStoreMock::new_with_running(vec![instance.clone()], &["test"]);
+
+        assert!(!StopInstanceAction::new(&instance).wait(&store, Instant::now()));
+    }
 }
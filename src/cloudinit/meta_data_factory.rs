@@ -14,7 +14,7 @@ mod tests {
     #[test]
     fn test_meta_user_data() {
         assert_eq!(
-            &MetaDataFactory::default().create("myinstance"),
+            &MetaDataFactory.create("myinstance"),
             "instance-id: myinstance\nlocal-hostname: myinstance\n"
         );
     }
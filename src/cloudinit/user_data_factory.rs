@@ -43,8 +43,7 @@ mod tests {
 
     #[test]
     fn test_write_user_data_without_execute() {
-        let actual =
-            UserDataFactory::default().create(&UserName::from_str("tux").unwrap(), "pubkey", None);
+        let actual = UserDataFactory.create(&UserName::from_str("tux").unwrap(), "pubkey", None);
         let expected = r#"#cloud-config
 users:
   - name: tux
@@ -64,7 +63,7 @@ write_files:
 
     #[test]
     fn test_write_user_data_with_execute() {
-        let actual = UserDataFactory::default().create(
+        let actual = UserDataFactory.create(
             &UserName::from_str("tux").unwrap(),
             "pubkey",
             Some("\"sudo apt install vim\""),
@@ -90,7 +89,7 @@ bootcmd:
 
     #[test]
     fn test_write_user_data_escapes_execute() {
-        let actual = UserDataFactory::default().create(
+        let actual = UserDataFactory.create(
             &UserName::from_str("tux").unwrap(),
             "pubkey",
             Some("a\\b\t\"c\"\nd\re"),
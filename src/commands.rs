@@ -1,66 +1,110 @@
 mod accel_arg;
+mod add_disk_command;
 mod all_images_arg;
 mod all_info_arg;
 mod all_instances_arg;
+mod backup_command;
+mod boot_arg;
 mod clone_command;
 mod command_dispatcher;
+mod commit_command;
+mod compact_disk_command;
 mod completions_command;
 mod console_command;
 mod context;
 mod create_command;
 mod delete_command;
+mod disk_command;
+mod disk_settings_arg;
+mod display_command;
 mod env_args;
+mod events_command;
 mod exec_command;
+mod export_disk_command;
 mod image;
 mod instance_arg;
+mod list_backup_command;
+mod list_disk_command;
 mod list_image_command;
 mod list_instance_command;
 mod list_port_command;
+mod logs_command;
 mod modify_command;
+mod monitor_command;
 mod prune_command;
+mod qmp_command;
+mod record_arg;
+mod remove_disk_command;
 mod rename_command;
+mod replay_command;
 mod restart_command;
+mod restore_command;
 mod run_command;
 mod scp_command;
+mod screenshot_command;
+mod sendkey_command;
 mod show_command;
 mod show_image_command;
 mod show_instance_command;
 mod ssh_command;
 mod start_command;
 mod stop_command;
+mod type_command;
 mod verbosity;
 mod yes_arg;
 
 pub use accel_arg::*;
+pub use add_disk_command::*;
 pub use all_images_arg::*;
 pub use all_info_arg::*;
 pub use all_instances_arg::*;
+pub use backup_command::*;
+pub use boot_arg::*;
 pub use clone_command::*;
 pub use command_dispatcher::*;
+pub use commit_command::*;
+pub use compact_disk_command::*;
 pub use completions_command::*;
 pub use console_command::*;
 pub use context::*;
 pub use create_command::*;
 pub use delete_command::*;
+pub use disk_command::*;
+pub use disk_settings_arg::*;
+pub use display_command::*;
 pub use env_args::*;
+pub use events_command::*;
 pub use exec_command::*;
+pub use export_disk_command::*;
 pub use image::*;
 pub use instance_arg::*;
+pub use list_backup_command::*;
+pub use list_disk_command::*;
 pub use list_image_command::*;
 pub use list_instance_command::*;
 pub use list_port_command::*;
+pub use logs_command::*;
 pub use modify_command::*;
+pub use monitor_command::*;
 pub use prune_command::*;
+pub use qmp_command::*;
+pub use record_arg::*;
+pub use remove_disk_command::*;
 pub use rename_command::*;
+pub use replay_command::*;
 pub use restart_command::*;
+pub use restore_command::*;
 pub use run_command::*;
 pub use scp_command::*;
+pub use screenshot_command::*;
+pub use sendkey_command::*;
 pub use show_command::*;
 pub use show_image_command::*;
 pub use show_instance_command::*;
 pub use ssh_command::*;
 pub use start_command::*;
 pub use stop_command::*;
+pub use type_command::*;
 pub use verbosity::*;
 pub use yes_arg::*;
 
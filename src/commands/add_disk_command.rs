@@ -0,0 +1,211 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::models::{DataSize, Disk, DiskName};
+use crate::qemu::QemuImg;
+use crate::view::Console;
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+/// Add a data disk to a VM instance
+///
+/// Creates a new qcow2 disk in the instance directory or attaches an existing
+/// disk image. The disk is attached right away if the instance is running, up
+/// to four more disks than the instance was started with. Any further disk is
+/// attached on the next restart.
+#[derive(Parser)]
+pub struct AddDiskCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Name of the disk
+    name: DiskName,
+    /// Size of a new disk (e.g. 20G for 20 gigabytes)
+    #[clap(short, long, required_unless_present = "file", conflicts_with = "file")]
+    size: Option<DataSize>,
+    /// Attach an existing disk image instead of creating a new disk
+    #[clap(short, long)]
+    file: Option<PathBuf>,
+}
+
+impl Command for AddDiskCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let env = context.get_env();
+        let system = context.get_system();
+        let instance_store = context.get_instance_store();
+        let mut instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if instance
+            .disks
+            .iter()
+            .any(|disk| disk.name == self.name.as_str())
+        {
+            return Err(Error::DiskAlreadyExists(
+                instance.name,
+                self.name.to_string(),
+            ));
+        }
+
+        let qemu_img = QemuImg::new(system);
+        let disk = match &self.file {
+            Some(file) => {
+                // QEMU runs from another directory, so keep the full path
+                let file = std::path::absolute(file).map_err(Error::from)?;
+                if !system.exists_path(&file) {
+                    return Err(Error::InvalidPath(file.display().to_string()));
+                }
+                let file = file.to_string_lossy().into_owned();
+                let format = qemu_img
+                    .get_file_info(&file)
+                    .map(|info| info.format)
+                    .filter(|format| !format.is_empty())
+                    .unwrap_or_else(|| "raw".to_string());
+                Disk {
+                    name: self.name.to_string(),
+                    format,
+                    file: Some(file),
+                }
+            }
+            None => {
+                let disk = Disk {
+                    name: self.name.to_string(),
+                    format: "qcow2".to_string(),
+                    file: None,
+                };
+                let size = self.size.as_ref().map_or(0, DataSize::get_bytes);
+                qemu_img.create(&disk.get_path(env, &instance.name), size as u64)?;
+                disk
+            }
+        };
+
+        // Recorded first, so a disk that cannot be attached now still comes
+        // up on the next start
+        instance.disks.push(disk.clone());
+        instance_store.store(&instance)?;
+
+        if instance_store.is_running(&instance) {
+            let path = disk.get_path(env, &instance.name);
+            instance_store.get_monitor(&instance)?.attach_disk(
+                &instance.name,
+                &disk,
+                &path,
+                &instance.disk_settings,
+            )?;
+        }
+
+        console.info(&format!(
+            "Added disk '{}' at {}",
+            disk.name,
+            Path::new(&disk.get_path(env, &instance.name)).display()
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_context(system: SystemMock, instance_store: InstanceStoreMock) -> commands::Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        commands::Context::new(Rc::new(system), env, Box::new(instance_store))
+    }
+
+    fn build_instance(disks: Vec<Disk>) -> Instance {
+        Instance {
+            name: "test".to_string(),
+            disks,
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_reject_path_traversal() {
+        assert!(AddDiskCommand::try_parse_from(["add", "test", "../etc", "-s", "1G"]).is_err());
+    }
+
+    #[test]
+    fn test_require_size_or_file() {
+        assert!(AddDiskCommand::try_parse_from(["add", "test", "data"]).is_err());
+    }
+
+    #[test]
+    fn test_reject_existing_disk_name() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(
+            SystemMock::new(),
+            InstanceStoreMock::new(vec![build_instance(vec![Disk {
+                name: "data".to_string(),
+                format: "qcow2".to_string(),
+                file: None,
+            }])]),
+        );
+
+        let result = AddDiskCommand::try_parse_from(["add", "test", "data", "-s", "1G"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(
+            result,
+            Err(Error::DiskAlreadyExists(ref instance, ref disk))
+                if instance == "test" && disk == "data"
+        ));
+    }
+
+    #[test]
+    fn test_create_new_disk_and_store_it() {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let path = env.get_instance_disk_file("test", "data");
+        let system = SystemMock::new()
+            .add_command_output(&format!("qemu-img create -f qcow2 {path} 1073741824"), b"");
+        let console_system = SystemMock::new();
+        let console = &mut Console::new(&console_system);
+        let store = InstanceStoreMock::new(vec![build_instance(Vec::new())]);
+        let stored = store.stored.clone();
+        let context = build_context(system, store);
+
+        AddDiskCommand::try_parse_from(["add", "test", "data", "-s", "1G"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert_eq!(
+            stored.lock().unwrap()[0].disks,
+            [Disk {
+                name: "data".to_string(),
+                format: "qcow2".to_string(),
+                file: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reject_missing_file() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(
+            SystemMock::new(),
+            InstanceStoreMock::new(vec![build_instance(Vec::new())]),
+        );
+
+        let result = AddDiskCommand::try_parse_from(["add", "test", "data", "-f", "/nowhere.img"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InvalidPath(_))));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::actions::{BackupInstanceAction, LoadInstanceAction};
+use crate::commands::{self, Command};
+use crate::error::Result;
+use crate::models::InstanceName;
+use crate::view::{Console, Spinner};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    Ls(commands::ListBackupCommand),
+}
+
+/// Back up the system disk of VM instances
+///
+/// The first backup of an instance copies the whole disk. While the instance
+/// keeps running, every further backup only holds the blocks written since the
+/// one before. Backing up a stopped instance always copies the whole disk.
+/// Backups of an instance are kept in a directory named after it, each along
+/// with the config, the kernel, the firmware variables and the TPM state of the
+/// instance at the time.
+///
+/// Examples:
+///
+///   Back up 'my-instance' to an external drive:
+///   $ cubic backup my-instance /mnt/backup
+///
+///   List the backups on the drive:
+///   $ cubic backup ls /mnt/backup
+///   Instance      Backup       Time                   Type              Size   File
+///   my-instance   1760870000   2025-10-19T10:33:20Z   full           2.1 GiB   /mnt/backup/my-instance/1760870000-full.qcow2
+///   my-instance   1760873600   2025-10-19T11:33:20Z   incremental   48.0 MiB   /mnt/backup/my-instance/1760873600-inc.qcow2
+///
+///   Restore the latest backup as the new instance 'restored':
+///   $ cubic restore /mnt/backup my-instance restored
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct BackupCommand {
+    #[command(subcommand)]
+    command: Option<BackupCommands>,
+
+    /// Name of the virtual machine instance
+    #[clap(required = true)]
+    instance: Option<InstanceName>,
+    /// Directory to store the backups in
+    #[clap(required = true)]
+    dir: Option<PathBuf>,
+}
+
+impl Command for BackupCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        if let Some(BackupCommands::Ls(cmd)) = &self.command {
+            return cmd.run(console, context);
+        }
+
+        let (Some(name), Some(dir)) = (&self.instance, &self.dir) else {
+            return Ok(());
+        };
+
+        let instance = LoadInstanceAction::new().run(context, console, name.as_str())?;
+
+        console.play(Arc::new(Mutex::new(Spinner::new(format!(
+            "Backing up {}",
+            instance.name
+        )))));
+        let result = BackupInstanceAction::new(&instance).run(context, dir);
+        console.stop();
+        let backup = result?;
+
+        console.info(&format!(
+            "Created {} backup {}",
+            backup.kind,
+            backup.path.display()
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backup() {
+        let cmd = BackupCommand::try_parse_from(["backup", "test", "/backup"]).unwrap();
+        assert!(cmd.command.is_none());
+        assert_eq!(cmd.instance.unwrap().as_str(), "test");
+        assert_eq!(cmd.dir, Some(PathBuf::from("/backup")));
+    }
+
+    #[test]
+    fn test_parse_backup_ls() {
+        let cmd = BackupCommand::try_parse_from(["backup", "ls", "/backup"]).unwrap();
+        assert!(matches!(cmd.command, Some(BackupCommands::Ls(_))));
+    }
+
+    #[test]
+    fn test_parse_backup_without_dir() {
+        assert!(BackupCommand::try_parse_from(["backup", "test"]).is_err());
+    }
+}
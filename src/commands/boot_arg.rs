@@ -0,0 +1,244 @@
+use crate::error::{Error, Result};
+use crate::models::Instance;
+use crate::platform::System;
+use clap::{ArgAction, Parser};
+use std::path::{Path, PathBuf};
+
+#[derive(Default, Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct BootArg {
+    /// Kernel to boot directly instead of the boot loader on the disk
+    #[clap(long, value_name = "FILE", conflicts_with = "no_kernel")]
+    pub kernel: Option<PathBuf>,
+    /// Boot from the disk again, which also drops the initrd and the command line
+    #[clap(long, action = ArgAction::SetTrue, conflicts_with_all = ["initrd", "append"])]
+    pub no_kernel: bool,
+    /// Initrd of the kernel
+    #[clap(long, value_name = "FILE", conflicts_with = "no_initrd")]
+    pub initrd: Option<PathBuf>,
+    /// Boot the kernel without an initrd
+    #[clap(long, action = ArgAction::SetTrue)]
+    pub no_initrd: bool,
+    /// Kernel command line (e.g. "root=/dev/vda1 console=ttyS0")
+    #[clap(long, value_name = "CMDLINE", conflicts_with = "no_append")]
+    pub append: Option<String>,
+    /// Boot the kernel without a command line
+    #[clap(long, action = ArgAction::SetTrue)]
+    pub no_append: bool,
+    /// Firmware to boot in place of the one that comes with QEMU
+    #[clap(long, value_name = "FILE", conflicts_with = "no_firmware")]
+    pub firmware: Option<PathBuf>,
+    /// Boot the firmware that comes with QEMU (default)
+    #[clap(long, action = ArgAction::SetTrue)]
+    pub no_firmware: bool,
+    /// Boot a UEFI firmware with Secure Boot and the Microsoft keys enrolled
+    #[clap(long, overrides_with = "no_secure_boot", action = ArgAction::SetTrue)]
+    pub secure_boot: bool,
+    /// Boot a UEFI firmware without Secure Boot (default)
+    #[clap(long, overrides_with = "secure_boot", action = ArgAction::SetTrue)]
+    pub no_secure_boot: bool,
+    /// Installer ISO to boot from until the VM shuts down
+    #[clap(long, value_name = "FILE", conflicts_with = "no_iso")]
+    pub iso: Option<PathBuf>,
+    /// Detach the installer ISO before the next start
+    #[clap(long, action = ArgAction::SetTrue)]
+    pub no_iso: bool,
+    /// Add a graphics card, shown with `cubic display`
+    #[clap(long, overrides_with = "no_display", action = ArgAction::SetTrue)]
+    pub display: bool,
+    /// Run without a graphics card (default)
+    #[clap(long, overrides_with = "display", action = ArgAction::SetTrue)]
+    pub no_display: bool,
+    /// Attach a software TPM 2.0 (needs swtpm)
+    #[clap(long, overrides_with = "no_tpm", action = ArgAction::SetTrue)]
+    pub tpm: bool,
+    /// Detach the TPM and keep its state for later (default)
+    #[clap(long, overrides_with = "tpm", action = ArgAction::SetTrue)]
+    pub no_tpm: bool,
+}
+
+impl BootArg {
+    /// Overrides the boot files that were given and keeps the others
+    pub fn apply(&self, system: &dyn System, instance: &mut Instance) -> Result<()> {
+        if let Some(kernel) = &self.kernel {
+            instance.kernel = Some(Self::get_path(system, &instance.name, "kernel", kernel)?);
+        } else if self.no_kernel {
+            instance.kernel = None;
+            instance.initrd = None;
+            instance.append = None;
+        }
+        if let Some(initrd) = &self.initrd {
+            instance.initrd = Some(Self::get_path(system, &instance.name, "initrd", initrd)?);
+        } else if self.no_initrd {
+            instance.initrd = None;
+        }
+        if let Some(append) = &self.append {
+            instance.append = Some(append.clone());
+        } else if self.no_append {
+            instance.append = None;
+        }
+        if let Some(firmware) = &self.firmware {
+            instance.firmware = Some(Self::get_path(
+                system,
+                &instance.name,
+                "firmware",
+                firmware,
+            )?);
+        } else if self.no_firmware {
+            instance.firmware = None;
+        }
+        if self.secure_boot {
+            instance.secure_boot = true;
+        } else if self.no_secure_boot {
+            instance.secure_boot = false;
+        }
+        if let Some(iso) = &self.iso {
+            instance.iso = Some(Self::get_path(system, &instance.name, "iso", iso)?);
+        } else if self.no_iso {
+            instance.iso = None;
+        }
+        if self.display {
+            instance.display = true;
+        } else if self.no_display {
+            instance.display = false;
+        }
+        if self.tpm {
+            instance.tpm = true;
+        } else if self.no_tpm {
+            instance.tpm = false;
+        }
+
+        instance.check_kernel()
+    }
+
+    /// Full path of a boot file. QEMU runs from another directory, and a
+    /// relative path in the config points into the instance directory.
+    pub fn get_path(
+        system: &dyn System,
+        instance: &str,
+        kind: &str,
+        file: &Path,
+    ) -> Result<String> {
+        let file = std::path::absolute(file).map_err(Error::from)?;
+        if !system.exists_path(&file) {
+            return Err(Error::BootFileNotFound(
+                instance.to_string(),
+                kind.to_string(),
+                file.display().to_string(),
+            ));
+        }
+        Ok(file.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::SystemMock;
+
+    fn build_instance() -> Instance {
+        Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        }
+    }
+
+    fn apply(system: &SystemMock, instance: &mut Instance, args: &[&str]) -> Result<()> {
+        BootArg::try_parse_from([&["test"], args].concat())
+            .unwrap()
+            .apply(system, instance)
+    }
+
+    #[test]
+    fn test_apply_sets_kernel_initrd_and_append() {
+        let system = SystemMock::new()
+            .add_file("/src/linux/bzImage", b"")
+            .add_file("/src/linux/initrd.img", b"");
+        let mut instance = build_instance();
+
+        apply(
+            &system,
+            &mut instance,
+            &[
+                "--kernel",
+                "/src/linux/bzImage",
+                "--initrd",
+                "/src/linux/initrd.img",
+                "--append",
+                "root=/dev/vda1",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(instance.kernel.as_deref(), Some("/src/linux/bzImage"));
+        assert_eq!(instance.initrd.as_deref(), Some("/src/linux/initrd.img"));
+        assert_eq!(instance.append.as_deref(), Some("root=/dev/vda1"));
+    }
+
+    #[test]
+    fn test_apply_reports_missing_file() {
+        let system = SystemMock::new();
+        let mut instance = build_instance();
+
+        let result = apply(&system, &mut instance, &["--firmware", "/srv/OVMF.fd"]);
+
+        assert!(matches!(
+            result,
+            Err(Error::BootFileNotFound(_, ref kind, _)) if kind == "firmware"
+        ));
+        assert_eq!(instance.firmware, None);
+    }
+
+    #[test]
+    fn test_apply_rejects_initrd_without_kernel() {
+        let system = SystemMock::new().add_file("/src/linux/initrd.img", b"");
+        let mut instance = build_instance();
+
+        let result = apply(
+            &system,
+            &mut instance,
+            &["--initrd", "/src/linux/initrd.img"],
+        );
+
+        assert!(matches!(result, Err(Error::KernelRequired(_, ref what)) if what == "initrd"));
+    }
+
+    #[test]
+    fn test_no_kernel_drops_initrd_and_append() {
+        let system = SystemMock::new();
+        let mut instance = Instance {
+            kernel: Some("vmlinuz".to_string()),
+            initrd: Some("initrd.img".to_string()),
+            append: Some("rw".to_string()),
+            firmware: Some("/srv/OVMF.fd".to_string()),
+            ..build_instance()
+        };
+
+        apply(&system, &mut instance, &["--no-kernel"]).unwrap();
+
+        assert_eq!(instance.kernel, None);
+        assert_eq!(instance.initrd, None);
+        assert_eq!(instance.append, None);
+        assert_eq!(instance.firmware.as_deref(), Some("/srv/OVMF.fd"));
+    }
+
+    #[test]
+    fn test_last_tpm_flag_wins() {
+        let system = SystemMock::new();
+        let mut instance = build_instance();
+
+        apply(&system, &mut instance, &["--no-tpm", "--tpm"]).unwrap();
+        assert!(instance.tpm);
+
+        apply(&system, &mut instance, &["--tpm", "--no-tpm"]).unwrap();
+        assert!(!instance.tpm);
+    }
+
+    #[test]
+    fn test_reject_no_kernel_with_initrd() {
+        assert!(
+            BootArg::try_parse_from(["test", "--no-kernel", "--initrd", "/srv/initrd.img"])
+                .is_err()
+        );
+    }
+}
@@ -1,9 +1,12 @@
 use crate::actions::{CreateInstanceAction, LoadInstanceAction};
 use crate::commands::{Command, Context};
-use crate::error::{Error, Result};
+use crate::error::{Error, FsOperation, Result};
 use crate::models::{InstanceName, LOW_DISK_SPACE_WARNING, ResourceAllocator};
+use crate::qemu::{QemuImg, Swtpm};
 use crate::view::{Console, Spinner};
 use clap::Parser;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 /// Clone VM instances
@@ -43,6 +46,22 @@ impl Command for CloneCommand {
             return Err(Error::InstanceNotStopped(source.name.to_string()));
         }
 
+        // The clone would need a passphrase of its own
+        if source.encryption.is_some() {
+            return Err(Error::EncryptedDiskNotSupported(
+                source.name.to_string(),
+                "clone".to_string(),
+            ));
+        }
+
+        // Two instances writing to the same disk image would corrupt it
+        for disk in source.disks.iter().filter(|disk| !disk.is_owned()) {
+            console.warn(&format!(
+                "The attached disk '{}' is not cloned. Attach it to {} with cubic disk add",
+                disk.name, self.new_name
+            ));
+        }
+
         console.play(Arc::new(Mutex::new(Spinner::new(format!(
             "Cloning {} to {}",
             self.name, self.new_name
@@ -60,9 +79,61 @@ impl Command for CloneCommand {
         // The clone gets its own cloud-init seed, so the guest generates new
         // host keys on the first boot.
         target.ssh_host_key = None;
+        target.disks.retain(|disk| disk.is_owned());
 
         // Create VM instance
-        CreateInstanceAction::new().run(context, image_path, target)?;
+        CreateInstanceAction::new().run(context, Some(image_path), target.clone())?;
+
+        let env = context.get_env();
+        let qemu_img = QemuImg::new(context.get_system());
+        for disk in &target.disks {
+            qemu_img.convert(
+                &disk.get_path(env, &source.name),
+                &disk.get_path(env, &target.name),
+            )?;
+        }
+
+        // A kernel of its own, as of an instance built from a container image,
+        // and the boot entries of the firmware
+        let system = context.get_system();
+        let mut files: Vec<(String, String)> = source
+            .get_owned_boot_files()?
+            .into_iter()
+            .map(|file| {
+                (
+                    env.get_instance_boot_file(&source.name, file),
+                    env.get_instance_boot_file(&target.name, file),
+                )
+            })
+            .collect();
+        let nvram = env.get_instance_nvram_file(&source.name);
+        if system.exists_path(Path::new(&nvram)) {
+            files.push((nvram, env.get_instance_nvram_file(&target.name)));
+        }
+        // The TPM holds the secrets the guest sealed, e.g. disk keys
+        let tpm_dir = env.get_instance_tpm_dir(&target.name);
+        let tpm_files = Swtpm::new(system, Path::new(&env.get_instance_tpm_dir(&source.name)))
+            .get_state_files();
+        if !tpm_files.is_empty() {
+            system.create_dir(Path::new(&tpm_dir))?;
+        }
+        for file in tpm_files {
+            if let Some(name) = file.file_name() {
+                let to = Path::new(&tpm_dir).join(name);
+                files.push((
+                    file.to_string_lossy().into_owned(),
+                    to.to_string_lossy().into_owned(),
+                ));
+            }
+        }
+        for (from, to) in files {
+            let to = PathBuf::from(to);
+            io::copy(
+                &mut system.open_file(Path::new(&from))?,
+                &mut system.create_file(&to)?,
+            )
+            .map_err(|error| Error::from_fs(FsOperation::WriteFile, &to, error))?;
+        }
 
         console.stop();
         Ok(())
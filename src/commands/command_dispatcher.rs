@@ -16,15 +16,29 @@ pub enum Commands {
     Ports(commands::ListPortCommand),
     Show(commands::ShowCommand),
     Modify(commands::ModifyCommand),
+    Disk(commands::DiskCommand),
+    Backup(commands::BackupCommand),
+    Restore(commands::RestoreCommand),
+    ExportDisk(commands::ExportDiskCommand),
     Console(commands::ConsoleCommand),
+    Logs(commands::LogsCommand),
+    Display(commands::DisplayCommand),
+    Screenshot(commands::ScreenshotCommand),
+    Sendkey(commands::SendkeyCommand),
+    Type(commands::TypeCommand),
+    Events(commands::EventsCommand),
+    Monitor(commands::MonitorCommand),
+    Qmp(commands::QmpCommand),
     Ssh(commands::SshCommand),
     Scp(commands::ScpCommand),
     Exec(commands::ExecCommand),
+    Replay(commands::ReplayCommand),
     Start(commands::StartCommand),
     Stop(commands::StopCommand),
     Restart(commands::RestartCommand),
     Rename(commands::RenameCommand),
     Clone(commands::CloneCommand),
+    Commit(commands::CommitCommand),
     Delete(commands::DeleteCommand),
     Prune(commands::PruneCommand),
     Completions(commands::CompletionsCommand),
@@ -121,16 +135,30 @@ impl CommandDispatcher {
             Commands::Ports(cmd) => cmd,
             Commands::Create(cmd) => cmd,
             Commands::Modify(cmd) => cmd,
+            Commands::Disk(cmd) => cmd,
+            Commands::Backup(cmd) => cmd,
+            Commands::Restore(cmd) => cmd,
+            Commands::ExportDisk(cmd) => cmd,
             Commands::Clone(cmd) => cmd,
+            Commands::Commit(cmd) => cmd,
             Commands::Rename(cmd) => cmd,
             Commands::Show(cmd) => cmd,
             Commands::Start(cmd) => cmd,
             Commands::Stop(cmd) => cmd,
             Commands::Restart(cmd) => cmd,
             Commands::Console(cmd) => cmd,
+            Commands::Logs(cmd) => cmd,
+            Commands::Display(cmd) => cmd,
+            Commands::Screenshot(cmd) => cmd,
+            Commands::Sendkey(cmd) => cmd,
+            Commands::Type(cmd) => cmd,
+            Commands::Events(cmd) => cmd,
+            Commands::Monitor(cmd) => cmd,
+            Commands::Qmp(cmd) => cmd,
             Commands::Ssh(cmd) => cmd,
             Commands::Scp(cmd) => cmd,
             Commands::Exec(cmd) => cmd,
+            Commands::Replay(cmd) => cmd,
             Commands::Delete(cmd) => cmd,
             Commands::Prune(cmd) => cmd,
             Commands::Completions(cmd) => cmd,
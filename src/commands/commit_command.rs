@@ -0,0 +1,245 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::image::LocalImages;
+use crate::models::{HashAlg, Image, ImageName, Instance, TimeSpan};
+use crate::qemu::QemuImg;
+use crate::ssh::SshClient;
+use crate::util;
+use crate::view::{Console, Spinner};
+use clap::Parser;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+// Makes cloud-init run again on the first boot of every new instance and
+// gives each of them a machine ID of its own
+const CLOUD_INIT_CLEAN_CMD: &str = "sudo cloud-init clean --logs --machine-id";
+
+/// Save a VM instance as a local image
+///
+/// Cleans the cloud-init state of the instance, so new instances run their
+/// own setup, and copies its disk into the image cache. The instance is
+/// started for the cleanup and stopped afterwards. A local image takes
+/// precedence over a downloadable image of the same name.
+///
+/// Examples:
+///
+///   Save the VM instance 'dev' as the image 'debian:tools':
+///   $ cubic commit dev debian:tools
+///
+///   Create a new VM instance from the image:
+///   $ cubic create dev2 --image debian:tools
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct CommitCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Name of the image (format: vendor:name, e.g. 'debian:tools')
+    image: ImageName,
+}
+
+impl Command for CommitCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        // The image cache holds no encryption, so the data would end up in
+        // the clear
+        if instance.encryption.is_some() {
+            return Err(Error::EncryptedDiskNotSupported(
+                instance.name,
+                "commit".to_string(),
+            ));
+        }
+
+        self.clean_cloud_init(console, context, &instance)?;
+
+        console.play(Arc::new(Mutex::new(Spinner::new(format!(
+            "Saving {} as {}:{}",
+            instance.name,
+            self.image.get_vendor(),
+            self.image.get_name()
+        )))));
+        let result = self.save_image(context, &instance);
+        console.stop();
+        let image = result?;
+
+        console.info(&format!(
+            "Saved {} as local image {}",
+            instance.name,
+            image.get_image_names()
+        ));
+        Ok(())
+    }
+}
+
+impl CommitCommand {
+    // Boots the instance to reset cloud-init in the guest. The instance is
+    // stopped again either way, because the disk must be at rest to copy it.
+    fn clean_cloud_init(
+        &self,
+        console: &mut Console<'_>,
+        context: &commands::Context,
+        instance: &Instance,
+    ) -> Result<()> {
+        let env = context.get_env();
+        commands::StartCommand {
+            qemu_args: None,
+            accel: commands::AccelArg::default(),
+            wait: true,
+            yes: commands::YesArg { value: false },
+            instances: self.instance.value.clone().into(),
+        }
+        .run(console, context)?;
+
+        let mut ssh = SshClient::new(context);
+        ssh.set_private_keys(env.get_home_ssh_private_key_paths(context.get_system()));
+        ssh.set_cmd(Some(CLOUD_INIT_CLEAN_CMD.to_string()));
+        let async_caller = util::AsyncCaller::new();
+        let result = async_caller
+            .call(ssh.open_channel(
+                console,
+                &instance.name,
+                &env.get_ssh_private_key_file(&instance.name),
+                instance.user.as_str(),
+                instance.ssh_port,
+            ))
+            .and_then(|channel| async_caller.call(ssh.output(&instance.name, channel)));
+
+        commands::StopCommand {
+            all: false.into(),
+            wait: true,
+            kill: false,
+            timeout: Some(TimeSpan::from_str(commands::DEFAULT_STOP_TIMEOUT).unwrap()),
+            instances: self.instance.value.clone().into(),
+        }
+        .run(console, context)?;
+
+        result.map(|_| ())
+    }
+
+    // Flattens the disk into the image cache and registers the image
+    fn save_image(&self, context: &commands::Context, instance: &Instance) -> Result<Image> {
+        let env = context.get_env();
+        let system = context.get_system();
+        let mut image = Image {
+            vendor: self.image.get_vendor().to_string(),
+            names: vec![self.image.get_name().to_string()],
+            arch: instance.arch,
+            image_url: String::new(),
+            checksum_url: String::new(),
+            hash_alg: HashAlg::Sha256,
+            size: None,
+            local: true,
+        };
+        let image_file = env.get_image_file(&image.to_file_name());
+        let tmp_file = format!("{image_file}.tmp");
+
+        system.create_writable_dir(Path::new(&env.get_image_dir()))?;
+        let result = QemuImg::new(system)
+            .convert(&env.get_instance_image_file(&instance.name), &tmp_file)
+            .and_then(|_| system.rename_file(Path::new(&tmp_file), Path::new(&image_file)));
+        if result.is_err() {
+            system.remove_file(Path::new(&tmp_file)).ok();
+        }
+        result?;
+        image.size = Some(system.get_path_size(Path::new(&image_file)));
+
+        let local_image_file = env.get_local_image_file();
+        let mut local_images = LocalImages::read_from_file(system, Path::new(&local_image_file));
+        local_images.add(image.clone());
+        local_images.write_to_file(system, Path::new(&local_image_file))?;
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Arch, DiskEncryption, Environment, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+
+    fn build_env() -> Environment {
+        Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        )
+    }
+
+    fn build_instance() -> Instance {
+        Instance {
+            name: "dev".to_string(),
+            arch: Arch::ARM64,
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_reject_invalid_image_name() {
+        assert!(CommitCommand::try_parse_from(["commit", "dev", "tools"]).is_err());
+    }
+
+    #[test]
+    fn test_reject_encrypted_instance() {
+        let system = Rc::new(SystemMock::new());
+        let console = &mut Console::new(system.as_ref());
+        let instance = Instance {
+            encryption: Some(DiskEncryption::File),
+            ..build_instance()
+        };
+        let context = commands::Context::new(
+            system.clone(),
+            build_env(),
+            Box::new(InstanceStoreMock::new(vec![instance])),
+        );
+
+        let result = CommitCommand::try_parse_from(["commit", "dev", "debian:tools"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(
+            matches!(result, Err(Error::EncryptedDiskNotSupported(ref name, _)) if name == "dev")
+        );
+    }
+
+    #[test]
+    fn test_save_image_flattens_disk_and_registers_image() {
+        let system = Rc::new(
+            SystemMock::new()
+                .add_command_output(
+                    "qemu-img convert -f qcow2 -O qcow2 /data/machines/dev/machine.img /cache/images/local_debian_tools_arm64.tmp",
+                    b"",
+                )
+                .add_file("/cache/images/local_debian_tools_arm64.tmp", &[0; 512]),
+        );
+        let env = build_env();
+        let context = commands::Context::new(
+            system.clone(),
+            env.clone(),
+            Box::new(InstanceStoreMock::new(vec![build_instance()])),
+        );
+
+        let image = CommitCommand::try_parse_from(["commit", "dev", "debian:tools"])
+            .unwrap()
+            .save_image(&context, &build_instance())
+            .unwrap();
+
+        assert_eq!(image.to_file_name(), "local_debian_tools_arm64");
+        assert_eq!(image.size, Some(512));
+        assert!(
+            system
+                .get_written_file("/cache/images/local_debian_tools_arm64")
+                .is_some()
+        );
+        assert_eq!(
+            LocalImages::read_from_file(system.as_ref(), Path::new(&env.get_local_image_file()))
+                .images,
+            [image]
+        );
+    }
+}
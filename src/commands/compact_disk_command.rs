@@ -0,0 +1,100 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::models::DataSize;
+use crate::qemu::QemuImg;
+use crate::view::{Console, Spinner};
+use clap::Parser;
+use std::sync::{Arc, Mutex};
+
+/// Compact the system disk of a VM instance
+///
+/// Rewrites the disk image without the blocks the guest no longer uses. Run
+/// `sudo fstrim -av` in the guest before stopping it to free as much as
+/// possible.
+#[derive(Parser)]
+pub struct CompactDiskCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+}
+
+impl Command for CompactDiskCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let env = context.get_env();
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if instance_store.is_running(&instance) {
+            return Err(Error::InstanceNotStopped(instance.name));
+        }
+
+        let qemu_img = QemuImg::new(context.get_system());
+        let before = qemu_img.get_image_info(env, &instance);
+
+        console.play(Arc::new(Mutex::new(Spinner::new(format!(
+            "Compacting disk of {}",
+            instance.name
+        )))));
+        let result = instance_store.compact(&instance);
+        console.stop();
+        result?;
+
+        if let (Some(before), Some(after)) = (before, qemu_img.get_image_info(env, &instance)) {
+            console.info(&format!(
+                "Compacted disk of {} from {} to {}",
+                instance.name,
+                DataSize::new(before.actual_size as usize).to_size(),
+                DataSize::new(after.actual_size as usize).to_size()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_context(instance_store: InstanceStoreMock) -> commands::Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            String::new(),
+            String::new(),
+        );
+        commands::Context::new(Rc::new(SystemMock::new()), env, Box::new(instance_store))
+    }
+
+    fn build_instance() -> Instance {
+        Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_reject_path_traversal() {
+        assert!(CompactDiskCommand::try_parse_from(["compact", "../etc"]).is_err());
+    }
+
+    #[test]
+    fn test_reject_running_instance() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(InstanceStoreMock::new_with_running(
+            vec![build_instance()],
+            &["test"],
+        ));
+
+        let result = CompactDiskCommand::try_parse_from(["compact", "test"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InstanceNotStopped(ref name)) if name == "test"));
+    }
+}
@@ -1,20 +1,26 @@
 use crate::actions::LoadInstanceAction;
 use crate::commands::{self, Command};
 use crate::error::{Error, Result};
-use crate::models::InstanceCertPaths;
-use crate::qemu::TlsClient;
-use crate::util;
+use crate::instance::InstanceStore;
+use crate::models::{Instance, InstanceCertPaths};
+use crate::platform::System;
+use crate::qemu::{QemuMonitorClient, TlsClient};
+use crate::recording::CastWriter;
+use crate::util::{self, ConsoleInput, EscapeCommand};
 use crate::view::Console;
 use clap::Parser;
-use std::path::PathBuf;
+use futures_util::StreamExt;
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::time::{Duration, Instant};
-use tokio::io::AsyncWriteExt;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_util::codec::FramedRead;
-use tokio_util::io::StreamReader;
 
 const CONSOLE_TIMEOUT: Duration = Duration::from_secs(60);
 const PROBE_IO_TIMEOUT: Duration = Duration::from_secs(1);
+const CONSOLE_CHARDEV: &str = "console";
 
 /// Open VM instance console
 ///
@@ -23,10 +29,23 @@ const PROBE_IO_TIMEOUT: Duration = Duration::from_secs(1);
 ///   Connect to the console of 'my-instance'
 ///   $ cubic console my-instance
 ///   Login requires a password. Set one with 'sudo passwd' over cubic ssh.
-///   Press Enter, ~, . to exit the console.
+///   Press Enter, ~, . to exit the console or Enter, ~, ? for help.
 ///
 ///   [...]
 ///
+///   Record the session for `cubic replay`
+///   $ cubic console my-instance --record session.cast
+///
+/// Escape sequences (after Enter):
+///
+///   ~.  Detach from the console
+///   ~?  Show the escape sequences
+///   ~b  Send a break, e.g. for the magic SysRq key of Linux
+///   ~r  Reset the VM instance
+///   ~p  Pause or resume the VM instance
+///   ~l  Start or stop logging the session to a file in the instance directory
+///   ~~  Send a literal ~
+///
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct ConsoleCommand {
@@ -34,6 +53,8 @@ pub struct ConsoleCommand {
     pub accel: commands::AccelArg,
     #[clap(flatten)]
     instance: commands::InstanceArg,
+    #[clap(flatten)]
+    record: commands::RecordArg,
 }
 
 impl Command for ConsoleCommand {
@@ -51,7 +72,7 @@ impl Command for ConsoleCommand {
             LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
 
         console.info("Login requires a password. Set one with 'sudo passwd' over cubic ssh.");
-        console.info("Press Enter, ~, . to exit the console.");
+        console.info("Press Enter, ~, . to exit the console or Enter, ~, ? for help.");
 
         let port = instance
             .console_port
@@ -82,18 +103,49 @@ impl Command for ConsoleCommand {
             thread::sleep(Duration::from_secs(1));
         }
 
+        let recording = self.record.create_writer(console, system)?;
         console.raw_mode();
+        let session = ConsoleSession {
+            system,
+            instance_store,
+            instance: &instance,
+            instance_dir: &instance_dir,
+            log: RefCell::new(None),
+            recording: RefCell::new(recording),
+        };
         let shell = util::AsyncCaller::new().call(async {
             let tls = TlsClient::new(&certs)?.connect_async(port).await?;
             let (mut reader, mut writer) = tokio::io::split(tls);
-            let mut stdin = StreamReader::new(FramedRead::new(
-                tokio::io::stdin(),
-                util::ShortcutDecoder::new(),
-            ));
-            let mut stdout = tokio::io::stdout();
+            let mut stdin = FramedRead::new(tokio::io::stdin(), util::ShortcutDecoder::new());
+
+            let input = async {
+                while let Some(Ok(input)) = stdin.next().await {
+                    match input {
+                        ConsoleInput::Data(data) => {
+                            if writer.write_all(&data).await.is_err() {
+                                break;
+                            }
+                        }
+                        ConsoleInput::Escape(EscapeCommand::Detach) => break,
+                        ConsoleInput::Escape(command) => session.run_escape(command),
+                    }
+                }
+            };
+            let output = async {
+                let mut stdout = tokio::io::stdout();
+                let mut buf = [0u8; 4096];
+                while let Ok(size) = reader.read(&mut buf).await {
+                    if size == 0 || stdout.write_all(&buf[..size]).await.is_err() {
+                        break;
+                    }
+                    stdout.flush().await.ok();
+                    session.log_output(&buf[..size]);
+                    session.record_output(&buf[..size]);
+                }
+            };
             tokio::select!(
-                _ = tokio::io::copy(&mut stdin, &mut writer) => {},
-                _ = tokio::io::copy(&mut reader, &mut stdout) => {},
+                _ = input => {},
+                _ = output => {},
             );
 
             let mut out = tokio::io::stdout();
@@ -109,12 +161,158 @@ impl Command for ConsoleCommand {
     }
 }
 
+// Escape command that acts on the instance through its monitor
+type MonitorAction = fn(&mut QemuMonitorClient, &str) -> Result<String>;
+
+// State of an attached console, which escape commands act on
+struct ConsoleSession<'a> {
+    system: &'a dyn System,
+    instance_store: &'a dyn InstanceStore,
+    instance: &'a Instance,
+    instance_dir: &'a Path,
+    log: RefCell<Option<Box<dyn Write>>>,
+    recording: RefCell<Option<CastWriter>>,
+}
+
+impl ConsoleSession<'_> {
+    fn run_escape(&self, command: EscapeCommand) {
+        let action: MonitorAction = match command {
+            EscapeCommand::Detach => return,
+            EscapeCommand::Help => return notify(&EscapeCommand::get_help().join("\r\n")),
+            EscapeCommand::ToggleLog => return notify(&self.toggle_log()),
+            EscapeCommand::Break => Self::send_break,
+            EscapeCommand::Reset => Self::reset,
+            EscapeCommand::Pause => Self::toggle_pause,
+        };
+
+        // The reply may take a while, during which the console output keeps
+        // flowing, so the command runs off the runtime thread
+        let key = command.get_key() as char;
+        let name = self.instance.name.clone();
+        match self.instance_store.get_monitor(self.instance) {
+            Ok(mut monitor) => {
+                tokio::task::spawn_blocking(move || {
+                    report_escape(key, action(&mut monitor, &name));
+                });
+            }
+            Err(error) => report_escape(key, Err(error)),
+        }
+    }
+
+    fn send_break(monitor: &mut QemuMonitorClient, _name: &str) -> Result<String> {
+        monitor.send_break(CONSOLE_CHARDEV)?;
+        Ok("Sent break".to_string())
+    }
+
+    fn reset(monitor: &mut QemuMonitorClient, name: &str) -> Result<String> {
+        monitor.reset()?;
+        Ok(format!("Reset {name}"))
+    }
+
+    fn toggle_pause(monitor: &mut QemuMonitorClient, name: &str) -> Result<String> {
+        if monitor.is_paused()? {
+            monitor.resume()?;
+            Ok(format!("Resumed {name}"))
+        } else {
+            monitor.pause()?;
+            Ok(format!("Paused {name} (press Enter, ~, p to resume)"))
+        }
+    }
+
+    // Each logging run goes to a new file, so toggling never overwrites an
+    // earlier capture
+    fn toggle_log(&self) -> String {
+        let mut log = self.log.borrow_mut();
+        if log.take().is_some() {
+            return "Stopped logging".to_string();
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = self.instance_dir.join(format!("session-{timestamp}.log"));
+        match self.system.create_file(&path) {
+            Ok(file) => {
+                *log = Some(file);
+                format!("Logging to {}", path.display())
+            }
+            Err(error) => format!("Cannot log to {}: {error}", path.display()),
+        }
+    }
+
+    fn log_output(&self, data: &[u8]) {
+        let mut log = self.log.borrow_mut();
+        if let Some(file) = log.as_mut()
+            && file.write_all(data).and_then(|_| file.flush()).is_err()
+        {
+            *log = None;
+            notify("Stopped logging after a write error");
+        }
+    }
+
+    fn record_output(&self, data: &[u8]) {
+        let mut recording = self.recording.borrow_mut();
+        if let Some(writer) = recording.as_mut()
+            && writer.write_output(data).is_err()
+        {
+            *recording = None;
+            notify("Stopped recording after a write error");
+        }
+    }
+}
+
+fn report_escape(key: char, result: Result<String>) {
+    notify(&result.unwrap_or_else(|error| format!("Cannot run ~{key}: {error}")));
+}
+
+// The terminal is in raw mode, which does not return the carriage on a newline
+fn notify(message: &str) {
+    let mut stdout = std::io::stdout();
+    write!(
+        stdout,
+        "\r\n[cubic] {}\r\n",
+        message.replace("\r\n", "\r\n[cubic] ")
+    )
+    .ok();
+    stdout.flush().ok();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::platform::SystemMock;
 
     #[test]
     fn test_reject_path_traversal() {
         assert!(ConsoleCommand::try_parse_from(["console", "../../etc"]).is_err());
     }
+
+    #[test]
+    fn test_toggle_log() {
+        let system = SystemMock::new();
+        let instance = Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        };
+        let instance_store = InstanceStoreMock::new(vec![instance.clone()]);
+        let session = ConsoleSession {
+            system: &system,
+            instance_store: &instance_store,
+            instance: &instance,
+            instance_dir: Path::new("/data/machines/test"),
+            log: RefCell::new(None),
+            recording: RefCell::new(None),
+        };
+
+        assert!(
+            session
+                .toggle_log()
+                .starts_with("Logging to /data/machines/test/session-")
+        );
+        assert!(session.log.borrow().is_some());
+        assert_eq!(session.toggle_log(), "Stopped logging");
+        assert!(session.log.borrow().is_none());
+    }
 }
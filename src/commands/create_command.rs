@@ -1,18 +1,29 @@
-use crate::actions::CreateInstanceAction;
+use crate::actions::{
+    CreateInstanceAction, ImportBoxAction, ImportOciAction, ImportedBox, ImportedOci,
+};
 use crate::commands::{
     self, Command, Context,
     image::{fetch_image, fetch_image_info},
 };
 use crate::error::{Error, Result};
 use crate::models::{
-    DataSize, ImageName, Instance, LOW_DISK_SPACE_WARNING, PortForward, ResourceAllocator, UserName,
+    Arch, DataSize, DiskBus, DiskEncryption, DiskSettings, ImageName, Instance,
+    LOW_DISK_SPACE_WARNING, PortForward, ResourceAllocator, UserName,
 };
+use crate::oci::ROOTFS_LABEL;
+use crate::qemu::{QemuImg, QemuSystem};
 use crate::view::Console;
 use crate::view::Spinner;
 use clap::{ArgAction, Parser};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
 pub const DEFAULT_DISK_SIZE: &str = "100G";
+const VAGRANT_USER: &str = "vagrant";
+// Boot files of an instance built from a container image, in its directory
+const KERNEL_FILE: &str = "vmlinuz";
+const INITRD_FILE: &str = "initrd.img";
 
 /// Create VM instances
 ///
@@ -39,15 +50,70 @@ pub const DEFAULT_DISK_SIZE: &str = "100G";
 ///   Create a VM instance without network access:
 ///   $ cubic create example6 --isolate ubuntu:noble
 ///
+///   Create a VM instance for I/O heavy builds:
+///   $ cubic create example8 --disk-cache none --disk-aio io_uring --disk-iothread -i ubuntu:noble
+///
+///   Create a VM instance with an encrypted disk and ask for its passphrase on every start:
+///   $ cubic create example7 --encrypt --prompt-passphrase -i ubuntu:noble
+///
+///   Create a VM instance from a Vagrant box (libvirt or virtualbox):
+///   $ cubic create example9 --from-box ~/boxes/legacy-dev.box
+///
+///   Create a VM instance from a container image that carries a kernel:
+///   $ cubic create example10 --from-oci quay.io/fedora/fedora-bootc:41
+///
+///   Create a VM instance from an OCI layout directory and boot it with your own kernel:
+///   $ cubic create example11 --from-oci ./vm-layout --kernel ./bzImage --initrd ./initrd.img
+///
+///   Create a VM instance that boots with Secure Boot:
+///   $ cubic create example12 --secure-boot -i ubuntu:noble
+///
+///   Create a VM instance with a TPM, e.g. for measured boot or disk keys:
+///   $ cubic create example13 --secure-boot --tpm -i ubuntu:noble
+///
+///   Create a VM instance with an empty disk and install it from an ISO:
+///   $ cubic create example14 --iso ~/Downloads/installer.iso --blank-disk 40G --display
+///   $ cubic start example14 && cubic display example14 --open
+///
+///   Create a VM instance and forward the instance's HTTP port to a free host port:
+///   $ cubic create example15 --port :80 -i ubuntu:noble
+///   $ cubic ports
+///
+///   Create a VM instance and forward the Kubernetes NodePort range to the host:
+///   $ cubic create example16 -p 30000-32767:30000-32767 -i ubuntu:noble
+///
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct CreateCommand {
     #[clap(flatten)]
     pub instance_name: commands::InstanceArg,
     /// VM image name (e.g. 'debian:trixie')
-    #[clap(short, long)]
-    image: ImageName,
-    /// Username (default: 'cubic')
+    #[clap(short, long, required_unless_present_any = ["from_box", "from_oci", "iso"])]
+    image: Option<ImageName>,
+    /// Vagrant box file or URL to create the VM instance from instead of an image
+    #[clap(long, value_name = "BOX", conflicts_with_all = ["image", "execute"])]
+    from_box: Option<String>,
+    /// Container image or OCI layout directory to create the VM instance from instead of an image
+    #[clap(long, value_name = "IMAGE", conflicts_with_all = ["image", "from_box"])]
+    from_oci: Option<String>,
+    /// Kernel to boot the container image with, in place of the one it carries
+    #[clap(long, requires = "from_oci", conflicts_with_all = ["image", "from_box"])]
+    kernel: Option<PathBuf>,
+    /// Initrd of the kernel given with --kernel
+    #[clap(long, requires = "kernel")]
+    initrd: Option<PathBuf>,
+    /// Installer ISO to boot from until the VM shuts down for the first time
+    #[clap(
+        long,
+        value_name = "FILE",
+        requires = "blank_disk",
+        conflicts_with_all = ["image", "from_box", "from_oci", "execute", "encrypt"]
+    )]
+    iso: Option<PathBuf>,
+    /// Size of the empty disk to install the ISO onto
+    #[clap(long, value_name = "SIZE", requires = "iso", conflicts_with = "disk")]
+    blank_disk: Option<DataSize>,
+    /// Username (default: 'cubic', or 'vagrant' for a box)
     #[clap(short, long)]
     user: Option<UserName>,
     /// Number of vCPUs for the VM instance (default: derived from host resources)
@@ -59,7 +125,7 @@ pub struct CreateCommand {
     /// Disk size of the VM instance
     #[clap(short, long, default_value = DEFAULT_DISK_SIZE)]
     disk: DataSize,
-    /// Forward ports from guest to host (e.g. -p 8000:80 or -p 9000:90/tcp)
+    /// Forward ports from guest to host (e.g. -p 8000:80, -p 9000:90/tcp, -p 30000-30100:30000-30100 or -p :80 for a free host port)
     #[clap(short, long)]
     port: Vec<PortForward>,
     /// Execute a command once on the first boot (e.g. "sudo apt install ...")
@@ -68,6 +134,54 @@ pub struct CreateCommand {
     /// Isolate the VM instance from network
     #[clap(long, action = ArgAction::SetTrue)]
     isolate: bool,
+    /// Give unused guest memory back to the host
+    #[clap(long, action = ArgAction::SetTrue)]
+    reclaim_memory: bool,
+    /// Encrypt the disk of the VM instance with a passphrase (LUKS)
+    #[clap(long, action = ArgAction::SetTrue)]
+    encrypt: bool,
+    /// Ask for the passphrase on every start instead of storing it
+    #[clap(long, requires = "encrypt", action = ArgAction::SetTrue)]
+    prompt_passphrase: bool,
+    /// Boot a UEFI firmware with Secure Boot and the Microsoft keys enrolled
+    #[clap(long, action = ArgAction::SetTrue)]
+    secure_boot: bool,
+    /// Attach a software TPM 2.0 (needs swtpm)
+    #[clap(long, action = ArgAction::SetTrue)]
+    tpm: bool,
+    /// Add a graphics card, shown with `cubic display`
+    #[clap(long, action = ArgAction::SetTrue)]
+    display: bool,
+    #[clap(flatten)]
+    disk_settings: commands::DiskSettingsArg,
+}
+
+impl CreateCommand {
+    fn get_encryption(&self) -> Option<DiskEncryption> {
+        match (self.encrypt, self.prompt_passphrase) {
+            (false, _) => None,
+            (true, false) => Some(DiskEncryption::File),
+            (true, true) => Some(DiskEncryption::Prompt),
+        }
+    }
+
+    fn prompt_passphrase(&self, console: &mut Console<'_>) -> Result<String> {
+        let name = self.instance_name.value.to_string();
+        let passphrase = console
+            .prompt_secret(&format!("Enter disk passphrase for {name}: "))
+            .map_err(|_| Error::PassphraseCancelled(name.clone()))?;
+        let repeated = console
+            .prompt_secret("Repeat the passphrase: ")
+            .map_err(|_| Error::PassphraseCancelled(name.clone()))?;
+
+        if passphrase.is_empty() {
+            return Err(Error::PassphraseCancelled(name));
+        }
+        if passphrase != repeated {
+            return Err(Error::PassphraseMismatch);
+        }
+        Ok(passphrase)
+    }
 }
 
 impl Command for CreateCommand {
@@ -81,39 +195,195 @@ impl Command for CreateCommand {
             ));
         }
 
+        let mut disk_settings = DiskSettings::default();
+        self.disk_settings
+            .apply(self.instance_name.value.as_str(), &mut disk_settings)?;
+
         if ResourceAllocator::is_disk_space_low(context.get_system(), env) {
             console.warn(LOW_DISK_SPACE_WARNING);
         }
 
-        // Fetch image
-        let image = &fetch_image_info(console, context.get_system(), env, &self.image)?;
-        fetch_image(console, context.get_system(), env, image)?;
+        // Asked before the download, which may take a while
+        let passphrase = if self.encrypt {
+            Some(self.prompt_passphrase(console)?)
+        } else {
+            None
+        };
+
+        // Boxes and container images are unpacked before the spinner, since
+        // a download shows a progress of its own
+        let name = self.instance_name.value.as_str();
+        let imported = match (&self.from_box, &self.from_oci) {
+            (Some(source), _) => Some(Imported::Box(
+                ImportBoxAction::new(source).run(console, context, name)?,
+            )),
+            (_, Some(source)) => {
+                let mut action =
+                    ImportOciAction::new(source, Arch::get_host(), self.disk.get_bytes() as u64);
+                action.set_kernel(self.kernel.clone(), self.initrd.clone());
+                Some(Imported::Oci(action.run(console, context, name)?))
+            }
+            (None, None) => None,
+        };
+        let result = self.create(
+            console,
+            context,
+            disk_settings,
+            passphrase,
+            imported.as_ref(),
+        );
+        if let Some(imported) = &imported {
+            context.get_system().remove_dir(imported.get_dir()).ok();
+        }
+        result
+    }
+}
+
+/// A system disk made from something else than a cubic image
+enum Imported {
+    Box(ImportedBox),
+    Oci(ImportedOci),
+}
+
+impl Imported {
+    fn get_dir(&self) -> &Path {
+        match self {
+            Imported::Box(imported) => &imported.dir,
+            Imported::Oci(imported) => &imported.dir,
+        }
+    }
+}
+
+/// Kernel command line of an instance built from a container image. An
+/// initrd finds the filesystem by its label, a kernel alone needs the device.
+fn get_kernel_append(imported: &ImportedOci, arch: Arch, settings: &DiskSettings) -> String {
+    let root = match (&imported.initrd, settings.get_bus()) {
+        (Some(_), _) => format!("LABEL={ROOTFS_LABEL}"),
+        (None, DiskBus::VirtioBlk) => "/dev/vda".to_string(),
+        (None, DiskBus::VirtioScsi) => "/dev/sda".to_string(),
+    };
+    format!(
+        "root={root} rw console={}",
+        QemuSystem::get_kernel_console(arch)
+    )
+}
+
+impl CreateCommand {
+    fn create(
+        &self,
+        console: &mut Console<'_>,
+        context: &Context,
+        disk_settings: DiskSettings,
+        passphrase: Option<String>,
+        imported: Option<&Imported>,
+    ) -> Result<()> {
+        let env = context.get_env();
+        let name = self.instance_name.value.to_string();
+        let iso = self
+            .iso
+            .as_ref()
+            .map(|iso| commands::BootArg::get_path(context.get_system(), &name, "iso", iso))
+            .transpose()?;
+        let (image_path, arch, local) = match (imported, &self.image) {
+            (Some(Imported::Oci(imported)), _) => (
+                Some(imported.image.to_string_lossy().into_owned()),
+                Arch::get_host(),
+                false,
+            ),
+            (Some(Imported::Box(imported)), _) => {
+                let arch = imported
+                    .metadata
+                    .architecture
+                    .as_deref()
+                    .and_then(|arch| Arch::from_str(arch).ok())
+                    .unwrap_or_else(Arch::get_host);
+                (
+                    Some(imported.image.to_string_lossy().into_owned()),
+                    arch,
+                    false,
+                )
+            }
+            (None, Some(image_name)) => {
+                let image = &fetch_image_info(console, context.get_system(), env, image_name)?;
+                fetch_image(console, context.get_system(), env, image)?;
+                (
+                    Some(env.get_image_file(&image.to_file_name())),
+                    image.arch,
+                    image.local,
+                )
+            }
+            // An installer fills the blank disk
+            (None, None) => (None, Arch::get_host(), false),
+        };
 
         console.play(Arc::new(Mutex::new(Spinner::new(format!(
             "Creating {}",
             self.instance_name.value
         )))));
         let ssh_port = context.get_system().bind_port()?;
+        let mut hostfwd = self.port.clone();
+        for fwd in &mut hostfwd {
+            fwd.allocate_host_port(context.get_system())?;
+        }
 
         let (default_cpus, default_mem) =
             ResourceAllocator::read_from_host(context.get_system()).get_default_resources();
 
+        // A box or a committed disk may be larger than the default size, and a
+        // disk never shrinks on creation
+        let mut disk_capacity = self.blank_disk.clone().unwrap_or(self.disk.clone());
+        if (local || matches!(imported, Some(Imported::Box(_))))
+            && let Some(image_path) = &image_path
+            && let Some(info) = QemuImg::new(context.get_system()).get_file_info(image_path)
+            && info.virtual_size > disk_capacity.get_bytes() as u64
+        {
+            disk_capacity = DataSize::new(info.virtual_size as usize);
+        }
+
+        // Boxes come with the user 'vagrant' and without cloud-init to add
+        // another one
+        let default_user = match imported {
+            Some(Imported::Box(_)) => UserName::from_str(VAGRANT_USER)?,
+            _ => context.get_env().get_username().clone(),
+        };
+
+        let oci = match imported {
+            Some(Imported::Oci(imported)) => Some(imported),
+            _ => None,
+        };
+        let append = oci.map(|oci| get_kernel_append(oci, arch, &disk_settings));
+
         let instance = Instance {
-            name: self.instance_name.value.to_string(),
-            arch: image.arch,
-            user: self
-                .user
-                .clone()
-                .unwrap_or_else(|| context.get_env().get_username().clone()),
+            name: name.clone(),
+            arch,
+            user: self.user.clone().unwrap_or(default_user),
             cpus: self.cpus.unwrap_or(default_cpus),
             mem: self.memory.clone().unwrap_or(default_mem),
-            disk_capacity: self.disk.clone(),
+            disk_capacity,
             ssh_port,
-            hostfwd: self.port.clone(),
+            hostfwd,
             execute: self.execute.clone(),
             isolate: self.isolate,
+            reclaim_memory: self.reclaim_memory,
+            encryption: self.get_encryption(),
+            disk_settings,
+            vagrant_box: match imported {
+                Some(Imported::Box(imported)) => Some(imported.vagrant_box.clone()),
+                _ => None,
+            },
+            oci_image: oci.map(|oci| oci.name.clone()),
+            kernel: oci.map(|_| KERNEL_FILE.to_string()),
+            initrd: oci
+                .and_then(|oci| oci.initrd.as_ref())
+                .map(|_| INITRD_FILE.to_string()),
+            append,
+            secure_boot: self.secure_boot,
+            tpm: self.tpm,
+            display: self.display,
+            iso,
             ..Instance::default()
         };
+        PortForward::check_count(&instance.name, &instance.hostfwd)?;
 
         console.debug(&format!(
             "Resolved instance '{}': {} vCPUs, {} memory, {} disk, ssh_port={}",
@@ -124,8 +394,32 @@ impl Command for CreateCommand {
             instance.ssh_port,
         ));
 
-        let image_path = &env.get_image_file(&image.to_file_name());
-        CreateInstanceAction::new().run(context, image_path, instance)?;
+        let mut action = CreateInstanceAction::new();
+        if let Some(passphrase) = &passphrase {
+            action.set_passphrase(passphrase);
+        }
+        action.run(context, image_path.as_deref(), instance)?;
+
+        let system = context.get_system();
+        match imported {
+            // The first login swaps the insecure key for the cubic key
+            Some(Imported::Box(imported)) => {
+                system.rename_file(&imported.key, Path::new(&env.get_vagrant_key_file(&name)))?
+            }
+            Some(Imported::Oci(imported)) => {
+                system.rename_file(
+                    &imported.kernel,
+                    Path::new(&env.get_instance_boot_file(&name, KERNEL_FILE)),
+                )?;
+                if let Some(initrd) = &imported.initrd {
+                    system.rename_file(
+                        initrd,
+                        Path::new(&env.get_instance_boot_file(&name, INITRD_FILE)),
+                    )?;
+                }
+            }
+            None => {}
+        }
 
         console.stop();
         Ok(())
@@ -168,4 +462,171 @@ mod tests {
             Err(Error::InstanceAlreadyExists(ref name)) if name == "test"
         ));
     }
+
+    #[test]
+    fn test_prompt_passphrase_requires_encrypt() {
+        assert!(
+            CreateCommand::try_parse_from([
+                "create",
+                "test",
+                "-i",
+                "debian:bookworm",
+                "--prompt-passphrase"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_encrypt_stores_the_passphrase_by_default() {
+        let cmd =
+            CreateCommand::try_parse_from(["create", "test", "-i", "debian:bookworm", "--encrypt"])
+                .unwrap();
+
+        assert_eq!(cmd.get_encryption(), Some(DiskEncryption::File));
+    }
+
+    #[test]
+    fn test_encrypt_rejects_mismatching_passphrases() {
+        let system = SystemMock::new();
+        system.push_input("secret");
+        system.push_input("secrte");
+        let console = &mut Console::new(&system);
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            String::new(),
+            String::new(),
+        );
+        let context = Context::new(
+            Rc::new(SystemMock::new()),
+            env,
+            Box::new(InstanceStoreMock::new(Vec::new())),
+        );
+
+        let result =
+            CreateCommand::try_parse_from(["create", "test", "-i", "debian:bookworm", "--encrypt"])
+                .unwrap()
+                .run(console, &context);
+
+        assert!(matches!(result, Err(Error::PassphraseMismatch)));
+    }
+
+    #[test]
+    fn test_create_needs_an_image_or_a_box() {
+        assert!(CreateCommand::try_parse_from(["create", "test"]).is_err());
+        assert!(CreateCommand::try_parse_from(["create", "test", "--from-box", "dev.box"]).is_ok());
+    }
+
+    #[test]
+    fn test_create_from_oci_needs_no_image() {
+        assert!(
+            CreateCommand::try_parse_from(["create", "test", "--from-oci", "debian:bookworm"])
+                .is_ok()
+        );
+        assert!(
+            CreateCommand::try_parse_from([
+                "create",
+                "test",
+                "--from-oci",
+                "debian:bookworm",
+                "--from-box",
+                "dev.box"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_create_from_iso_needs_a_blank_disk() {
+        assert!(
+            CreateCommand::try_parse_from([
+                "create",
+                "test",
+                "--iso",
+                "installer.iso",
+                "--blank-disk",
+                "40G"
+            ])
+            .is_ok()
+        );
+        assert!(
+            CreateCommand::try_parse_from(["create", "test", "--iso", "installer.iso"]).is_err()
+        );
+        assert!(CreateCommand::try_parse_from(["create", "test", "--blank-disk", "40G"]).is_err());
+    }
+
+    #[test]
+    fn test_create_from_iso_conflicts_with_image() {
+        assert!(
+            CreateCommand::try_parse_from([
+                "create",
+                "test",
+                "-i",
+                "debian:bookworm",
+                "--iso",
+                "installer.iso",
+                "--blank-disk",
+                "40G"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_kernel_requires_from_oci() {
+        assert!(
+            CreateCommand::try_parse_from([
+                "create",
+                "test",
+                "-i",
+                "debian:bookworm",
+                "--kernel",
+                "bzImage"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_kernel_append_mounts_the_root_filesystem() {
+        let imported = ImportedOci {
+            dir: PathBuf::from("/cache/oci/test.tmp"),
+            image: PathBuf::from("/cache/oci/test.tmp/rootfs.qcow2"),
+            kernel: PathBuf::from("/cache/oci/test.tmp/vmlinuz"),
+            initrd: None,
+            name: "debian".to_string(),
+        };
+        let settings = DiskSettings::default();
+
+        assert_eq!(
+            get_kernel_append(&imported, Arch::AMD64, &settings),
+            "root=/dev/vda rw console=ttyS0"
+        );
+        assert_eq!(
+            get_kernel_append(
+                &ImportedOci {
+                    initrd: Some(PathBuf::from("/cache/oci/test.tmp/initrd.img")),
+                    ..imported
+                },
+                Arch::ARM64,
+                &settings
+            ),
+            "root=LABEL=cubic-root rw console=ttyAMA0"
+        );
+    }
+
+    #[test]
+    fn test_create_from_box_conflicts_with_image() {
+        assert!(
+            CreateCommand::try_parse_from([
+                "create",
+                "test",
+                "-i",
+                "debian:bookworm",
+                "--from-box",
+                "dev.box"
+            ])
+            .is_err()
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use crate::actions::LoadInstanceAction;
 use crate::commands::{self, Command};
 use crate::error::{Error, Result};
+use crate::models::TimeSpan;
 use crate::view::{ConfirmDialog, Console};
 use clap::Parser;
 
@@ -50,11 +51,13 @@ impl Command for DeleteCommand {
 
         // Ask for confirmation
         if self.yes.value || ConfirmDialog::new("\nDo you want to proceed?").confirm(console) {
-            // Stop the VM instances
+            // Stop the VM instances. Their disks are about to go, so there is
+            // no point in waiting for the guests to shut down.
             commands::StopCommand {
                 all: false.into(),
                 wait: true,
-                kill: true,
+                kill: false,
+                timeout: Some(TimeSpan::from_secs(0)),
                 instances: self.instances.value.clone().into(),
             }
             .run(console, context)?;
@@ -0,0 +1,56 @@
+use crate::commands::{self, Command};
+use crate::error::Result;
+use crate::view::Console;
+use clap::{Parser, Subcommand};
+
+#[derive(Subcommand)]
+pub enum DiskCommands {
+    Add(commands::AddDiskCommand),
+    Rm(commands::RemoveDiskCommand),
+    Ls(commands::ListDiskCommand),
+    Compact(commands::CompactDiskCommand),
+}
+
+/// Manage data disks of VM instances
+///
+/// Data disks live next to the system disk, so the system disk can be rebuilt
+/// without losing the data. Inside the guest, a disk shows up as
+/// /dev/disk/by-id/virtio-<name>.
+///
+/// Examples:
+///
+///   Add a new 20 GiB disk named 'pgdata' to 'my-instance':
+///   $ cubic disk add my-instance pgdata --size 20G
+///
+///   Attach an existing disk image to 'my-instance':
+///   $ cubic disk add my-instance backup --file ~/backup.qcow2
+///
+///   List the disks of 'my-instance':
+///   $ cubic disk ls my-instance
+///   Name     Format   Size       File
+///   pgdata   qcow2    20.0 GiB   ~/.local/share/cubic/machines/my-instance/disk-pgdata.qcow2
+///
+///   Remove the disk 'pgdata' from 'my-instance':
+///   $ cubic disk rm my-instance pgdata
+///
+///   Give the space the guest freed with fstrim back to the host:
+///   $ cubic disk compact my-instance
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct DiskCommand {
+    #[command(subcommand)]
+    command: DiskCommands,
+}
+
+impl Command for DiskCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        match &self.command {
+            DiskCommands::Add(cmd) => cmd as &dyn Command,
+            DiskCommands::Rm(cmd) => cmd,
+            DiskCommands::Ls(cmd) => cmd,
+            DiskCommands::Compact(cmd) => cmd,
+        }
+        .run(console, context)
+    }
+}
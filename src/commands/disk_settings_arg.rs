@@ -0,0 +1,107 @@
+use crate::error::{Error, Result};
+use crate::models::{DetectZeroes, DiskAio, DiskBus, DiskCache, DiskDiscard, DiskSettings};
+use clap::{ArgAction, Parser};
+
+#[derive(Default, Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct DiskSettingsArg {
+    /// Host cache mode of the disks
+    #[clap(long, value_enum)]
+    pub disk_cache: Option<DiskCache>,
+    /// Host I/O backend of the disks
+    #[clap(long, value_enum)]
+    pub disk_aio: Option<DiskAio>,
+    /// What becomes of the blocks the guest trims
+    #[clap(long, value_enum)]
+    pub disk_discard: Option<DiskDiscard>,
+    /// Look for zeroed blocks in guest writes
+    #[clap(long, value_enum)]
+    pub disk_detect_zeroes: Option<DetectZeroes>,
+    /// Run the disk I/O in a dedicated thread
+    #[clap(long, overrides_with = "no_disk_iothread", action = ArgAction::SetTrue)]
+    pub disk_iothread: bool,
+    /// Run the disk I/O in the QEMU main loop (default)
+    #[clap(long, overrides_with = "disk_iothread", action = ArgAction::SetTrue)]
+    pub no_disk_iothread: bool,
+    /// Controller of the system disk
+    #[clap(long, value_enum)]
+    pub disk_bus: Option<DiskBus>,
+}
+
+impl DiskSettingsArg {
+    /// Overrides the settings that were given and keeps the others
+    pub fn apply(&self, instance: &str, settings: &mut DiskSettings) -> Result<()> {
+        if let Some(cache) = self.disk_cache {
+            settings.cache = Some(cache);
+        }
+        if let Some(aio) = self.disk_aio {
+            settings.aio = Some(aio);
+        }
+        if let Some(discard) = self.disk_discard {
+            settings.discard = Some(discard);
+        }
+        if let Some(detect_zeroes) = self.disk_detect_zeroes {
+            settings.detect_zeroes = Some(detect_zeroes);
+        }
+        if self.disk_iothread {
+            settings.iothread = true;
+        } else if self.no_disk_iothread {
+            settings.iothread = false;
+        }
+        if let Some(bus) = self.disk_bus {
+            settings.bus = Some(bus);
+        }
+
+        if !settings.is_valid() {
+            return Err(Error::NativeAioNeedsDirectCache(instance.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_keeps_settings_that_were_not_given() {
+        let mut settings = DiskSettings {
+            cache: Some(DiskCache::None),
+            iothread: true,
+            ..DiskSettings::default()
+        };
+
+        DiskSettingsArg::try_parse_from(["test", "--disk-aio", "io_uring"])
+            .unwrap()
+            .apply("test", &mut settings)
+            .unwrap();
+
+        assert_eq!(
+            settings,
+            DiskSettings {
+                cache: Some(DiskCache::None),
+                aio: Some(DiskAio::IoUring),
+                iothread: true,
+                ..DiskSettings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_apply_rejects_native_aio_with_host_cache() {
+        let mut settings = DiskSettings::default();
+
+        let result = DiskSettingsArg::try_parse_from(["test", "--disk-aio", "native"])
+            .unwrap()
+            .apply("test", &mut settings);
+
+        assert!(
+            matches!(result, Err(Error::NativeAioNeedsDirectCache(ref name)) if name == "test")
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_bus() {
+        assert!(DiskSettingsArg::try_parse_from(["test", "--disk-bus", "ide"]).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::models::InstanceCertPaths;
+use crate::util::SystemCommand;
+use crate::view::{Console, MapView};
+use clap::{ArgAction, Parser};
+use std::io;
+use std::path::PathBuf;
+
+// VNC only takes the first 8 characters of a password
+const PASSWORD_LENGTH: usize = 8;
+const PASSWORD_CHARS: &[u8] = b"abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Show the display of a VM instance
+///
+/// The VNC server of the instance listens on localhost and encrypts with TLS.
+/// Viewers verify it against the CA certificate of the instance. Each call
+/// sets a new password, which replaces the previous one.
+///
+/// Examples:
+///
+///   Add a display to 'my-instance' and start it:
+///   $ cubic modify my-instance --display
+///   $ cubic start my-instance
+///
+///   Print how to connect to the display:
+///   $ cubic display my-instance
+///
+///   Open the display in the VNC viewer of the system:
+///   $ cubic display my-instance --open
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct DisplayCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Open the display in the VNC viewer of the system
+    #[clap(long, action = ArgAction::SetTrue)]
+    open: bool,
+}
+
+impl Command for DisplayCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if !instance.display {
+            return Err(Error::DisplayNotEnabled(instance.name));
+        }
+        let port = instance
+            .display_port
+            .filter(|_| instance_store.is_running(&instance))
+            .ok_or_else(|| Error::InstanceNotRunning(instance.name.clone()))?;
+
+        let password = generate_password()?;
+        instance_store
+            .get_monitor(&instance)?
+            .set_vnc_password(&password)?;
+
+        let instance_dir = PathBuf::from(context.get_env().get_instance_dir2(&instance.name));
+        let mut view = MapView::new();
+        view.add("VNC", &format!("127.0.0.1:{port}"));
+        view.add("Password", &password);
+        view.add(
+            "CA Cert",
+            &InstanceCertPaths::load(&instance_dir)
+                .ca_cert
+                .display()
+                .to_string(),
+        );
+        view.print(console);
+
+        if self.open {
+            context
+                .get_system()
+                .spawn_command(&get_open_command(&format!("vnc://127.0.0.1:{port}")))?;
+        }
+        Ok(())
+    }
+}
+
+// Leaves out characters that are easy to mistake for each other
+fn generate_password() -> Result<String> {
+    let mut password = String::with_capacity(PASSWORD_LENGTH);
+    let mut bytes = [0u8; PASSWORD_LENGTH];
+    while password.len() < PASSWORD_LENGTH {
+        getrandom::fill(&mut bytes).map_err(|error| Error::from(io::Error::from(error)))?;
+        append_password_chars(&mut password, &bytes);
+    }
+    Ok(password)
+}
+
+// Skips the bytes past the last whole multiple of the alphabet size, which
+// would make the first characters more likely than the others
+fn append_password_chars(password: &mut String, bytes: &[u8]) {
+    let limit = 256 - 256 % PASSWORD_CHARS.len();
+    let missing = PASSWORD_LENGTH.saturating_sub(password.len());
+    password.extend(
+        bytes
+            .iter()
+            .map(|byte| *byte as usize)
+            .filter(|byte| *byte < limit)
+            .take(missing)
+            .map(|byte| PASSWORD_CHARS[byte % PASSWORD_CHARS.len()] as char),
+    );
+}
+
+// Hands the URL to the viewer the desktop registered for it
+fn get_open_command(url: &str) -> SystemCommand {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = SystemCommand::new("cmd");
+        command.arg("/C").arg("start").arg("");
+        command
+    } else if cfg!(target_os = "macos") {
+        SystemCommand::new("open")
+    } else {
+        SystemCommand::new("xdg-open")
+    };
+    command.arg(url);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_context(instance: Instance) -> commands::Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        commands::Context::new(
+            Rc::new(SystemMock::new()),
+            env,
+            Box::new(InstanceStoreMock::new(vec![instance])),
+        )
+    }
+
+    #[test]
+    fn test_reject_instance_without_display() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        });
+
+        let result = DisplayCommand::try_parse_from(["display", "test"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::DisplayNotEnabled(ref name)) if name == "test"));
+    }
+
+    #[test]
+    fn test_reject_stopped_instance() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(Instance {
+            name: "test".to_string(),
+            display: true,
+            display_port: Some(45901),
+            ..Instance::default()
+        });
+
+        let result = DisplayCommand::try_parse_from(["display", "test"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InstanceNotRunning(ref name)) if name == "test"));
+    }
+
+    #[test]
+    fn test_generate_password_fits_vnc() {
+        let password = generate_password().unwrap();
+
+        assert_eq!(password.len(), PASSWORD_LENGTH);
+        assert!(password.bytes().all(|byte| PASSWORD_CHARS.contains(&byte)));
+    }
+
+    #[test]
+    fn test_append_password_chars_skips_the_biased_bytes() {
+        let mut password = String::new();
+
+        append_password_chars(&mut password, &[0, 228, 255, 227, 57]);
+
+        assert_eq!(password, "a9a");
+    }
+}
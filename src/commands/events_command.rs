@@ -0,0 +1,164 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::qemu::QmpEvent;
+use crate::view::Console;
+use clap::Parser;
+use std::time::Duration;
+
+// Guest lifecycle, disk and memory events. QEMU reports many more, but most of
+// them (e.g. RTC_CHANGE) are noise for a VM user.
+const REPORTED_EVENTS: &[&str] = &[
+    "SHUTDOWN",
+    "RESET",
+    "STOP",
+    "RESUME",
+    "GUEST_PANICKED",
+    "BLOCK_IO_ERROR",
+    "WATCHDOG",
+    "BALLOON_CHANGE",
+];
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Show VM instance events
+///
+/// Waits for the next guest event (shutdown, reset, panic, disk error, ...)
+/// and prints it.
+///
+/// Examples:
+///
+///   Wait for the next event of 'my-instance':
+///   $ cubic events my-instance
+///   2026-10-19T12:34:56.123456Z RESET {"guest":true,"reason":"guest-reset"}
+///
+///   Print all events of 'my-instance' until it stops:
+///   $ cubic events --follow my-instance
+///
+///   Print the events as JSON lines:
+///   $ cubic events --follow --json my-instance
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct EventsCommand {
+    /// Keep printing events until the instance stops
+    #[clap(short, long, default_value_t = false)]
+    follow: bool,
+    /// Print the events as JSON lines
+    #[clap(long, default_value_t = false)]
+    json: bool,
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+}
+
+impl EventsCommand {
+    fn format(&self, event: &QmpEvent) -> Result<String> {
+        if self.json {
+            return serde_json::to_string(event).map_err(Error::from);
+        }
+
+        Ok(match &event.data {
+            Some(data) => format!("{} {} {data}", event.timestamp, event.event),
+            None => format!("{} {}", event.timestamp, event.event),
+        })
+    }
+}
+
+impl Command for EventsCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if !instance_store.is_running(&instance) {
+            return Err(Error::InstanceNotRunning(instance.name));
+        }
+
+        let mut listener = instance_store.get_event_listener(&instance)?;
+        loop {
+            let event = match listener.next_event(POLL_INTERVAL) {
+                Ok(Some(event)) => event,
+                Ok(None) => continue,
+                // QEMU closes the monitor as the instance stops
+                Err(_) if !instance_store.is_running(&instance) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            if REPORTED_EVENTS.contains(&event.event.as_str()) {
+                console.print(&self.format(&event)?);
+                if !self.follow {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_event() -> QmpEvent {
+        serde_json::from_str(
+            r#"{"event":"SHUTDOWN","data":{"guest":true},"timestamp":{"seconds":1791979199,"microseconds":42}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reject_path_traversal() {
+        assert!(EventsCommand::try_parse_from(["events", "../../etc"]).is_err());
+    }
+
+    #[test]
+    fn test_format_event_as_text() {
+        let cmd = EventsCommand::try_parse_from(["events", "test"]).unwrap();
+
+        assert_eq!(
+            cmd.format(&build_event()).unwrap(),
+            r#"2026-10-14T11:59:59.000042Z SHUTDOWN {"guest":true}"#
+        );
+    }
+
+    #[test]
+    fn test_format_event_as_json() {
+        let cmd = EventsCommand::try_parse_from(["events", "--json", "test"]).unwrap();
+
+        assert_eq!(
+            cmd.format(&build_event()).unwrap(),
+            r#"{"event":"SHUTDOWN","data":{"guest":true},"timestamp":{"seconds":1791979199,"microseconds":42}}"#
+        );
+    }
+
+    #[test]
+    fn test_reject_stopped_instance() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let env = Environment::new(
+            UserName::from_str("myuser").unwrap(),
+            String::new(),
+            String::new(),
+        );
+        let instance = Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        };
+        let context = commands::Context::new(
+            Rc::new(SystemMock::new()),
+            env,
+            Box::new(InstanceStoreMock::new(vec![instance])),
+        );
+
+        assert!(matches!(
+            EventsCommand::try_parse_from(["events", "test"])
+                .unwrap()
+                .run(console, &context),
+            Err(Error::InstanceNotRunning(ref name)) if name == "test"
+        ));
+    }
+}
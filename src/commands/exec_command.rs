@@ -65,7 +65,7 @@ impl Command for ExecCommand {
             &user,
             ssh_port,
         ))?;
-        async_caller.call(ssh.shell(console, name.as_str(), channel))?;
+        async_caller.call(ssh.shell(console, name.as_str(), channel, None))?;
         Ok(())
     }
 }
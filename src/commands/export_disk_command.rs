@@ -0,0 +1,313 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, FsOperation, Result};
+use crate::models::{ExportFormat, Instance};
+use crate::ova::{OvaWriter, OvfDescriptor};
+use crate::qemu::QemuImg;
+use crate::view::{Console, TransferView};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Export the system disk of a VM instance for other hypervisors
+///
+/// The formats vmdk (VMware), vdi (VirtualBox), vhdx (Hyper-V) and raw hold
+/// the disk alone. An ova bundles the disk with a descriptor of the CPUs and
+/// memory, so VirtualBox and VMware import the VM in the same shape.
+///
+/// Examples:
+///
+///   Export 'my-instance' for VirtualBox to my-instance.vdi:
+///   $ cubic export-disk my-instance --format vdi
+///
+///   Export 'my-instance' as an appliance for VMware or VirtualBox:
+///   $ cubic export-disk my-instance --format ova --output ~/my-instance.ova
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct ExportDiskCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Format of the export
+    #[clap(short, long, value_enum)]
+    format: ExportFormat,
+    /// File to write (default: <instance>.<format> in the current directory)
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl ExportDiskCommand {
+    fn get_output(&self, instance: &Instance) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| {
+            PathBuf::from(format!("{}.{}", instance.name, self.format.get_extension()))
+        })
+    }
+
+    fn export(
+        &self,
+        console: &mut Console<'_>,
+        context: &commands::Context,
+        instance: &Instance,
+        target: &Path,
+    ) -> Result<u64> {
+        let qemu_img = QemuImg::new(context.get_system());
+        let image = context.get_env().get_instance_image_file(&instance.name);
+        let total = qemu_img
+            .get_file_info(&image)
+            .map(|info| info.virtual_size)
+            .unwrap_or(instance.disk_capacity.get_bytes() as u64);
+
+        let view = Arc::new(Mutex::new(TransferView::new(&format!(
+            "Exporting {}",
+            instance.name
+        ))));
+        console.play(view.clone());
+        let result = qemu_img.export(
+            &image,
+            &target.to_string_lossy(),
+            self.format,
+            &mut |progress| {
+                view.lock()
+                    .unwrap()
+                    .set_progress((progress * total as f64) as u64, Some(total))
+            },
+        );
+        console.stop();
+        result.map(|_| total)
+    }
+
+    fn write_ova(
+        &self,
+        context: &commands::Context,
+        instance: &Instance,
+        disk: &Path,
+        capacity: u64,
+        output: &Path,
+    ) -> Result<()> {
+        let system = context.get_system();
+        let writer = OvaWriter::new(&instance.name);
+        let disk_size = system.get_path_size(disk);
+        let descriptor =
+            OvfDescriptor::new(instance, &writer.get_disk_name(), disk_size, capacity).render();
+
+        writer
+            .create_ova(
+                system.create_file(output)?,
+                &descriptor,
+                &mut system.open_file(disk)?,
+                disk_size,
+            )
+            .map_err(|error| Error::from_fs(FsOperation::WriteFile, output, error))
+    }
+}
+
+impl Command for ExportDiskCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let system = context.get_system();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        // A running guest keeps writing while the disk is read
+        if context.get_instance_store().is_running(&instance) {
+            return Err(Error::InstanceNotStopped(instance.name));
+        }
+
+        // The other hypervisors could not read the disk
+        if instance.encryption.is_some() {
+            return Err(Error::EncryptedDiskNotSupported(
+                instance.name,
+                "export".to_string(),
+            ));
+        }
+
+        let output = self.get_output(&instance);
+        if system.exists_path(&output) {
+            return Err(Error::FileAlreadyExists(
+                output.to_string_lossy().into_owned(),
+            ));
+        }
+
+        if self.format == ExportFormat::Ova {
+            // The disk is converted next to the archive, then packed into it
+            let disk = PathBuf::from(format!("{}.vmdk.tmp", output.to_string_lossy()));
+            let result = self
+                .export(console, context, &instance, &disk)
+                .and_then(|capacity| self.write_ova(context, &instance, &disk, capacity, &output));
+            system.remove_file(&disk).ok();
+            if result.is_err() {
+                system.remove_file(&output).ok();
+            }
+            result?;
+        } else {
+            self.export(console, context, &instance, &output)?;
+        }
+
+        console.info(&format!(
+            "Exported {} to {}",
+            instance.name,
+            output.display()
+        ));
+        // Secrets the guest sealed to the TPM won't unlock elsewhere
+        if instance.tpm {
+            console.warn(&format!(
+                "The TPM state of {} is not part of the export",
+                instance.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{DiskEncryption, Environment, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_context(
+        system: Rc<SystemMock>,
+        instance_store: InstanceStoreMock,
+    ) -> commands::Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        commands::Context::new(system, env, Box::new(instance_store))
+    }
+
+    fn build_instance() -> Instance {
+        Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_export_vdi_to_default_output() {
+        let system = Rc::new(SystemMock::new().add_command_output(
+            "qemu-img convert -p -f qcow2 -O vdi /data/machines/test/machine.img test.vdi",
+            b"    (100.00/100%)\r\n",
+        ));
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(
+            system.clone(),
+            InstanceStoreMock::new(vec![build_instance()]),
+        );
+
+        ExportDiskCommand::try_parse_from(["export-disk", "test", "--format", "vdi"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert_eq!(system.get_output(), "info: Exported test to test.vdi\n");
+    }
+
+    #[test]
+    fn test_export_ova_packs_descriptor_and_disk() {
+        let system = Rc::new(
+            // The mock leaves no converted disk behind, so one stands in
+            SystemMock::new()
+                .add_file("/out/test.ova.vmdk.tmp", b"vmdk")
+                .add_command_output(
+                    "qemu-img convert -p -f qcow2 -O vmdk -o subformat=streamOptimized /data/machines/test/machine.img /out/test.ova.vmdk.tmp",
+                    b"",
+                ),
+        );
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(
+            system.clone(),
+            InstanceStoreMock::new(vec![build_instance()]),
+        );
+
+        ExportDiskCommand::try_parse_from([
+            "export-disk",
+            "test",
+            "--format",
+            "ova",
+            "--output",
+            "/out/test.ova",
+        ])
+        .unwrap()
+        .run(console, &context)
+        .unwrap();
+
+        let ova = system.get_written_file("/out/test.ova").unwrap();
+        let mut archive = tar::Archive::new(&ova[..]);
+        let names = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                entry
+                    .unwrap()
+                    .path()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["test.ovf", "test-disk1.vmdk"]);
+        assert!(system.get_written_file("/out/test.ova.vmdk.tmp").is_none());
+    }
+
+    #[test]
+    fn test_export_running_instance_fails() {
+        let system = Rc::new(SystemMock::new());
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(
+            system.clone(),
+            InstanceStoreMock::new_with_running(vec![build_instance()], &["test"]),
+        );
+
+        let result = ExportDiskCommand::try_parse_from(["export-disk", "test", "--format", "raw"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InstanceNotStopped(name)) if name == "test"));
+    }
+
+    #[test]
+    fn test_export_encrypted_instance_fails() {
+        let system = Rc::new(SystemMock::new());
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(
+            system.clone(),
+            InstanceStoreMock::new(vec![Instance {
+                encryption: Some(DiskEncryption::File),
+                ..build_instance()
+            }]),
+        );
+
+        let result = ExportDiskCommand::try_parse_from(["export-disk", "test", "--format", "vmdk"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::EncryptedDiskNotSupported(..))));
+    }
+
+    #[test]
+    fn test_export_does_not_overwrite() {
+        let system = Rc::new(SystemMock::new().add_file("/out/test.vmdk", b""));
+        let console = &mut Console::new(system.as_ref());
+        let context = build_context(
+            system.clone(),
+            InstanceStoreMock::new(vec![build_instance()]),
+        );
+
+        let result = ExportDiskCommand::try_parse_from([
+            "export-disk",
+            "test",
+            "--format",
+            "vmdk",
+            "-o",
+            "/out/test.vmdk",
+        ])
+        .unwrap()
+        .run(console, &context);
+
+        assert!(matches!(result, Err(Error::FileAlreadyExists(file)) if file == "/out/test.vmdk"));
+    }
+}
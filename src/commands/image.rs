@@ -79,6 +79,7 @@ mod tests {
             checksum_url: String::new(),
             hash_alg: HashAlg::Sha512,
             size: None,
+            local: false,
         };
 
         // A cached image must return without touching the image directory
@@ -0,0 +1,103 @@
+use crate::commands::{self, Command};
+use crate::error::Result;
+use crate::models::{Backup, DataSize};
+use crate::qemu::QemuImg;
+use crate::util;
+use crate::view::{Alignment, Console, TableView};
+use clap::Parser;
+use std::path::PathBuf;
+
+/// List the backups in a backup directory
+#[derive(Parser)]
+pub struct ListBackupCommand {
+    /// Directory the backups are stored in
+    dir: PathBuf,
+}
+
+impl Command for ListBackupCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let system = context.get_system();
+        let qemu_img = QemuImg::new(system);
+
+        let mut instance_dirs = system.read_dir(&self.dir)?;
+        instance_dirs.sort();
+
+        let mut view = TableView::new();
+        view.add_row()
+            .add("Instance", Alignment::Left)
+            .add("Backup", Alignment::Left)
+            .add("Time", Alignment::Left)
+            .add("Type", Alignment::Left)
+            .add("Size", Alignment::Right)
+            .add("File", Alignment::Left);
+
+        for instance_dir in &instance_dirs {
+            let instance = instance_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            for backup in Backup::read_chain(system, instance_dir) {
+                // Only the blocks in the file itself, not the whole chain
+                let size = qemu_img
+                    .get_file_info(&backup.path.to_string_lossy())
+                    .map(|info| DataSize::new(info.actual_size as usize).to_size());
+                view.add_row()
+                    .add(&instance, Alignment::Left)
+                    .add(&backup.id.to_string(), Alignment::Left)
+                    .add(&util::format_utc(backup.id as i64), Alignment::Left)
+                    .add(&backup.kind.to_string(), Alignment::Left)
+                    .add(&util::format_or_na(size), Alignment::Right)
+                    .add(&backup.path.to_string_lossy(), Alignment::Left);
+            }
+        }
+
+        view.print(console);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_list_backups_in_chain_order() {
+        let system = Rc::new(
+            SystemMock::new()
+                .add_file("/backup/test/86400-inc.qcow2", b"")
+                .add_file("/backup/test/0-full.qcow2", b"")
+                .add_file("/backup/test/0.toml", b""),
+        );
+        let console = &mut Console::new(system.as_ref());
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let context = commands::Context::new(
+            system.clone(),
+            env,
+            Box::new(InstanceStoreMock::new(Vec::new())),
+        );
+
+        ListBackupCommand::try_parse_from(["ls", "/backup"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert_eq!(
+            system.get_output(),
+            "\
+Instance   Backup   Time                   Type          Size   File
+test       0        1970-01-01T00:00:00Z   full           n/a   /backup/test/0-full.qcow2
+test       86400    1970-01-02T00:00:00Z   incremental    n/a   /backup/test/86400-inc.qcow2
+"
+        );
+    }
+}
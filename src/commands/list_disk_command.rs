@@ -0,0 +1,131 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::Result;
+use crate::models::DataSize;
+use crate::qemu::QemuImg;
+use crate::util;
+use crate::view::{Alignment, Console, TableView};
+use clap::Parser;
+
+/// List data disks of a VM instance
+#[derive(Parser)]
+pub struct ListDiskCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+}
+
+impl Command for ListDiskCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let env = context.get_env();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if instance.disks.is_empty() {
+            console.print("No data disks are attached.");
+            console.print(&format!(
+                "Add one with cubic disk add {} <name> --size <size>",
+                instance.name
+            ));
+            return Ok(());
+        }
+
+        let qemu_img = QemuImg::new(context.get_system());
+        let mut view = TableView::new();
+        view.add_row()
+            .add("Name", Alignment::Left)
+            .add("Format", Alignment::Left)
+            .add("Size", Alignment::Right)
+            .add("File", Alignment::Left);
+
+        for disk in &instance.disks {
+            let path = disk.get_path(env, &instance.name);
+            let size = qemu_img
+                .get_file_info(&path)
+                .map(|info| DataSize::new(info.virtual_size as usize).to_size());
+            view.add_row()
+                .add(&disk.name, Alignment::Left)
+                .add(&disk.format, Alignment::Left)
+                .add(&util::format_or_na(size), Alignment::Right)
+                .add(&path, Alignment::Left);
+        }
+
+        view.print(console);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Disk, Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_list_disks_with_unreadable_size() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let context = commands::Context::new(
+            Rc::new(SystemMock::new()),
+            env,
+            Box::new(InstanceStoreMock::new(vec![Instance {
+                name: "test".to_string(),
+                disks: vec![Disk {
+                    name: "backup".to_string(),
+                    format: "raw".to_string(),
+                    file: Some("/srv/backup.img".to_string()),
+                }],
+                ..Instance::default()
+            }])),
+        );
+
+        ListDiskCommand::try_parse_from(["ls", "test"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert_eq!(
+            system.get_output(),
+            "\
+Name     Format   Size   File
+backup   raw       n/a   /srv/backup.img
+"
+        );
+    }
+
+    #[test]
+    fn test_list_without_disks_explains_how_to_add_one() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let context = commands::Context::new(
+            Rc::new(SystemMock::new()),
+            env,
+            Box::new(InstanceStoreMock::new(vec![Instance {
+                name: "test".to_string(),
+                ..Instance::default()
+            }])),
+        );
+
+        ListDiskCommand::try_parse_from(["ls", "test"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert_eq!(
+            system.get_output(),
+            "No data disks are attached.\nAdd one with cubic disk add test <name> --size <size>\n"
+        );
+    }
+}
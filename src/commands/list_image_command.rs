@@ -16,6 +16,7 @@ use clap::Parser;
 ///   debian:{11, bullseye}      amd64   343.8 MiB       no
 ///   debian:{10, buster}        amd64   301.7 MiB       no
 ///   debian:{13, trixie}        amd64   412.0 MiB      yes
+///   debian:tools               amd64     2.1 GiB    local
 ///   fedora:41                  amd64   468.9 MiB       no
 ///   fedora:42                  amd64   507.6 MiB       no
 ///   fedora:43                  amd64   556.3 MiB       no
@@ -64,7 +65,13 @@ impl Command for ListImageCommand {
                 .add(&image.arch.to_string(), Alignment::Left)
                 .add(&size, Alignment::Right)
                 .add(
-                    if ImageStore::new().exists(context.get_system(), context.get_env(), &image) {
+                    if image.local {
+                        "local"
+                    } else if ImageStore::new().exists(
+                        context.get_system(),
+                        context.get_env(),
+                        &image,
+                    ) {
                         "yes"
                     } else {
                         "no"
@@ -8,16 +8,18 @@ use clap::Parser;
 /// List ports for VM instances
 ///
 /// Shows port forwarding rules from VM instance to host. Use cubic modify <instance>
-/// to configure the forwarding. The SSH port is assigned by cubic and is shown by
+/// to configure the forwarding. Rules added without a host port (e.g. -p :80) show
+/// the port cubic picked for them. The SSH port is assigned by cubic and is shown by
 /// cubic show instead.
 ///
 /// Examples:
 ///
 ///   $ cubic ports
-///   Instance      Host             Guest   Protocol   In Use
-///   noble-arm64   127.0.0.1:2222   :22     /tcp       no
-///   trixie        127.0.0.1:4000   :4000   /tcp       yes
-///   trixie        0.0.0.0:80       :8000   /udp       yes
+///   Instance      Host                    Guest          Protocol   In Use
+///   noble-arm64   127.0.0.1:2222          :22            /tcp       no
+///   trixie        127.0.0.1:4000          :4000          /tcp       yes
+///   trixie        0.0.0.0:80              :8000          /udp       yes
+///   k3s           127.0.0.1:30000-30100   :30000-30100   /tcp       yes
 ///
 ///   $ cubic ports
 ///   No port forwarding rules are configured.
@@ -52,10 +54,10 @@ impl Command for ListPortCommand {
                 view.add_row()
                     .add(&instance_name, Alignment::Left)
                     .add(
-                        &format!("{}:{}", rule.get_host_ip(), rule.get_host_port()),
+                        &format!("{}:{}", rule.get_host_ip(), rule.format_host_ports()),
                         Alignment::Left,
                     )
-                    .add(&format!(":{}", rule.get_guest_port()), Alignment::Left)
+                    .add(&format!(":{}", rule.format_guest_ports()), Alignment::Left)
                     .add(&format!("/{}", rule.get_protocol()), Alignment::Left)
                     .add(status, Alignment::Left);
                 rule_count += 1;
@@ -126,6 +128,48 @@ Add one with cubic modify <instance> --port <host_port>:<guest_port>
         assert_eq!(system.get_output(), NO_RULES);
     }
 
+    #[test]
+    fn test_list_ports_shows_picked_port_of_auto_rule() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(vec![Instance {
+            name: "test".to_string(),
+            hostfwd: vec![serde_json::from_str("\"tcp:127.0.0.1:41000-:80,auto\"").unwrap()],
+            ..Instance::default()
+        }]);
+
+        ListPortCommand {}.run(console, &context).unwrap();
+
+        assert_eq!(
+            system.get_output(),
+            "\
+Instance   Host              Guest   Protocol   In Use
+test       127.0.0.1:41000   :80     /tcp       no
+"
+        );
+    }
+
+    #[test]
+    fn test_list_ports_shows_ranges_compactly() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(vec![Instance {
+            name: "test".to_string(),
+            hostfwd: vec!["30000-30100:30000-30100".parse().unwrap()],
+            ..Instance::default()
+        }]);
+
+        ListPortCommand {}.run(console, &context).unwrap();
+
+        assert_eq!(
+            system.get_output(),
+            "\
+Instance   Host                    Guest          Protocol   In Use
+test       127.0.0.1:30000-30100   :30000-30100   /tcp       no
+"
+        );
+    }
+
     #[test]
     fn test_list_ports_skips_instances_without_rules() {
         let system = SystemMock::new();
@@ -0,0 +1,201 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::Result;
+use crate::platform::System;
+use crate::view::Console;
+use clap::{ArgAction, Parser};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Show the serial console output of a VM instance
+///
+/// The output of the current run is kept along with the one before, so the
+/// messages of a boot that failed or a kernel panic remain after the fact.
+/// Neither has a size limit: the log of the current run grows for as long as
+/// the instance runs, and the next start moves it aside.
+///
+/// Examples:
+///
+///   Show the console output of 'my-instance':
+///   $ cubic logs my-instance
+///
+///   Show the last 50 lines since the instance was started:
+///   $ cubic logs my-instance --since-boot --tail 50
+///
+///   Keep printing new output while the instance runs:
+///   $ cubic logs my-instance --follow
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct LogsCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Keep printing new output until the instance stops
+    #[clap(short, long, action = ArgAction::SetTrue)]
+    follow: bool,
+    /// Only show the output since the instance was started last
+    #[clap(long, action = ArgAction::SetTrue)]
+    since_boot: bool,
+    /// Only show the last N lines
+    #[clap(short = 'n', long, value_name = "N")]
+    tail: Option<usize>,
+}
+
+impl LogsCommand {
+    fn select_lines<'a>(&self, previous: &'a str, current: &'a str) -> Vec<&'a str> {
+        let mut lines: Vec<&str> = if self.since_boot {
+            current.lines().collect()
+        } else {
+            previous.lines().chain(current.lines()).collect()
+        };
+        if let Some(tail) = self.tail {
+            lines.drain(..lines.len().saturating_sub(tail));
+        }
+        lines
+            .into_iter()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect()
+    }
+
+    // Prints what the log gained since `offset` in whole lines and returns
+    // the offset of the first byte left unprinted. Offsets count raw bytes,
+    // since the lossy decoding of invalid UTF-8 changes the length.
+    fn print_new_lines(&self, console: &mut Console<'_>, log: &[u8], offset: usize) -> usize {
+        // A restart starts the log over
+        let offset = if offset <= log.len() { offset } else { 0 };
+        let Some(end) = log[offset..].iter().rposition(|byte| *byte == b'\n') else {
+            return offset;
+        };
+        for line in String::from_utf8_lossy(&log[offset..offset + end]).lines() {
+            console.print(line.trim_end_matches('\r'));
+        }
+        offset + end + 1
+    }
+}
+
+impl Command for LogsCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let system = context.get_system();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        let log_file = PathBuf::from(
+            context
+                .get_env()
+                .get_instance_console_log_file(&instance.name),
+        );
+        let previous_file = PathBuf::from(format!("{}.1", log_file.display()));
+        let current = read_log(system, &log_file);
+        let previous = String::from_utf8_lossy(&read_log(system, &previous_file)).into_owned();
+
+        // A partial last line is printed once it is complete
+        let complete = current
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |end| end + 1);
+        let shown = if self.follow {
+            &current[..complete]
+        } else {
+            &current[..]
+        };
+        let shown = String::from_utf8_lossy(shown);
+        let lines = self.select_lines(&previous, &shown);
+        if lines.is_empty() && !self.follow {
+            console.info(&format!("No console output of {} yet", instance.name));
+        }
+        for line in lines {
+            console.print(line);
+        }
+
+        if self.follow {
+            let instance_store = context.get_instance_store();
+            let mut offset = complete;
+            while instance_store.is_running(&instance) {
+                thread::sleep(FOLLOW_INTERVAL);
+                offset = self.print_new_lines(console, &read_log(system, &log_file), offset);
+            }
+        }
+        Ok(())
+    }
+}
+
+// QEMU writes what the guest sends, which need not be valid UTF-8
+fn read_log(system: &dyn System, path: &Path) -> Vec<u8> {
+    let mut data = Vec::new();
+    if let Ok(mut file) = system.open_file(path) {
+        file.read_to_end(&mut data).ok();
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::SystemMock;
+
+    fn parse(args: &[&str]) -> LogsCommand {
+        LogsCommand::try_parse_from([&["logs", "test"], args].concat()).unwrap()
+    }
+
+    #[test]
+    fn test_select_lines_of_both_runs() {
+        assert_eq!(
+            parse(&[]).select_lines("old\r\n", "booting\r\nlogin:"),
+            ["old", "booting", "login:"]
+        );
+    }
+
+    #[test]
+    fn test_select_lines_since_boot() {
+        assert_eq!(
+            parse(&["--since-boot"]).select_lines("old\n", "booting\nlogin:"),
+            ["booting", "login:"]
+        );
+    }
+
+    #[test]
+    fn test_select_lines_tail() {
+        assert_eq!(
+            parse(&["--tail", "2"]).select_lines("a\nb\n", "c\nd\n"),
+            ["c", "d"]
+        );
+        assert_eq!(parse(&["-n", "5"]).select_lines("a\n", "b\n"), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_print_new_lines_holds_back_partial_line() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let command = parse(&["--follow"]);
+
+        let offset = command.print_new_lines(console, b"a\nb\nc", 2);
+
+        assert_eq!(offset, 4);
+        assert_eq!(system.get_output(), "b\n");
+    }
+
+    #[test]
+    fn test_print_new_lines_counts_invalid_utf8_as_raw_bytes() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let command = parse(&["--follow"]);
+
+        // The lossy decoding turns the single byte into three
+        let offset = command.print_new_lines(console, b"\xff\nb\n", 0);
+        let offset = command.print_new_lines(console, b"\xff\nb\nc\n", offset);
+
+        assert_eq!(offset, 6);
+        assert_eq!(system.get_output(), "\u{fffd}\nb\nc\n");
+    }
+
+    #[test]
+    fn test_read_log_of_missing_file_is_empty() {
+        let system = SystemMock::new();
+
+        assert!(read_log(&system, Path::new("/data/console.log")).is_empty());
+    }
+}
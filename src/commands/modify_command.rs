@@ -1,16 +1,20 @@
 use crate::actions::LoadInstanceAction;
 use crate::commands::{self, Command};
-use crate::error::Result;
-use crate::models::{DataSize, PortForward};
+use crate::error::{Error, FsOperation, Result};
+use crate::models::{DataSize, DiskLayout, Instance, PortForward};
+use crate::qemu::{MEMORY_HOTPLUG_ALIGNMENT, QemuImg, QemuMonitorClient, QemuSystem};
 use crate::view::Console;
 use clap::{ArgAction, Parser};
+use std::io::Read;
+use std::path::Path;
 
 /// Modify VM instances
 ///
 /// Use this command to change the settings of an existing VM instance (CPU, memory,
 /// disk, etc.). Port forwarding rules (--port/--rm-port) take effect immediately if
-/// the instance is running. All other changes are applied on the next (re-)start of
-/// the VM instance.
+/// the instance is running. So do more vCPUs (amd64 only) and any memory size,
+/// which the balloon takes away or hotplug adds. All other changes are applied
+/// on the next (re-)start of the VM instance.
 ///
 /// Examples:
 ///
@@ -23,6 +27,10 @@ use clap::{ArgAction, Parser};
 ///   Assign 200 GiB of storage to a VM instance:
 ///   $ cubic modify example3 --disk 200G
 ///
+///   Shrink the storage of a VM instance to 20 GiB (reads the partition table
+///   of the stopped instance to check that no partition reaches beyond it):
+///   $ cubic modify example3 --disk 20G --shrink
+///
 ///   Forward the VM instance's SSH port (TCP port 22) to the host on port 2222:
 ///   $ cubic modify example4 --port 2222:22
 ///
@@ -38,6 +46,34 @@ use clap::{ArgAction, Parser};
 ///   Allow network connection of a VM instance:
 ///   $ cubic modify example8 --no-isolate
 ///
+///   Give unused memory of a VM instance back to the host:
+///   $ cubic modify example9 --reclaim-memory
+///
+///   Bypass the host page cache for the disks of a VM instance:
+///   $ cubic modify example10 --disk-cache none --disk-aio native
+///
+///   Boot your own kernel with an initrd against the disk of a VM instance:
+///   $ cubic modify example11 --kernel ./bzImage --initrd ./initrd.img --append "root=/dev/vda1 console=ttyS0"
+///
+///   Boot the VM instance from its disk again:
+///   $ cubic modify example11 --no-kernel
+///
+///   Boot a VM instance with a firmware build of your own:
+///   $ cubic modify example12 --firmware ./OVMF.fd
+///
+///   Boot a VM instance with Secure Boot:
+///   $ cubic modify example13 --secure-boot
+///
+///   Attach a TPM to a VM instance:
+///   $ cubic modify example14 --tpm
+///
+///   Add a graphics card to a VM instance:
+///   $ cubic modify example15 --display
+///
+///   Forward the HTTP port of a VM instance to a free host port, and remove it again:
+///   $ cubic modify example16 --port :80
+///   $ cubic modify example16 --rm-port :80
+///
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct ModifyCommand {
@@ -52,7 +88,10 @@ pub struct ModifyCommand {
     /// Disk size of the virtual machine instance  (e.g. 10G for 10 gigabytes)
     #[clap(short, long)]
     disk: Option<DataSize>,
-    /// Add port forwarding rule (format: [host_ip:]host_port:guest_port[/(udp|tcp)], e.g. -p 8000:80/tcp)
+    /// Allow a --disk size below the current one
+    #[clap(long, requires = "disk", default_value_t = false)]
+    shrink: bool,
+    /// Add port forwarding rule (format: [host_ip:][host_port[-end]]:guest_port[-end][/(udp|tcp)], e.g. -p 8000:80/tcp, -p 30000-30100:30000-30100 or -p :80 for a free host port)
     #[clap(short, long)]
     port: Vec<PortForward>,
     /// Remove port forwarding rule (e.g. -P 8000:80)
@@ -64,6 +103,16 @@ pub struct ModifyCommand {
     /// Do not isolate VM instance from network (default)
     #[clap(long, overrides_with = "isolate", action = ArgAction::SetTrue)]
     no_isolate: bool,
+    /// Give unused guest memory back to the host
+    #[clap(long, overrides_with = "no_reclaim_memory", action = ArgAction::SetTrue)]
+    reclaim_memory: bool,
+    /// Keep guest memory assigned to the instance (default)
+    #[clap(long, overrides_with = "reclaim_memory", action = ArgAction::SetTrue)]
+    no_reclaim_memory: bool,
+    #[clap(flatten)]
+    disk_settings: commands::DiskSettingsArg,
+    #[clap(flatten)]
+    boot: commands::BootArg,
 }
 
 impl Command for ModifyCommand {
@@ -72,19 +121,84 @@ impl Command for ModifyCommand {
         let mut instance =
             LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
 
+        if let Some(disk) = &self.disk
+            && self.shrink
+            && disk.get_bytes() < instance.disk_capacity.get_bytes()
+        {
+            if instance_store.is_running(&instance) {
+                return Err(Error::InstanceNotStopped(instance.name));
+            }
+            if instance.encryption.is_some() {
+                return Err(Error::EncryptedDiskNotSupported(
+                    instance.name,
+                    "shrink".to_string(),
+                ));
+            }
+            Self::check_disk_layout(console, context, &instance, disk)?;
+        }
+
+        // Checked before anything takes effect
+        let secure_boot = instance.secure_boot;
+        self.boot.apply(context.get_system(), &mut instance)?;
+        // The variable store of the other firmware lacks or holds the keys,
+        // so the next start copies the matching template
+        if instance.secure_boot != secure_boot {
+            let nvram = context.get_env().get_instance_nvram_file(&instance.name);
+            let nvram = Path::new(&nvram);
+            if context.get_system().exists_path(nvram) {
+                context.get_system().remove_file(nvram)?;
+            }
+        }
+
         let is_running = instance_store.is_running(&instance);
         let hostfwd_changed = !self.port.is_empty() || !self.rm_port.is_empty();
+        // Rules to remove are looked up, since an automatic one is named
+        // without the host port it got
+        let removed = self
+            .rm_port
+            .iter()
+            .map(|rm| {
+                instance
+                    .hostfwd
+                    .iter()
+                    .find(|rule| rm.matches(rule))
+                    .unwrap_or(rm)
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        let mut added = self.port.clone();
+        for fwd in &mut added {
+            fwd.allocate_host_port(context.get_system())?;
+        }
+        let hostfwd = instance
+            .hostfwd
+            .iter()
+            .chain(&added)
+            .filter(|rule| !self.rm_port.iter().any(|rm| rm.matches(rule)))
+            .cloned()
+            .collect::<Vec<_>>();
+        PortForward::check_count(&instance.name, &hostfwd)?;
 
         if is_running && hostfwd_changed {
             let mut monitor = instance_store.get_monitor(&instance)?;
-            for fwd in &self.rm_port {
+            for fwd in &removed {
                 monitor.remove_hostfwd(fwd)?;
             }
-            for fwd in &self.port {
+            for fwd in &added {
                 monitor.add_hostfwd(fwd)?;
             }
         }
 
+        if is_running && (self.cpus.is_some() || self.memory.is_some()) {
+            if self.cpus.is_some() && !QemuSystem::supports_cpu_hotplug(instance.arch) {
+                return Err(Error::CpuHotplugNotSupported(
+                    instance.name.clone(),
+                    instance.arch,
+                ));
+            }
+            self.apply_live(&instance.name, &mut instance_store.get_monitor(&instance)?)?;
+        }
+
         if is_running {
             console.info("Note: changes may require a restart to take effect.");
         }
@@ -98,7 +212,11 @@ impl Command for ModifyCommand {
         }
 
         if let Some(disk) = &self.disk {
-            instance_store.resize(&mut instance, disk.get_bytes() as u64)?;
+            if self.shrink {
+                instance_store.shrink(&mut instance, disk.get_bytes() as u64)?;
+            } else {
+                instance_store.resize(&mut instance, disk.get_bytes() as u64)?;
+            }
         }
 
         if self.isolate {
@@ -107,20 +225,130 @@ impl Command for ModifyCommand {
             instance.isolate = false;
         }
 
-        instance.hostfwd.append(&mut self.port.clone());
-        instance.hostfwd.retain(|p| !self.rm_port.contains(p));
+        self.disk_settings
+            .apply(&instance.name, &mut instance.disk_settings)?;
+
+        if self.reclaim_memory {
+            instance.reclaim_memory = true;
+        } else if self.no_reclaim_memory {
+            instance.reclaim_memory = false;
+        }
+
+        instance.hostfwd = hostfwd;
 
         instance_store.store(&instance)?;
         Ok(())
     }
 }
 
+impl ModifyCommand {
+    // Boots the instance to ask the guest how much of its disk it uses. The
+    // instance is stopped again either way.
+    // Read from the image rather than the running guest, whose growpart
+    // would stretch the last partition to the full disk again on every boot
+    fn check_disk_layout(
+        console: &mut Console<'_>,
+        context: &commands::Context,
+        instance: &Instance,
+        size: &DataSize,
+    ) -> Result<()> {
+        let env = context.get_env();
+        let system = context.get_system();
+        let head_file = env.get_instance_disk_head_file(&instance.name);
+        let head_path = Path::new(&head_file);
+
+        let head = QemuImg::new(system)
+            .read_head(
+                &env.get_instance_image_file(&instance.name),
+                &head_file,
+                DiskLayout::HEAD_SIZE,
+            )
+            .and_then(|_| {
+                let mut head = Vec::new();
+                system
+                    .open_file(head_path)?
+                    .read_to_end(&mut head)
+                    .map_err(|e| Error::from_fs(FsOperation::ReadFile, head_path, e))?;
+                Ok(head)
+            });
+        if system.exists_path(head_path) {
+            system.remove_file(head_path)?;
+        }
+
+        // Without a known layout there is no telling what the shrink would cut off
+        let layout = DiskLayout::parse(&head?)
+            .ok_or_else(|| Error::UnknownDiskLayout(instance.name.clone()))?;
+        if layout.get_end() > size.get_bytes() as u64 {
+            return Err(Error::ShrinkBelowUsage(
+                instance.name.clone(),
+                DataSize::new(layout.get_end() as usize).to_size(),
+            ));
+        }
+        if let DiskLayout::Gpt { .. } = layout {
+            console.warn(&format!(
+                "The backup GPT header at the end of the disk of instance '{}' is cut off. The guest boots from the primary header, and growpart or `sudo sgdisk -e` in the guest writes a new backup.",
+                instance.name
+            ));
+        }
+        Ok(())
+    }
+
+    // Only ever adds vCPUs, since removing them needs the guest to let go of
+    // them first. Fewer vCPUs take effect on the next start.
+    fn apply_live(&self, name: &str, monitor: &mut QemuMonitorClient) -> Result<()> {
+        // Checked before anything changes, so a refused size leaves the
+        // running instance as it was
+        let memory = match &self.memory {
+            Some(memory) => {
+                let size = memory.get_bytes() as u64;
+                let current = monitor.get_memory_size()?;
+                Self::check_memory_hotplug(name, size, current)?;
+                Some((size, current))
+            }
+            None => None,
+        };
+
+        if let Some(cpus) = self.cpus {
+            let online = monitor.get_cpu_count()?;
+            if cpus > online {
+                monitor.add_cpus(name, cpus - online)?;
+            }
+        }
+
+        // Memory beyond the current size is hot-added, anything below it is
+        // taken away by the balloon
+        if let Some((size, current)) = memory {
+            if size > current {
+                monitor.add_memory(size - current)?;
+                // Deflates a balloon an earlier shrink left behind. A guest
+                // without balloon driver has nothing to deflate.
+                monitor.set_balloon(size).ok();
+            } else {
+                monitor.set_balloon(size)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_memory_hotplug(name: &str, size: u64, current: u64) -> Result<()> {
+        if size > current && !(size - current).is_multiple_of(MEMORY_HOTPLUG_ALIGNMENT) {
+            return Err(Error::MemoryHotplugNotAligned(
+                name.to_string(),
+                DataSize::new(MEMORY_HOTPLUG_ALIGNMENT as usize).to_size(),
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::instance::InstanceStoreMock;
-    use crate::models::{Environment, Instance, UserName};
-    use crate::platform::SystemMock;
+    use crate::models::{Arch, Environment, Instance, UserName};
+    use crate::platform::{FileSystem, SystemMock};
+    use std::path::PathBuf;
     use std::rc::Rc;
     use std::str::FromStr;
 
@@ -167,7 +395,7 @@ mod tests {
             &["test"],
         ));
 
-        ModifyCommand::try_parse_from(["modify", "test", "--cpus", "2"])
+        ModifyCommand::try_parse_from(["modify", "test", "--isolate"])
             .unwrap()
             .run(console, &context)
             .unwrap();
@@ -178,6 +406,151 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_modify_running_instance_memory_attempts_live_apply() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(InstanceStoreMock::new_with_running(
+            vec![Instance {
+                name: "test".to_string(),
+                ..Instance::default()
+            }],
+            &["test"],
+        ));
+
+        // Like the port test below, the mock has no monitor to set the
+        // balloon, so the error shows the live path was taken.
+        let result = ModifyCommand::try_parse_from(["modify", "test", "--memory", "1G"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_modify_running_instance_cpus_attempts_live_apply() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(InstanceStoreMock::new_with_running(
+            vec![Instance {
+                name: "test".to_string(),
+                arch: Arch::AMD64,
+                ..Instance::default()
+            }],
+            &["test"],
+        ));
+
+        let result = ModifyCommand::try_parse_from(["modify", "test", "--cpus", "4"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InstanceNotRunning(_))));
+    }
+
+    #[test]
+    fn test_modify_running_arm64_instance_rejects_cpu_hotplug() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let store = InstanceStoreMock::new_with_running(
+            vec![Instance {
+                name: "test".to_string(),
+                arch: Arch::ARM64,
+                ..Instance::default()
+            }],
+            &["test"],
+        );
+        let stored = store.stored.clone();
+        let context = build_context(store);
+
+        let result = ModifyCommand::try_parse_from(["modify", "test", "--cpus", "4"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(
+            result,
+            Err(Error::CpuHotplugNotSupported(ref name, Arch::ARM64)) if name == "test"
+        ));
+        assert!(stored.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_modify_toggles_memory_reclaim() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let store = InstanceStoreMock::new(vec![Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        }]);
+        let stored = store.stored.clone();
+        let context = build_context(store);
+
+        ModifyCommand::try_parse_from(["modify", "test", "--reclaim-memory"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert!(stored.lock().unwrap()[0].reclaim_memory);
+    }
+
+    #[test]
+    fn test_modify_rejects_missing_kernel_before_any_change() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let store = InstanceStoreMock::new(vec![Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        }]);
+        let stored = store.stored.clone();
+        let context = build_context(store);
+
+        let result = ModifyCommand::try_parse_from([
+            "modify",
+            "test",
+            "--cpus",
+            "4",
+            "--kernel",
+            "/srv/bzImage",
+        ])
+        .unwrap()
+        .run(console, &context);
+
+        assert!(matches!(
+            result,
+            Err(Error::BootFileNotFound(_, ref kind, ref path))
+                if kind == "kernel" && path == "/srv/bzImage"
+        ));
+        assert!(stored.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_modify_secure_boot_drops_the_variable_store() {
+        let nvram = PathBuf::from("machines").join("test").join("nvram.fd");
+        let system = Rc::new(SystemMock::new().add_file(&nvram.to_string_lossy(), b"vars"));
+        let console = &mut Console::new(system.as_ref());
+        let store = InstanceStoreMock::new(vec![Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        }]);
+        let stored = store.stored.clone();
+        let context = commands::Context::new(
+            system.clone(),
+            Environment::new(
+                UserName::from_str("cubic").unwrap(),
+                String::new(),
+                String::new(),
+            ),
+            Box::new(store),
+        );
+
+        ModifyCommand::try_parse_from(["modify", "test", "--secure-boot"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert!(stored.lock().unwrap()[0].secure_boot);
+        assert!(!system.exists_path(&nvram));
+    }
+
     #[test]
     fn test_modify_running_instance_port_attempts_live_apply() {
         let system = SystemMock::new();
@@ -201,4 +574,158 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(system.get_output(), "");
     }
+
+    #[test]
+    fn test_modify_auto_ports() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let mut forward: PortForward = ":80".parse().unwrap();
+        forward.allocate_host_port(&system).unwrap();
+        let store = InstanceStoreMock::new(vec![Instance {
+            name: "test".to_string(),
+            hostfwd: vec![forward],
+            ..Instance::default()
+        }]);
+        let stored = store.stored.clone();
+        let context = build_context(store);
+
+        ModifyCommand::try_parse_from(["modify", "test", "-P", ":80", "-p", ":443"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        let hostfwd = &stored.lock().unwrap()[0].hostfwd;
+        assert_eq!(hostfwd.len(), 1);
+        assert_eq!(hostfwd[0].get_guest_port(), 443);
+        assert!(hostfwd[0].is_auto());
+        assert_ne!(hostfwd[0].get_host_port(), 0);
+    }
+
+    #[test]
+    fn test_check_memory_hotplug() {
+        const MIB: u64 = 1024 * 1024;
+
+        assert!(ModifyCommand::check_memory_hotplug("test", 6144 * MIB, 4096 * MIB).is_ok());
+        // Shrinking goes through the balloon, which takes any size
+        assert!(ModifyCommand::check_memory_hotplug("test", 3000 * MIB, 4096 * MIB).is_ok());
+        assert!(matches!(
+            ModifyCommand::check_memory_hotplug("test", 4097 * MIB, 4096 * MIB),
+            Err(Error::MemoryHotplugNotAligned(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_shrink_requires_disk() {
+        assert!(ModifyCommand::try_parse_from(["modify", "test", "--shrink"]).is_err());
+    }
+
+    #[test]
+    fn test_shrink_rejects_running_instance() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(InstanceStoreMock::new_with_running(
+            vec![Instance {
+                name: "test".to_string(),
+                disk_capacity: DataSize::from_str("10G").unwrap(),
+                ..Instance::default()
+            }],
+            &["test"],
+        ));
+
+        let result = ModifyCommand::try_parse_from(["modify", "test", "--disk", "5G", "--shrink"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InstanceNotStopped(ref name)) if name == "test"));
+    }
+
+    // The start of a GPT disk whose only partition ends at the given byte
+    fn build_gpt_head(end: u64) -> Vec<u8> {
+        let mut head = vec![0; 2048];
+        head[510..512].copy_from_slice(&[0x55, 0xaa]);
+        head[512..520].copy_from_slice(b"EFI PART");
+        head[584..592].copy_from_slice(&2u64.to_le_bytes());
+        head[592..596].copy_from_slice(&1u32.to_le_bytes());
+        head[596..600].copy_from_slice(&128u32.to_le_bytes());
+        head[1024..1040].fill(0xaa);
+        head[1064..1072].copy_from_slice(&(end / 512 - 1).to_le_bytes());
+        head
+    }
+
+    // A host whose qemu-img finds the given start on the disk of "test"
+    fn build_shrink_system(head: &[u8]) -> Rc<SystemMock> {
+        let dir = PathBuf::from("machines").join("test");
+        Rc::new(
+            SystemMock::new()
+                .add_command_output(
+                    &format!(
+                        "qemu-img dd -f qcow2 -O raw bs=1048576 count=1 if={} of={}",
+                        dir.join("machine.img").to_string_lossy(),
+                        dir.join("disk_head").to_string_lossy()
+                    ),
+                    b"",
+                )
+                .add_file(&dir.join("disk_head").to_string_lossy(), head),
+        )
+    }
+
+    fn run_shrink(system: Rc<SystemMock>, size: &str) -> Result<()> {
+        let console = &mut Console::new(system.as_ref());
+        let context = commands::Context::new(
+            system.clone(),
+            Environment::new(
+                UserName::from_str("cubic").unwrap(),
+                String::new(),
+                String::new(),
+            ),
+            Box::new(InstanceStoreMock::new(vec![Instance {
+                name: "test".to_string(),
+                disk_capacity: DataSize::from_str("10G").unwrap(),
+                ..Instance::default()
+            }])),
+        );
+
+        ModifyCommand::try_parse_from(["modify", "test", "--disk", size, "--shrink"])
+            .unwrap()
+            .run(console, &context)
+    }
+
+    #[test]
+    fn test_shrink_rejects_a_last_partition_that_fills_the_disk() {
+        let head = PathBuf::from("machines").join("test").join("disk_head");
+        let system = build_shrink_system(&build_gpt_head(
+            DataSize::from_str("10G").unwrap().get_bytes() as u64,
+        ));
+
+        let result = run_shrink(system.clone(), "5G");
+
+        assert!(matches!(
+            result,
+            Err(Error::ShrinkBelowUsage(ref name, ref end)) if name == "test" && end == "10.0 GiB"
+        ));
+        assert!(!system.exists_path(&head));
+    }
+
+    #[test]
+    fn test_shrink_keeps_a_shrunk_partition() {
+        let head = PathBuf::from("machines").join("test").join("disk_head");
+        let system = build_shrink_system(&build_gpt_head(
+            DataSize::from_str("4G").unwrap().get_bytes() as u64,
+        ));
+
+        run_shrink(system.clone(), "5G").unwrap();
+
+        assert!(system.get_output().contains("backup GPT header"));
+        assert!(!system.exists_path(&head));
+    }
+
+    #[test]
+    fn test_shrink_rejects_an_unknown_layout() {
+        let system = build_shrink_system(&[0; 2048]);
+
+        assert!(matches!(
+            run_shrink(system, "5G"),
+            Err(Error::UnknownDiskLayout(ref name)) if name == "test"
+        ));
+    }
 }
@@ -0,0 +1,70 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::view::Console;
+use clap::Parser;
+
+const PROMPT: &str = "(qemu) ";
+
+/// Open the QEMU monitor of a VM instance
+///
+/// Opens an interactive prompt for the QEMU Human Monitor Protocol (HMP).
+/// Other commands cannot reach the monitor while the prompt is open.
+///
+/// Examples:
+///
+///   Open the monitor of 'my-instance':
+///   $ cubic monitor my-instance
+///   Type 'help' to list the commands and 'exit' or Ctrl+D to leave.
+///   Note: 'quit' ends the QEMU process of the instance.
+///   (qemu) info status
+///   VM status: running
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct MonitorCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+}
+
+impl Command for MonitorCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if !instance_store.is_running(&instance) {
+            return Err(Error::InstanceNotRunning(instance.name));
+        }
+
+        let mut monitor = instance_store.get_monitor(&instance)?;
+        console.info("Type 'help' to list the commands and 'exit' or Ctrl+D to leave.");
+        console.info("Note: 'quit' ends the QEMU process of the instance.");
+
+        while let Some(line) = console.prompt_line(PROMPT) {
+            match line.as_str() {
+                "" => continue,
+                "exit" => break,
+                _ => match monitor.run_hmp_command(&line) {
+                    Ok(output) if output.is_empty() => {}
+                    Ok(output) => console.print(&output),
+                    // A rejected command is no reason to end the session
+                    Err(e @ Error::MonitorCommandFailed(_)) => console.error(&e.to_string()),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_path_traversal() {
+        assert!(MonitorCommand::try_parse_from(["monitor", "../../etc"]).is_err());
+    }
+}
@@ -1,16 +1,18 @@
 use crate::commands::{self, Command};
 use crate::error::Result;
-use crate::models::DataSize;
+use crate::image::LocalImages;
+use crate::models::{DataSize, Environment};
+use crate::platform::System;
 use crate::view::{ConfirmDialog, Console};
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const LEGACY_INSTANCES_DIR: &str = "instances";
 
 /// Clear caches
 ///
 /// This command removes cached VM image files and instance files left behind
-/// by older versions of cubic.
+/// by older versions of cubic. Local images saved by `cubic commit` are kept.
 ///
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
@@ -19,21 +21,52 @@ pub struct PruneCommand {
     yes: commands::YesArg,
 }
 
+impl PruneCommand {
+    // Local images are no cache, they only exist on this host
+    fn get_image_paths(system: &dyn System, env: &Environment) -> Vec<PathBuf> {
+        let image_dir = PathBuf::from(env.get_image_dir());
+        let local_image_file = PathBuf::from(env.get_local_image_file());
+        let local_images = LocalImages::read_from_file(system, &local_image_file);
+        if local_images.images.is_empty() {
+            return vec![image_dir];
+        }
+
+        let local_files: Vec<PathBuf> = local_images
+            .images
+            .iter()
+            .map(|image| image_dir.join(image.to_file_name()))
+            .chain([local_image_file])
+            .collect();
+        system
+            .read_dir(&image_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|path| !local_files.contains(path))
+            .collect()
+    }
+
+    fn remove_path(system: &dyn System, path: &Path) {
+        if system.exists_dir(path) {
+            system.remove_dir(path).ok();
+        } else {
+            system.remove_file(path).ok();
+        }
+    }
+}
+
 impl Command for PruneCommand {
     fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
         let env = context.get_env();
         let system = context.get_system();
 
-        let dirs = [
-            PathBuf::from(env.get_image_dir()),
-            PathBuf::from(env.get_cache_dir()).join(LEGACY_INSTANCES_DIR),
-        ];
+        let mut paths = Self::get_image_paths(system, env);
+        paths.push(PathBuf::from(env.get_cache_dir()).join(LEGACY_INSTANCES_DIR));
+        paths.push(PathBuf::from(env.get_image_cache_file()));
 
         // Calculate size
-        let cache_file = PathBuf::from(env.get_image_cache_file());
         let total = DataSize::new(
-            dirs.iter()
-                .chain([&cache_file])
+            paths
+                .iter()
                 .fold(0, |total, path| total + system.get_path_size(path)) as usize,
         )
         .to_size();
@@ -45,9 +78,8 @@ impl Command for PruneCommand {
             || ConfirmDialog::new("Are you sure you want to continue?").confirm(console)
         {
             // Delete files
-            system.remove_file(&cache_file).ok();
-            for dir in &dirs {
-                system.remove_dir(dir).ok();
+            for path in &paths {
+                Self::remove_path(system, path);
             }
 
             // Print size of deleted files
@@ -62,9 +94,8 @@ impl Command for PruneCommand {
 mod tests {
     use super::*;
     use crate::instance::InstanceStoreMock;
-    use crate::models::{Environment, UserName};
-    use crate::platform::{FileSystem, System, SystemMock};
-    use std::path::Path;
+    use crate::models::{Arch, HashAlg, Image, UserName};
+    use crate::platform::{FileSystem, SystemMock};
     use std::rc::Rc;
     use std::str::FromStr;
 
@@ -133,4 +164,36 @@ mod tests {
 
         assert!(run_prune(&system, &env).contains("frees 2.0 KiB"));
     }
+
+    #[test]
+    fn test_keep_the_committed_images() {
+        let env = build_env();
+        let image = Image {
+            vendor: "debian".to_string(),
+            names: vec!["tools".to_string()],
+            arch: Arch::AMD64,
+            image_url: String::new(),
+            checksum_url: String::new(),
+            hash_alg: HashAlg::Sha256,
+            size: None,
+            local: true,
+        };
+        let local_image_file = format!("{}/{}", env.get_image_dir(), image.to_file_name());
+        let cached_image_file = format!("{}/debian_trixie_amd64", env.get_image_dir());
+        let system = Rc::new(
+            SystemMock::new()
+                .add_file(&cached_image_file, &[0; 1024])
+                .add_file(&local_image_file, &[0; 1024]),
+        );
+        LocalImages {
+            images: vec![image],
+        }
+        .write_to_file(system.as_ref(), Path::new(&env.get_local_image_file()))
+        .unwrap();
+
+        assert!(run_prune(&system, &env).contains("frees 1.0 KiB"));
+        assert!(!system.exists_path(Path::new(&cached_image_file)));
+        assert!(system.exists_path(Path::new(&local_image_file)));
+        assert!(system.exists_path(Path::new(&env.get_local_image_file())));
+    }
 }
@@ -0,0 +1,77 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::view::Console;
+use clap::Parser;
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Run a QMP command on a VM instance
+///
+/// Sends a single command to the QEMU Machine Protocol (QMP) monitor of a
+/// running instance and prints the reply as JSON.
+///
+/// Examples:
+///
+///   Query the run state of 'my-instance':
+///   $ cubic qmp my-instance query-status
+///   {
+///     "running": true,
+///     "status": "running"
+///   }
+///
+///   Pass arguments as JSON object:
+///   $ cubic qmp my-instance balloon '{"value": 2147483648}'
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct QmpCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// QMP command name (e.g. 'query-status')
+    command: String,
+    /// Command arguments as JSON object (e.g. '{"device": "drive0"}')
+    #[clap(value_parser = Value::from_str)]
+    arguments: Option<Value>,
+}
+
+impl Command for QmpCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if !instance_store.is_running(&instance) {
+            return Err(Error::InstanceNotRunning(instance.name));
+        }
+
+        let reply = instance_store
+            .get_monitor(&instance)?
+            .execute_raw(&self.command, self.arguments.clone().unwrap_or(Value::Null))?;
+        console.print(&serde_json::to_string_pretty(&reply).map_err(Error::from)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_path_traversal() {
+        assert!(QmpCommand::try_parse_from(["qmp", "../../etc", "query-status"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_json_arguments() {
+        let cmd =
+            QmpCommand::try_parse_from(["qmp", "test", "balloon", r#"{"value": 1024}"#]).unwrap();
+
+        assert_eq!(cmd.arguments.unwrap()["value"], 1024);
+    }
+
+    #[test]
+    fn test_reject_invalid_json_arguments() {
+        assert!(QmpCommand::try_parse_from(["qmp", "test", "balloon", "{value"]).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use crate::error::{Error, FsOperation, Result};
+use crate::platform::System;
+use crate::recording::CastWriter;
+use crate::view::Console;
+use clap::Parser;
+use std::path::PathBuf;
+
+// Size to record when the output is no terminal
+const DEFAULT_GEOMETRY: (u32, u32) = (80, 24);
+
+#[derive(Parser, Clone, Default)]
+pub struct RecordArg {
+    /// Record the session to an asciinema v2 file, which `cubic replay` plays back
+    #[clap(long = "record", value_name = "FILE")]
+    pub file: Option<PathBuf>,
+}
+
+impl RecordArg {
+    pub fn create_writer(
+        &self,
+        console: &Console<'_>,
+        system: &dyn System,
+    ) -> Result<Option<CastWriter>> {
+        let Some(path) = &self.file else {
+            return Ok(None);
+        };
+        let (width, height) = console.get_geometry().unwrap_or(DEFAULT_GEOMETRY);
+        CastWriter::new(system.create_file(path)?, width, height)
+            .map(Some)
+            .map_err(|error| Error::from_fs(FsOperation::WriteFile, path, error))
+    }
+}
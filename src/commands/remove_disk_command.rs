@@ -0,0 +1,146 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::models::DiskName;
+use crate::view::{ConfirmDialog, Console};
+use clap::Parser;
+use std::path::Path;
+
+/// Remove a data disk from a VM instance
+///
+/// Detaches the disk, right away if the instance is running. A disk that cubic
+/// created is deleted, an attached disk image is left in place.
+#[derive(Parser)]
+pub struct RemoveDiskCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Name of the disk
+    name: DiskName,
+    #[clap(flatten)]
+    yes: commands::YesArg,
+}
+
+impl Command for RemoveDiskCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let env = context.get_env();
+        let instance_store = context.get_instance_store();
+        let mut instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        let disk = instance
+            .disks
+            .iter()
+            .find(|disk| disk.name == self.name.as_str())
+            .cloned()
+            .ok_or_else(|| Error::UnknownDisk(instance.name.clone(), self.name.to_string()))?;
+        let path = disk.get_path(env, &instance.name);
+
+        if disk.is_owned()
+            && !self.yes.value
+            && !ConfirmDialog::new(&format!(
+                "The disk '{}' and all its data are going to be deleted.\nDo you want to proceed?",
+                disk.name
+            ))
+            .confirm(console)
+        {
+            return Ok(());
+        }
+
+        if instance_store.is_running(&instance) {
+            instance_store
+                .get_monitor(&instance)?
+                .detach_disk(&instance.name, &disk)?;
+        }
+
+        instance.disks.retain(|other| other.name != disk.name);
+        instance_store.store(&instance)?;
+
+        if disk.is_owned() {
+            context.get_system().remove_file(Path::new(&path))?;
+        }
+
+        console.info(&format!("Removed disk '{}'", disk.name));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Disk, Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_context(instance_store: InstanceStoreMock) -> commands::Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        commands::Context::new(Rc::new(SystemMock::new()), env, Box::new(instance_store))
+    }
+
+    fn build_instance() -> Instance {
+        Instance {
+            name: "test".to_string(),
+            disks: vec![Disk {
+                name: "backup".to_string(),
+                format: "raw".to_string(),
+                file: Some("/srv/backup.img".to_string()),
+            }],
+            ..Instance::default()
+        }
+    }
+
+    #[test]
+    fn test_reject_unknown_disk() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(InstanceStoreMock::new(vec![build_instance()]));
+
+        let result = RemoveDiskCommand::try_parse_from(["rm", "test", "data"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(
+            result,
+            Err(Error::UnknownDisk(ref instance, ref disk)) if instance == "test" && disk == "data"
+        ));
+    }
+
+    #[test]
+    fn test_remove_attached_disk_keeps_its_file() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let store = InstanceStoreMock::new(vec![build_instance()]);
+        let stored = store.stored.clone();
+        let context = build_context(store);
+
+        // No confirmation is asked, since the file stays where it is
+        RemoveDiskCommand::try_parse_from(["rm", "test", "backup"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert!(stored.lock().unwrap()[0].disks.is_empty());
+    }
+
+    #[test]
+    fn test_remove_from_running_instance_detaches_first() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let store = InstanceStoreMock::new_with_running(vec![build_instance()], &["test"]);
+        let stored = store.stored.clone();
+        let context = build_context(store);
+
+        // The mock has no monitor, so the disk stays configured
+        let result = RemoveDiskCommand::try_parse_from(["rm", "test", "backup"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(result.is_err());
+        assert!(stored.lock().unwrap().is_empty());
+    }
+}
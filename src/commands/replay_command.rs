@@ -0,0 +1,163 @@
+use crate::commands::{self, Command};
+use crate::error::{Error, FsOperation, Result};
+use crate::recording::{Cast, CastEvent};
+use crate::util;
+use crate::view::Console;
+use clap::Parser;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// Keys that end a replay early. Ctrl+C arrives as a byte in raw mode.
+const QUIT_KEYS: [u8; 3] = [b'q', b'Q', 0x03];
+
+/// Replay a recorded terminal session
+///
+/// Plays back a recording of `cubic console --record` or `cubic ssh --record`,
+/// or any other asciinema v2 file. Press q or Ctrl+C to stop.
+///
+/// Examples:
+///
+///   Replay session.cast:
+///   $ cubic replay session.cast
+///
+///   Replay it twice as fast:
+///   $ cubic replay session.cast --speed 2
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct ReplayCommand {
+    /// Recording to play back
+    file: PathBuf,
+    /// Playback speed, e.g. 2 for twice and 0.5 for half as fast
+    #[clap(short, long, default_value_t = 1.0, value_parser = parse_speed)]
+    speed: f64,
+}
+
+impl ReplayCommand {
+    // Time since the start of the replay at which the event is shown
+    fn get_offset(&self, event: &CastEvent) -> Duration {
+        Duration::from_secs_f64(event.time.max(0.0) / self.speed)
+    }
+
+    async fn play(&self, cast: &Cast) {
+        let start = tokio::time::Instant::now();
+        let mut stdout = tokio::io::stdout();
+        for event in &cast.events {
+            tokio::time::sleep_until(start + self.get_offset(event)).await;
+            if stdout.write_all(event.data.as_bytes()).await.is_err() {
+                return;
+            }
+            stdout.flush().await.ok();
+        }
+    }
+}
+
+impl Command for ReplayCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let mut text = String::new();
+        context
+            .get_system()
+            .open_file(&self.file)?
+            .read_to_string(&mut text)
+            .map_err(|error| Error::from_fs(FsOperation::ReadFile, &self.file, error))?;
+        let cast = Cast::parse(&text)
+            .map_err(|error| Error::from_fs(FsOperation::ReadFile, &self.file, error))?;
+
+        if let Some((width, height)) = console.get_geometry()
+            && (width < cast.width || height < cast.height)
+        {
+            console.warn(&format!(
+                "The recording needs a terminal of {}x{}, this one has {width}x{height}",
+                cast.width, cast.height
+            ));
+        }
+
+        console.raw_mode();
+        util::AsyncCaller::new().call(async {
+            tokio::select!(
+                _ = self.play(&cast) => {},
+                _ = wait_for_quit() => {},
+            );
+            let mut stdout = tokio::io::stdout();
+            stdout.write_all(b"\r\n").await.ok();
+            stdout.flush().await.ok();
+        });
+        console.reset();
+        Ok(())
+    }
+}
+
+async fn wait_for_quit() {
+    let mut stdin = tokio::io::stdin();
+    let mut buf = [0u8; 64];
+    while let Ok(size) = stdin.read(&mut buf).await {
+        if size == 0 {
+            break;
+        }
+        if buf[..size].iter().any(|byte| QUIT_KEYS.contains(byte)) {
+            return;
+        }
+    }
+    // Without input, e.g. when piped, the replay runs to its end
+    std::future::pending().await
+}
+
+fn parse_speed(value: &str) -> std::result::Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .ok_or_else(|| "Must be a positive number (e.g. 2 or 0.5)".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_offset_scales_with_speed() {
+        let command = ReplayCommand::try_parse_from(["replay", "a.cast", "--speed", "2"]).unwrap();
+        let event = CastEvent {
+            time: 3.0,
+            data: String::new(),
+        };
+
+        assert_eq!(command.get_offset(&event), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_reject_invalid_speed() {
+        assert!(ReplayCommand::try_parse_from(["replay", "a.cast", "--speed", "0"]).is_err());
+        assert!(ReplayCommand::try_parse_from(["replay", "a.cast", "-s", "-1"]).is_err());
+        assert!(ReplayCommand::try_parse_from(["replay", "a.cast", "-s", "fast"]).is_err());
+    }
+
+    #[test]
+    fn test_reject_invalid_recording() {
+        let system = Rc::new(SystemMock::new().add_file("a.cast", b"not a recording"));
+        let console = &mut Console::new(system.as_ref());
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let context = commands::Context::new(
+            system.clone(),
+            env,
+            Box::new(InstanceStoreMock::new(vec![])),
+        );
+
+        let result = ReplayCommand::try_parse_from(["replay", "a.cast"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(result.is_err());
+    }
+}
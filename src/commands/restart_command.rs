@@ -1,5 +1,6 @@
 use crate::commands::{self, Command};
 use crate::error::Result;
+use crate::models::TimeSpan;
 use crate::view::Console;
 use clap::Parser;
 
@@ -13,11 +14,17 @@ use clap::Parser;
 ///   Restart multiple VM instances:
 ///   $ cubic restart trixie noble
 ///
+///   Restart the VM instance 'my-instance' and force it off after 10 seconds:
+///   $ cubic restart --timeout 10s my-instance
+///
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct RestartCommand {
     #[clap(flatten)]
     pub accel: commands::AccelArg,
+    /// Force the instance off if it has not shut down in time (e.g. 30s, 2m)
+    #[clap(short, long, default_value = commands::DEFAULT_STOP_TIMEOUT)]
+    timeout: TimeSpan,
     #[clap(flatten)]
     instances: commands::InstancesArg,
 }
@@ -28,6 +35,7 @@ impl Command for RestartCommand {
             all: false.into(),
             wait: true,
             kill: false,
+            timeout: Some(self.timeout),
             instances: self.instances.value.clone().into(),
         }
         .run(console, context)?;
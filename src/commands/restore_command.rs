@@ -0,0 +1,363 @@
+use crate::actions::CreateInstanceAction;
+use crate::commands::{Command, Context};
+use crate::error::{Error, FsOperation, Result};
+use crate::instance::TomlInstanceDeserializer;
+use crate::models::{Backup, Instance, InstanceName, PortForward};
+use crate::view::{Console, Spinner};
+use clap::Parser;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Restore a backup as a new VM instance
+///
+/// The backup chain up to the chosen backup is merged into the system disk of
+/// the new instance, which gets the configuration the instance had at that
+/// time, along with its kernel, firmware variables and TPM state. Data disks are
+/// not part of a backup. Port forwarding rules with a fixed host port are left
+/// out, since the backed up instance may still use them.
+///
+/// Examples:
+///
+///   Restore the latest backup of 'my-instance' as 'restored':
+///   $ cubic restore /mnt/backup my-instance restored
+///
+///   Restore an earlier backup listed by `cubic backup ls /mnt/backup`:
+///   $ cubic restore /mnt/backup my-instance restored --backup 1760870000
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct RestoreCommand {
+    /// Directory the backups are stored in
+    dir: PathBuf,
+    /// Name of the backed up virtual machine instance
+    instance: InstanceName,
+    /// Name of the new virtual machine instance
+    new_name: InstanceName,
+    /// Backup to restore (default: the latest)
+    #[clap(long)]
+    backup: Option<u64>,
+}
+
+impl Command for RestoreCommand {
+    fn run(&self, console: &mut Console<'_>, context: &Context) -> Result<()> {
+        let system = context.get_system();
+        let name = self.instance.as_str();
+
+        if context.get_instance_store().exists(self.new_name.as_str()) {
+            return Err(Error::InstanceAlreadyExists(self.new_name.to_string()));
+        }
+
+        let backup_dir = Backup::get_instance_dir(&self.dir, name);
+        let chain = Backup::read_chain(system, &backup_dir);
+        let backup = match self.backup {
+            Some(id) => chain
+                .iter()
+                .find(|backup| backup.id == id)
+                .ok_or_else(|| Error::UnknownBackup(name.to_string(), id.to_string()))?,
+            None => chain.last().ok_or_else(|| {
+                Error::NoBackup(name.to_string(), self.dir.to_string_lossy().into_owned())
+            })?,
+        };
+
+        let mut instance = TomlInstanceDeserializer::new()
+            .deserialize(name, &mut system.open_file(&backup.get_config_file())?)?;
+        instance.name = self.new_name.to_string();
+        instance.ssh_port = system.bind_port()?;
+        // The backed up instance may still hold its host ports
+        for fwd in &instance.hostfwd {
+            if !fwd.is_auto() {
+                console.warn(&format!(
+                    "The port forwarding rule {fwd} is not restored. Add it to {} with cubic modify --port",
+                    self.new_name
+                ));
+            }
+        }
+        instance.hostfwd.retain(PortForward::is_auto);
+        for fwd in &mut instance.hostfwd {
+            fwd.allocate_host_port(system)?;
+        }
+        // Same as a clone, the guest generates new host keys on the first boot
+        instance.ssh_host_key = None;
+        // The disk of the new instance has no bitmap, so its first backup is
+        // a full one
+        instance.backup_point = None;
+        for disk in &instance.disks {
+            console.warn(&format!(
+                "The disk '{}' is not part of the backup. Attach it to {} with cubic disk add",
+                disk.name, self.new_name
+            ));
+        }
+        instance.disks.clear();
+
+        console.play(Arc::new(Mutex::new(Spinner::new(format!(
+            "Restoring {} as {}",
+            name, self.new_name
+        )))));
+        // Converting the last image reads through its backing files, so the
+        // whole chain ends up in a single image
+        let result = CreateInstanceAction::new()
+            .run(
+                context,
+                Some(&backup.path.to_string_lossy()),
+                instance.clone(),
+            )
+            .and_then(|_| Self::restore_instance_files(context, backup, &instance));
+        console.stop();
+        result
+    }
+}
+
+impl RestoreCommand {
+    // A kernel of its own, the firmware variables and the TPM state are
+    // kept next to the backup
+    fn restore_instance_files(
+        context: &Context,
+        backup: &Backup,
+        instance: &Instance,
+    ) -> Result<()> {
+        let env = context.get_env();
+        let system = context.get_system();
+        let mut files: Vec<(PathBuf, PathBuf)> = instance
+            .get_owned_boot_files()?
+            .into_iter()
+            .map(|file| {
+                (
+                    backup.get_boot_file(file),
+                    PathBuf::from(env.get_instance_boot_file(&instance.name, file)),
+                )
+            })
+            .collect();
+        let nvram = backup.get_nvram_file();
+        if system.exists_path(&nvram) {
+            files.push((
+                nvram,
+                PathBuf::from(env.get_instance_nvram_file(&instance.name)),
+            ));
+        }
+        let tpm_files = backup.read_tpm_files(system);
+        let tpm_dir = PathBuf::from(env.get_instance_tpm_dir(&instance.name));
+        if !tpm_files.is_empty() {
+            system.create_dir(&tpm_dir)?;
+        }
+        for (from, file) in tpm_files {
+            files.push((from, tpm_dir.join(file)));
+        }
+        for (from, to) in files {
+            io::copy(&mut system.open_file(&from)?, &mut system.create_file(&to)?)
+                .map_err(|error| Error::from_fs(FsOperation::WriteFile, &to, error))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::{InstanceSerializer, InstanceStoreMock};
+    use crate::models::{Environment, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    fn build_context(system: SystemMock, instances: Vec<Instance>) -> Context {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        Context::new(
+            Rc::new(system),
+            env,
+            Box::new(InstanceStoreMock::new(instances)),
+        )
+    }
+
+    #[test]
+    fn test_restore_without_backups() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(SystemMock::new().add_dir("/backup"), Vec::new());
+
+        let result = RestoreCommand::try_parse_from(["restore", "/backup", "test", "restored"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(
+            matches!(result, Err(Error::NoBackup(name, dir)) if name == "test" && dir == "/backup")
+        );
+    }
+
+    #[test]
+    fn test_restore_unknown_backup() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(
+            SystemMock::new().add_file("/backup/test/100-full.qcow2", b""),
+            Vec::new(),
+        );
+
+        let result = RestoreCommand::try_parse_from([
+            "restore", "/backup", "test", "restored", "--backup", "200",
+        ])
+        .unwrap()
+        .run(console, &context);
+
+        assert!(
+            matches!(result, Err(Error::UnknownBackup(name, id)) if name == "test" && id == "200")
+        );
+    }
+
+    #[test]
+    fn test_restore_to_existing_instance() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(
+            SystemMock::new().add_file("/backup/test/100-full.qcow2", b""),
+            vec![Instance {
+                name: "restored".to_string(),
+                ..Instance::default()
+            }],
+        );
+
+        let result = RestoreCommand::try_parse_from(["restore", "/backup", "test", "restored"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InstanceAlreadyExists(name)) if name == "restored"));
+    }
+
+    #[test]
+    fn test_restore_leaves_out_fixed_host_ports_and_copies_the_kernel() {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let backed_up = Instance {
+            name: "test".to_string(),
+            hostfwd: vec![
+                PortForward::from_str("8080:80").unwrap(),
+                PortForward::from_str(":443").unwrap(),
+            ],
+            kernel: Some("vmlinuz".to_string()),
+            ..Instance::default()
+        };
+        let mut config = Vec::new();
+        InstanceSerializer::new()
+            .serialize(&backed_up, &mut config)
+            .unwrap();
+        let target_image = format!("{}.tmp/machine.img", env.get_instance_dir2("restored"));
+        let system = Rc::new(
+            SystemMock::new()
+                .add_file("/backup/test/100-full.qcow2", b"")
+                .add_file("/backup/test/100.toml", &config)
+                .add_file("/backup/test/100-vmlinuz", b"kernel")
+                .add_command_output(
+                    &format!(
+                        "qemu-img convert -f qcow2 -O qcow2 /backup/test/100-full.qcow2 {target_image}"
+                    ),
+                    b"",
+                )
+                .add_command_output(&format!("qemu-img resize {target_image} 0"), b""),
+        );
+        let console = &mut Console::new(system.as_ref());
+        let store = InstanceStoreMock::new(Vec::new());
+        let stored = Arc::clone(&store.stored);
+        let context = Context::new(system.clone(), env.clone(), Box::new(store));
+
+        RestoreCommand::try_parse_from(["restore", "/backup", "test", "restored"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        let stored = stored.lock().unwrap();
+        assert_eq!(stored[0].hostfwd.len(), 1);
+        assert!(stored[0].hostfwd[0].is_auto());
+        assert_ne!(stored[0].hostfwd[0].get_host_port(), 0);
+        assert_eq!(
+            system.get_written_file(&env.get_instance_boot_file("restored", "vmlinuz")),
+            Some(b"kernel".to_vec())
+        );
+        assert!(system.get_output().contains("8080"));
+    }
+
+    #[test]
+    fn test_restore_copies_the_firmware_variables_and_the_tpm_state() {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let mut config = Vec::new();
+        InstanceSerializer::new()
+            .serialize(
+                &Instance {
+                    name: "test".to_string(),
+                    tpm: true,
+                    ..Instance::default()
+                },
+                &mut config,
+            )
+            .unwrap();
+        let target_image = format!("{}.tmp/machine.img", env.get_instance_dir2("restored"));
+        let system = Rc::new(
+            SystemMock::new()
+                .add_file("/backup/test/100-full.qcow2", b"")
+                .add_file("/backup/test/100.toml", &config)
+                .add_file("/backup/test/100.nvram.fd", b"vars")
+                .add_file("/backup/test/100.tpm.tpm2-00.permall", b"sealed")
+                .add_command_output(
+                    &format!(
+                        "qemu-img convert -f qcow2 -O qcow2 /backup/test/100-full.qcow2 {target_image}"
+                    ),
+                    b"",
+                )
+                .add_command_output(&format!("qemu-img resize {target_image} 0"), b""),
+        );
+        let console = &mut Console::new(system.as_ref());
+        let context = Context::new(
+            system.clone(),
+            env.clone(),
+            Box::new(InstanceStoreMock::new(Vec::new())),
+        );
+
+        RestoreCommand::try_parse_from(["restore", "/backup", "test", "restored"])
+            .unwrap()
+            .run(console, &context)
+            .unwrap();
+
+        assert_eq!(
+            system.get_written_file(&env.get_instance_nvram_file("restored")),
+            Some(b"vars".to_vec())
+        );
+        assert_eq!(
+            system.get_written_file(&format!(
+                "{}/tpm2-00.permall",
+                env.get_instance_tpm_dir("restored")
+            )),
+            Some(b"sealed".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_restore_rejects_a_boot_file_outside_the_instance_dir() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = build_context(
+            SystemMock::new()
+                .add_file("/backup/test/100-full.qcow2", b"")
+                .add_file(
+                    "/backup/test/100.toml",
+                    b"cpus = 1\nmem = 1073741824\ndisk_capacity = 2361393152\nssh_port = 14357\nkernel = \"../../../.bashrc\"\n",
+                ),
+            Vec::new(),
+        );
+
+        let result = RestoreCommand::try_parse_from(["restore", "/backup", "test", "restored"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::InvalidInstanceConfig { .. })));
+    }
+}
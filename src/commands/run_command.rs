@@ -46,6 +46,7 @@ impl Command for RunCommand {
             target: Target::from_instance_name(self.create_cmd.instance_name.value.clone()),
             accel: self.accel,
             env_args: self.env_args.clone(),
+            record: commands::RecordArg::default(),
         }
         .run(console, context)
     }
@@ -0,0 +1,132 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, FsOperation, Result};
+use crate::platform::System;
+use crate::screenshot::{PngWriter, PpmImage};
+use crate::view::Console;
+use clap::Parser;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const SCREENDUMP_FILE: &str = "screendump.ppm";
+
+/// Save a screenshot of a VM instance as PNG
+///
+/// Takes the picture of the graphics card, so the instance needs a display.
+///
+/// Examples:
+///
+///   Save the screen of 'my-instance' to screen.png:
+///   $ cubic screenshot my-instance screen.png
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct ScreenshotCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// PNG file to write
+    output: PathBuf,
+}
+
+impl Command for ScreenshotCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let system = context.get_system();
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if !instance.display {
+            return Err(Error::DisplayNotEnabled(instance.name));
+        }
+        if !instance_store.is_running(&instance) {
+            return Err(Error::InstanceNotRunning(instance.name));
+        }
+
+        // QEMU dumps into the instance directory, which it may write to
+        let dump = PathBuf::from(context.get_env().get_instance_dir2(&instance.name))
+            .join(SCREENDUMP_FILE);
+        let result = instance_store
+            .get_monitor(&instance)?
+            .screendump(&dump)
+            .and_then(|_| convert_screendump(system, &dump, &self.output));
+        system.remove_file(&dump).ok();
+        result?;
+
+        console.info(&format!(
+            "Saved screenshot of {} to {}",
+            instance.name,
+            self.output.display()
+        ));
+        Ok(())
+    }
+}
+
+fn convert_screendump(system: &dyn System, dump: &Path, output: &Path) -> Result<()> {
+    let mut data = Vec::new();
+    system
+        .open_file(dump)?
+        .read_to_end(&mut data)
+        .map_err(|error| Error::from_fs(FsOperation::ReadFile, dump, error))?;
+    let image = PpmImage::parse(&data)
+        .map_err(|error| Error::from_fs(FsOperation::ReadFile, dump, error))?;
+    PngWriter::write(&mut system.create_file(output)?, &image)
+        .map_err(|error| Error::from_fs(FsOperation::WriteFile, output, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_convert_screendump_writes_png() {
+        let system = SystemMock::new().add_file(
+            "/data/machines/test/screendump.ppm",
+            b"P6\n1 1\n255\n\x01\x02\x03",
+        );
+
+        convert_screendump(
+            &system,
+            Path::new("/data/machines/test/screendump.ppm"),
+            Path::new("screen.png"),
+        )
+        .unwrap();
+
+        assert!(
+            system
+                .get_written_file("screen.png")
+                .unwrap()
+                .starts_with(b"\x89PNG")
+        );
+    }
+
+    #[test]
+    fn test_reject_instance_without_display() {
+        let system = Rc::new(SystemMock::new());
+        let console = &mut Console::new(system.as_ref());
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        );
+        let instance = Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        };
+        let context = commands::Context::new(
+            system.clone(),
+            env,
+            Box::new(InstanceStoreMock::new(vec![instance])),
+        );
+
+        let result = ScreenshotCommand::try_parse_from(["screenshot", "test", "screen.png"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::DisplayNotEnabled(_))));
+    }
+}
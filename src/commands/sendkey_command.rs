@@ -0,0 +1,105 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::models::KeyCombo;
+use crate::view::Console;
+use clap::Parser;
+
+/// Press keys on the keyboard of a VM instance
+///
+/// Keys go through QEMU, so they reach boot menus and guests without network.
+/// Each argument is one combination of keys pressed together, named by the
+/// QEMU key codes (e.g. ctrl, alt, delete, ret, esc, f1, a, 1, up). An arm64
+/// instance only has a keyboard along with a display.
+///
+/// Examples:
+///
+///   Reboot 'my-instance' with Ctrl+Alt+Delete:
+///   $ cubic sendkey my-instance ctrl-alt-delete
+///
+///   Select the second entry of a boot menu:
+///   $ cubic sendkey my-instance down ret
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct SendkeyCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Key combinations to press one after another (e.g. ctrl-alt-delete)
+    #[clap(required = true)]
+    keys: Vec<KeyCombo>,
+}
+
+impl Command for SendkeyCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        if !instance_store.is_running(&instance) {
+            return Err(Error::InstanceNotRunning(instance.name));
+        }
+        if !instance.has_keyboard() {
+            return Err(Error::KeyboardNotAttached(instance.name));
+        }
+
+        let mut monitor = instance_store.get_monitor(&instance)?;
+        for combo in &self.keys {
+            console.debug(&format!("Sending {combo} to {}", instance.name));
+            monitor.send_keys(combo)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::InstanceStoreMock;
+    use crate::models::{Arch, Environment, Instance, UserName};
+    use crate::platform::SystemMock;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_keys() {
+        let command =
+            SendkeyCommand::try_parse_from(["sendkey", "test", "down", "ctrl-alt-delete"]).unwrap();
+
+        assert_eq!(command.keys.len(), 2);
+        assert_eq!(command.keys[1].get_keys(), ["ctrl", "alt", "delete"]);
+    }
+
+    #[test]
+    fn test_reject_missing_keys() {
+        assert!(SendkeyCommand::try_parse_from(["sendkey", "test"]).is_err());
+    }
+
+    #[test]
+    fn test_reject_arm64_instance_without_display() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let context = commands::Context::new(
+            Rc::new(SystemMock::new()),
+            Environment::new(
+                UserName::from_str("cubic").unwrap(),
+                String::new(),
+                String::new(),
+            ),
+            Box::new(InstanceStoreMock::new_with_running(
+                vec![Instance {
+                    name: "test".to_string(),
+                    arch: Arch::ARM64,
+                    ..Instance::default()
+                }],
+                &["test"],
+            )),
+        );
+
+        let result = SendkeyCommand::try_parse_from(["sendkey", "test", "ret"])
+            .unwrap()
+            .run(console, &context);
+
+        assert!(matches!(result, Err(Error::KeyboardNotAttached(ref name)) if name == "test"));
+    }
+}
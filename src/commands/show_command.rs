@@ -16,6 +16,7 @@ use clap::Parser;
 ///   Arch:         amd64
 ///   CPUs:         6
 ///   Memory:       16.0 GiB
+///   Balloon:      16.0 GiB
 ///   Disk Used:    5.2 GiB
 ///   Disk Total:   100.0 GiB
 ///   User:         cubic
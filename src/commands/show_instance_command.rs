@@ -1,10 +1,13 @@
 use crate::actions::LoadInstanceAction;
 use crate::commands::{self, Command};
 use crate::error::{Error, Result};
+use crate::models::DataSize;
+use crate::qemu::{QemuFirmware, QemuPathBuilder};
 use crate::ssh::HostKeyChecker;
 use crate::util;
 use crate::view::{Console, MapView};
 use clap::Parser;
+use std::path::{Path, PathBuf};
 
 /// Show VM instances
 #[derive(Parser)]
@@ -19,6 +22,7 @@ pub struct ShowInstanceCommand {
 impl Command for ShowInstanceCommand {
     fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
         let env = context.get_env();
+        let system = context.get_system();
         let instance_store = context.get_instance_store();
 
         if !instance_store.exists(self.instance.value.as_str()) {
@@ -37,10 +41,67 @@ impl Command for ShowInstanceCommand {
         view.add("Arch", &instance.arch.to_string());
         view.add("CPUs", &instance.cpus.to_string());
         view.add("Memory", &instance.mem.to_size());
+        // Best effort, an unreachable monitor must not break show
+        if instance_store.is_running(&instance)
+            && let Ok(balloon) = instance_store
+                .get_monitor(&instance)
+                .and_then(|mut monitor| monitor.get_balloon())
+        {
+            view.add("Balloon", &DataSize::new(balloon as usize).to_size());
+        }
+        if instance.reclaim_memory {
+            view.add("Reclaim Memory", "yes");
+        }
         if let Some(disk_used) = &instance.disk_used {
             view.add("Disk Used", &disk_used.to_size());
         }
         view.add("Disk Total", &instance.disk_capacity.to_size());
+        if !instance.disk_settings.is_default() {
+            view.add("Disk I/O", &instance.disk_settings.to_string());
+        }
+        if let Some(encryption) = &instance.encryption {
+            view.add("Encryption", &encryption.to_string());
+        }
+        if let Some(vagrant_box) = &instance.vagrant_box {
+            view.add("Vagrant Box", &vagrant_box.to_string());
+        }
+        if let Some(oci_image) = &instance.oci_image {
+            view.add("OCI Image", oci_image);
+        }
+        let boot_files = [("Kernel", &instance.kernel), ("Initrd", &instance.initrd)];
+        for (key, file) in boot_files {
+            if let Some(file) = file {
+                view.add(key, &env.get_instance_boot_file(&instance.name, file));
+            }
+        }
+        if let Some(append) = &instance.append {
+            view.add("Append", append);
+        }
+        if let Some(iso) = &instance.iso {
+            view.add("ISO", &env.get_instance_boot_file(&instance.name, iso));
+        }
+        // The firmware the next start picks
+        let firmware = match &instance.firmware {
+            Some(firmware) => Some(PathBuf::from(
+                env.get_instance_boot_file(&instance.name, firmware),
+            )),
+            None => QemuFirmware::locate(
+                system,
+                QemuPathBuilder::new(system).get_dirs(),
+                instance.arch,
+                instance.secure_boot,
+            )
+            .map(|firmware| firmware.code),
+        };
+        if let Some(firmware) = firmware {
+            view.add("Firmware", &firmware.display().to_string());
+        }
+        if instance.secure_boot {
+            view.add("Secure Boot", "yes");
+        }
+        if instance.tpm {
+            view.add("TPM", "yes");
+        }
         view.add("User", instance.user.as_str());
         view.add("Isolated", util::to_yes_no(instance.isolate));
         view.add("SSH Port", &instance.ssh_port.to_string());
@@ -50,6 +111,11 @@ impl Command for ShowInstanceCommand {
         if let Some(console_port) = instance.console_port {
             view.add("Console Port", &console_port.to_string());
         }
+        if instance.display
+            && let Some(display_port) = instance.display_port
+        {
+            view.add("Display Port", &display_port.to_string());
+        }
 
         // Port forwarding
         for (index, rule) in instance.hostfwd.iter().enumerate() {
@@ -63,6 +129,13 @@ impl Command for ShowInstanceCommand {
             }
             view.add("Disk Image", &env.get_instance_image_file(&instance.name));
             view.add("Config", &env.get_instance_toml_config_file(&instance.name));
+            let nvram = env.get_instance_nvram_file(&instance.name);
+            if system.exists_path(Path::new(&nvram)) {
+                view.add("NVRAM", &nvram);
+            }
+            if instance.tpm {
+                view.add("TPM State", &env.get_instance_tpm_dir(&instance.name));
+            }
             view.add("SSH Key", &ssh_key);
             if let Some(host_key) = &instance.ssh_host_key {
                 view.add(
@@ -221,6 +294,36 @@ SSH:          ssh -i {ssh_key} -p 8000 john@localhost
         );
     }
 
+    #[test]
+    fn test_show_custom_firmware_and_secure_boot() {
+        let system = SystemMock::new();
+        let console = &mut Console::new(&system);
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            String::new(),
+            String::new(),
+        );
+        let instance_store = InstanceStoreMock::new(vec![Instance {
+            name: "test".to_string(),
+            firmware: Some("/srv/OVMF_CODE.secboot.fd".to_string()),
+            secure_boot: true,
+            ..Instance::default()
+        }]);
+        let context =
+            commands::Context::new(Rc::new(SystemMock::new()), env, Box::new(instance_store));
+
+        ShowInstanceCommand {
+            instance: InstanceName::from_str("test").unwrap().into(),
+            all: false.into(),
+        }
+        .run(console, &context)
+        .unwrap();
+
+        let output = system.get_output();
+        assert!(output.contains("Firmware:    /srv/OVMF_CODE.secboot.fd\n"));
+        assert!(output.contains("Secure Boot: yes\n"));
+    }
+
     #[test]
     fn test_show_command_failed() {
         let system = SystemMock::new();
@@ -16,6 +16,9 @@ use std::sync::{Arc, Mutex};
 ///   $ cubic ssh my-instance
 ///   [...]
 ///
+///   Record the session for `cubic replay`:
+///   $ cubic ssh my-instance --record session.cast
+///
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct SshCommand {
@@ -25,6 +28,8 @@ pub struct SshCommand {
     pub accel: commands::AccelArg,
     #[clap(flatten)]
     pub env_args: commands::EnvArgs,
+    #[clap(flatten)]
+    pub record: commands::RecordArg,
 }
 
 impl Command for SshCommand {
@@ -70,7 +75,8 @@ impl Command for SshCommand {
             ssh_port,
         ))?;
         console.stop();
-        async_caller.call(ssh.shell(console, &instance.name, channel))?;
+        let recording = self.record.create_writer(console, context.get_system())?;
+        async_caller.call(ssh.shell(console, &instance.name, channel, recording))?;
         Ok(())
     }
 }
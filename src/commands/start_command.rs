@@ -68,6 +68,12 @@ impl Command for StartCommand {
                         instance.name, old_port, instance.ssh_port
                     ));
                 }
+                self.reassign_forward_ports(
+                    console,
+                    context.get_system(),
+                    instance_store,
+                    instance,
+                )?;
 
                 self.fit_to_available_memory(
                     console,
@@ -107,6 +113,39 @@ impl Command for StartCommand {
 }
 
 impl StartCommand {
+    /// Pick new host ports for automatic forwarding rules whose port is taken.
+    ///
+    /// A port counts as taken if the rule cannot bind its own address and
+    /// protocol, unlike for SSH, where only a greeting counts. Rules with a
+    /// fixed host port stay as the user asked.
+    fn reassign_forward_ports(
+        &self,
+        console: &mut Console<'_>,
+        system: &dyn System,
+        instance_store: &dyn InstanceStore,
+        instance: &mut Instance,
+    ) -> Result<()> {
+        let mut changed = false;
+        for fwd in instance.hostfwd.iter_mut().filter(|fwd| fwd.is_auto()) {
+            let old_port = fwd.get_host_port();
+            if !fwd.is_host_port_free(system) {
+                fwd.allocate_host_port(system)?;
+                changed = true;
+                console.debug(&format!(
+                    "Instance '{}' port {} for guest port {} is taken, reassigned to {}",
+                    instance.name,
+                    old_port,
+                    fwd.get_guest_port(),
+                    fwd.get_host_port()
+                ));
+            }
+        }
+        if changed {
+            instance_store.store(instance)?;
+        }
+        Ok(())
+    }
+
     /// Reduce an instance to a size that fits the host's available memory.
     ///
     /// QEMU fails to start when the host cannot back the requested memory, so
@@ -164,7 +203,7 @@ mod tests {
     use super::*;
     use crate::commands::Context;
     use crate::instance::{InstanceDao, InstanceStoreMock};
-    use crate::models::{Environment, UserName};
+    use crate::models::{Environment, PortForward, UserName};
     use crate::platform::SystemMock;
     use std::rc::Rc;
     use std::str::FromStr;
@@ -251,6 +290,66 @@ mod tests {
         assert_eq!(build_dao(&system).load("test").unwrap().ssh_port, 22000);
     }
 
+    #[test]
+    fn test_reassigns_only_auto_forward_ports_that_are_taken() {
+        let system = Rc::new(
+            SystemMock::new()
+                .set_host_resources(GIB as u64, GIB as u64, 8)
+                .add_dir("/data/machines/test")
+                .add_open_port(23000)
+                .add_open_port(8000),
+        );
+        let auto: PortForward = serde_json::from_str("\"tcp:127.0.0.1:23000-:80,auto\"").unwrap();
+        let context = build_starved_context(&system, 22000);
+        let instance = Instance {
+            ssh_port: 22000,
+            hostfwd: vec![auto, "8000:8000".parse().unwrap()],
+            ..build_instance()
+        };
+        build_dao(&system).store(&instance).unwrap();
+        let mut console = Console::new(system.as_ref());
+        let command = StartCommand::try_parse_from(["start", "--yes", "test"]).unwrap();
+
+        assert!(matches!(
+            command.run(&mut console, &context),
+            Err(Error::NotEnoughMemory(_))
+        ));
+        let hostfwd = build_dao(&system).load("test").unwrap().hostfwd;
+        assert_ne!(hostfwd[0].get_host_port(), 23000);
+        assert_eq!(hostfwd[1].get_host_port(), 8000);
+    }
+
+    #[test]
+    fn test_probes_auto_forward_ports_by_their_protocol() {
+        let system = Rc::new(
+            SystemMock::new()
+                .set_host_resources(GIB as u64, GIB as u64, 8)
+                .add_dir("/data/machines/test")
+                .add_udp_port(5353)
+                .add_open_port(5354),
+        );
+        let taken: PortForward = serde_json::from_str("\"udp:127.0.0.1:5353-:53,auto\"").unwrap();
+        // Only a TCP listener holds this one
+        let free: PortForward = serde_json::from_str("\"udp:0.0.0.0:5354-:54,auto\"").unwrap();
+        let context = build_starved_context(&system, 22000);
+        let instance = Instance {
+            ssh_port: 22000,
+            hostfwd: vec![taken, free],
+            ..build_instance()
+        };
+        build_dao(&system).store(&instance).unwrap();
+        let mut console = Console::new(system.as_ref());
+        let command = StartCommand::try_parse_from(["start", "--yes", "test"]).unwrap();
+
+        assert!(matches!(
+            command.run(&mut console, &context),
+            Err(Error::NotEnoughMemory(_))
+        ));
+        let hostfwd = build_dao(&system).load("test").unwrap().hostfwd;
+        assert_ne!(hostfwd[0].get_host_port(), 5353);
+        assert_eq!(hostfwd[1].get_host_port(), 5354);
+    }
+
     #[test]
     fn test_reject_path_traversal() {
         assert!(StartCommand::try_parse_from(["start", "../../etc"]).is_err());
@@ -1,12 +1,19 @@
 use crate::actions::{LoadInstanceAction, StopInstanceAction};
 use crate::commands::{self, Command};
 use crate::error::Result;
+use crate::instance::InstanceStore;
+use crate::models::TimeSpan;
 use crate::view::Console;
 use crate::view::Spinner;
 use clap::Parser;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_STOP_TIMEOUT: &str = "60s";
+
+// How long QEMU gets to exit after it was asked to quit, before it is killed
+const QUIT_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Stop VM instances
 ///
@@ -24,6 +31,9 @@ use std::time::Duration;
 ///   Force-kill the VM instance 'my-instance':
 ///   $ cubic stop --kill my-instance
 ///
+///   Shut down the VM instance 'my-instance' and force it off after 30 seconds:
+///   $ cubic stop --timeout 30s my-instance
+///
 #[derive(Parser)]
 #[clap(verbatim_doc_comment)]
 pub struct StopCommand {
@@ -35,6 +45,9 @@ pub struct StopCommand {
     /// Kill the virtual machine instance
     #[clap(short, long, default_value_t = false)]
     pub kill: bool,
+    /// Force the instance off if it has not shut down in time (e.g. 30s, 2m)
+    #[clap(short, long, conflicts_with = "kill")]
+    pub timeout: Option<TimeSpan>,
     #[clap(flatten)]
     pub instances: commands::InstancesArg,
 }
@@ -75,17 +88,61 @@ impl Command for StopCommand {
         let mut actions = Vec::new();
         for instance in &stopping {
             let mut action = StopInstanceAction::new(instance);
-            action.run(instance_store, self.kill)?;
+            if let Err(e) = action.run(instance_store, self.kill) {
+                // An unreachable monitor is no reason to give up when the
+                // instance may be forced off anyway
+                if self.timeout.is_none() {
+                    return Err(e);
+                }
+                action.escalate(instance_store)?;
+            }
             actions.push(action);
         }
 
-        if self.wait {
+        if let Some(timeout) = &self.timeout {
+            // The guest gets the timeout to shut down, QEMU a short grace
+            // period to quit, and whatever is left is killed.
+            Self::escalate_after(instance_store, &mut actions, timeout.get_duration())?;
+            Self::escalate_after(instance_store, &mut actions, QUIT_TIMEOUT)?;
+        }
+
+        if self.wait || self.timeout.is_some() {
             while actions.iter().any(|action| !action.is_done(instance_store)) {
                 thread::sleep(Duration::from_secs(1))
             }
         }
 
         console.stop();
+
+        if self.timeout.is_some() {
+            for action in &actions {
+                if let Some(stage) = action.get_stage() {
+                    console.info(&format!(
+                        "Stopped {} by {stage}",
+                        action.get_instance().name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl StopCommand {
+    // Waits for all instances with a shared deadline and escalates the ones
+    // still running once it has passed
+    fn escalate_after(
+        instance_store: &dyn InstanceStore,
+        actions: &mut [StopInstanceAction],
+        timeout: Duration,
+    ) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        for action in actions {
+            if !action.wait(instance_store, deadline) {
+                action.escalate(instance_store)?;
+            }
+        }
         Ok(())
     }
 }
@@ -95,7 +152,7 @@ mod tests {
     use super::*;
     use crate::error::Error;
     use crate::instance::InstanceStoreMock;
-    use crate::models::{Environment, UserName};
+    use crate::models::{Environment, Instance, UserName};
     use crate::platform::SystemMock;
     use std::rc::Rc;
     use std::str::FromStr;
@@ -125,6 +182,7 @@ mod tests {
                 all: false.into(),
                 wait: false,
                 kill: false,
+                timeout: None,
                 instances: Vec::new().into(),
             }
             .run(console, &context),
@@ -152,10 +210,35 @@ mod tests {
                 all: true.into(),
                 wait: false,
                 kill: false,
+                timeout: None,
                 instances: Vec::new().into(),
             }
             .run(console, &context)
             .is_ok()
         );
     }
+
+    #[test]
+    fn test_timeout_conflicts_with_kill() {
+        assert!(
+            StopCommand::try_parse_from(["stop", "--kill", "--timeout", "30s", "test"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_timeout_forces_off_instance_without_monitor() {
+        let instance = Instance {
+            name: "test".to_string(),
+            ..Instance::default()
+        };
+        // The mock stays running after the kill, so the wait loop would
+        // never end. Checking the escalation path before it is enough.
+        let store = InstanceStoreMock::new_with_running(vec![instance.clone()], &["test"]);
+        let mut action = StopInstanceAction::new(&instance);
+        assert!(action.run(&store, false).is_err());
+        let mut actions = vec![action];
+        StopCommand::escalate_after(&store, &mut actions, Duration::ZERO).unwrap();
+
+        assert_eq!(*store.killed.lock().unwrap(), ["test"]);
+    }
 }
@@ -0,0 +1,89 @@
+use crate::actions::LoadInstanceAction;
+use crate::commands::{self, Command};
+use crate::error::{Error, Result};
+use crate::models::KeyCombo;
+use crate::view::Console;
+use clap::Parser;
+
+/// Type text on the keyboard of a VM instance
+///
+/// Presses the keys of a US keyboard layout through QEMU, which works before
+/// the guest has network, e.g. for an installer or a login prompt. An arm64
+/// instance only has a keyboard along with a display.
+///
+/// Examples:
+///
+///   Enter the user name at the login prompt of 'my-instance':
+///   $ cubic type my-instance root
+///   $ cubic sendkey my-instance ret
+///
+#[derive(Parser)]
+#[clap(verbatim_doc_comment)]
+pub struct TypeCommand {
+    #[clap(flatten)]
+    instance: commands::InstanceArg,
+    /// Text to type
+    text: String,
+}
+
+impl TypeCommand {
+    // Checks every character first, so the guest never gets half the text
+    fn get_keys(&self) -> Result<Vec<KeyCombo>> {
+        self.text
+            .chars()
+            .map(|c| KeyCombo::from_char(c).ok_or(Error::UntypableCharacter(c)))
+            .collect()
+    }
+}
+
+impl Command for TypeCommand {
+    fn run(&self, console: &mut Console<'_>, context: &commands::Context) -> Result<()> {
+        let instance_store = context.get_instance_store();
+        let instance =
+            LoadInstanceAction::new().run(context, console, self.instance.value.as_str())?;
+
+        let keys = self.get_keys()?;
+        if !instance_store.is_running(&instance) {
+            return Err(Error::InstanceNotRunning(instance.name));
+        }
+        if !instance.has_keyboard() {
+            return Err(Error::KeyboardNotAttached(instance.name));
+        }
+
+        let mut monitor = instance_store.get_monitor(&instance)?;
+        for combo in &keys {
+            monitor.send_keys(combo)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_keys() {
+        let command = TypeCommand::try_parse_from(["type", "test", "Hi!\n"]).unwrap();
+
+        assert_eq!(
+            command
+                .get_keys()
+                .unwrap()
+                .iter()
+                .map(|combo| combo.to_string())
+                .collect::<Vec<_>>(),
+            ["shift-h", "i", "shift-1", "ret"]
+        );
+    }
+
+    #[test]
+    fn test_get_keys_rejects_untypable_character() {
+        let command = TypeCommand::try_parse_from(["type", "test", "grüß"]).unwrap();
+
+        assert!(matches!(
+            command.get_keys(),
+            Err(Error::UntypableCharacter('ü'))
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::models::Arch;
 use std::fmt;
 use std::io;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -76,6 +77,21 @@ pub enum Error {
     )]
     StartTimeout,
 
+    #[error(
+        "Instance '{0}' has no display.\n\nAdd one with: `cubic modify --display {0}`, then restart the instance"
+    )]
+    DisplayNotEnabled(String),
+
+    #[error(
+        "Instance '{0}' has no keyboard, which arm64 instances only get along with a display.\n\nAdd one with: `cubic modify --display {0}`, then restart the instance"
+    )]
+    KeyboardNotAttached(String),
+
+    #[error(
+        "Cannot type '{0}': only characters of a US keyboard layout are supported.\n\nSend other keys with: `cubic sendkey`"
+    )]
+    UntypableCharacter(char),
+
     #[error(
         "Timed out waiting for the console of instance '{0}'.\n\nTroubleshoot:\n  - Check that the instance is running: `cubic list`\n  - Run with --verbose to see the QEMU command\n  - Try again; the system may be under load\n"
     )]
@@ -86,9 +102,102 @@ pub enum Error {
     )]
     NotEnoughMemory(String),
 
-    #[error("Cannot shrink the disk of the instance '{0}'")]
+    #[error(
+        "Instance '{0}' cannot add vCPUs while running, since {1} guests do not support CPU hotplug.\n\nStop the instance first: `cubic stop --wait {0}`"
+    )]
+    CpuHotplugNotSupported(String, Arch),
+
+    #[error(
+        "Instance '{0}' has room for {1} more vCPUs while running.\n\nStop the instance to go beyond that: `cubic stop --wait {0}`"
+    )]
+    CpuHotplugLimitReached(String, u16),
+
+    #[error(
+        "Instance '{0}' can only gain memory in steps of {1} while running.\n\nChoose a size that many steps above the current one, or stop the instance first: `cubic stop --wait {0}`"
+    )]
+    MemoryHotplugNotAligned(String, String),
+
+    #[error(
+        "Instance '{0}' already has a disk named '{1}'.\n\nList its disks with: `cubic disk ls {0}`"
+    )]
+    DiskAlreadyExists(String, String),
+
+    #[error("Instance '{0}' has no disk named '{1}'.\n\nList its disks with: `cubic disk ls {0}`")]
+    UnknownDisk(String, String),
+
+    #[error(
+        "Instance '{0}' did not release disk '{1}'.\n\nTroubleshoot:\n  - Unmount the disk in the guest and try again\n  - Stop the instance and remove the disk then: `cubic stop --wait {0}`\n"
+    )]
+    DiskBusy(String, String),
+
+    #[error(
+        "Instance '{0}' has no free port to attach a disk while running. A start leaves room for {spare} more disks than it boots with.\n\nRestart the instance to attach the disk: `cubic restart {0}`",
+        spare = crate::qemu::SPARE_HOTPLUG_PORTS
+    )]
+    NoFreeHotplugPort(String),
+
+    #[error(
+        "Cannot shrink the disk of the instance '{0}'.\n\nShrink it with: `cubic modify --shrink --disk <size> {0}`"
+    )]
     CannotShrinkDisk(String),
 
+    #[error(
+        "Cannot {1} the encrypted disk of instance '{0}'.\n\nOnly disks with a stored passphrase can grow, and no encrypted disk can be shrunk, compacted, cloned, committed, backed up or exported."
+    )]
+    EncryptedDiskNotSupported(String, String),
+
+    #[error(
+        "Native AIO needs a disk cache that bypasses the host page cache.\n\nChoose one with: `cubic modify --disk-cache none {0}`"
+    )]
+    NativeAioNeedsDirectCache(String),
+
+    #[error(
+        "The {1} '{2}' of instance '{0}' does not exist.\n\nChange it with: `cubic modify --{1} <file> {0}`"
+    )]
+    BootFileNotFound(String, String, String),
+
+    #[error(
+        "Instance '{0}' has no kernel to go with its {1}.\n\nSet one with: `cubic modify --kernel <file> {0}`"
+    )]
+    KernelRequired(String, String),
+
+    #[error(
+        "The boot file '{1}' of instance '{0}' is neither an absolute path nor a file name in the instance directory"
+    )]
+    InvalidBootFile(String, String),
+
+    #[error("Backup of instance '{0}' failed: {1}")]
+    BackupFailed(String, String),
+
+    #[error(
+        "No backup of instance '{0}' found in '{1}'.\n\nList the backups with: `cubic backup ls {1}`"
+    )]
+    NoBackup(String, String),
+
+    #[error(
+        "Backup '{1}' of instance '{0}' not found.\n\nList the backups with: `cubic backup ls <dir>`"
+    )]
+    UnknownBackup(String, String),
+
+    #[error("File '{0}' already exists.\n\nChoose another file with: `--output <file>`")]
+    FileAlreadyExists(String),
+
+    #[error("The passphrases do not match")]
+    PassphraseMismatch,
+
+    #[error("Entering the disk passphrase of instance '{0}' was cancelled")]
+    PassphraseCancelled(String),
+
+    #[error(
+        "The partitions of instance '{0}' reach up to {1}.\n\nTroubleshoot:\n  - Choose a disk size of at least {1}\n  - Shrink the filesystem and the last partition in the guest first, then stop the instance without a reboot, on which growpart grows them again\n"
+    )]
+    ShrinkBelowUsage(String, String),
+
+    #[error(
+        "Cannot tell where the data on the disk of instance '{0}' ends, so it is not shrunk.\n\nOnly GPT and DOS partition tables and bare ext4 filesystems are read."
+    )]
+    UnknownDiskLayout(String),
+
     #[error(
         "Hardware acceleration needs a guest arch equal to the host arch.\n\nInstance '{0}' is {1} and this host is {2}.\n\nRun it with `--accel off` to use software emulation."
     )]
@@ -101,13 +210,49 @@ pub enum Error {
     #[error("Verification of image failed")]
     InvalidChecksum,
 
+    #[error("Cannot import Vagrant box '{0}': {1}")]
+    InvalidBox(String, String),
+
+    #[error(
+        "Vagrant box '{0}' is made for the provider '{1}'.\n\nChoose the libvirt or virtualbox version of the box."
+    )]
+    UnsupportedBoxProvider(String, String),
+
+    #[error("Cannot use OCI image '{0}': {1}")]
+    InvalidOciImage(String, String),
+
+    #[error(
+        "OCI image '{0}' carries no kernel.\n\nPass one with `--kernel <file>`, and its initrd with `--initrd <file>`."
+    )]
+    OciKernelNotFound(String),
+
     // QEMU and system commands
     #[error("{}", format_qemu_not_found_help())]
     QemuNotFound,
 
+    #[error(
+        "No Secure Boot firmware with enrolled keys found for instance '{0}'.\n\nTroubleshoot:\n  - Install the UEFI firmware of your distribution (e.g. ovmf or edk2-ovmf)\n  - Boot without Secure Boot: `cubic modify --no-secure-boot {0}`\n"
+    )]
+    SecureBootFirmwareNotFound(String),
+
+    #[error(
+        "A TPM needs swtpm, which was not found.\n\nTroubleshoot:\n  - Install swtpm with your package manager\n  - Start without a TPM: `cubic modify --no-tpm <instance>`\n"
+    )]
+    SwtpmNotFound,
+
     #[error("System command '{0}' was not found on PATH")]
     SystemCommandNotFound(String),
 
+    #[error(
+        "Building a disk from an OCI image needs mke2fs of e2fsprogs 1.47.1 or newer.\n\nInstall e2fsprogs with your package manager."
+    )]
+    Mke2fsNotFound,
+
+    #[error(
+        "Building a disk from an OCI image needs mke2fs of e2fsprogs 1.47.1 or newer, found {0}.\n\nUpdate e2fsprogs with your package manager."
+    )]
+    Mke2fsTooOld(String),
+
     #[error(
         "Failed to execute a system command.
 
@@ -131,6 +276,12 @@ Troubleshoot:
     #[error("Failed to apply port forwarding rule on the running instance: {0}")]
     HostfwdCommandFailed(String),
 
+    #[error("Instance '{0}' cannot forward more than {1} host ports")]
+    TooManyForwardedPorts(String, u16),
+
+    #[error("QEMU monitor rejected the command: {0}")]
+    MonitorCommandFailed(String),
+
     #[error("Process {0} is not running")]
     ProcessNotFound(u64),
 
@@ -178,6 +329,9 @@ Troubleshoot:
     #[error("Cannot connect to port {0} ({1})")]
     ConnectionFailed(u16, #[source] io::Error),
 
+    #[error("Cannot bind to {0} ({1})")]
+    BindFailed(SocketAddr, #[source] io::Error),
+
     #[error(
         "No available port found.\n\nAll ports are currently in use. Stop unused processes and try again."
     )]
@@ -195,6 +349,9 @@ Troubleshoot:
     #[error("The new SSH host key of instance '{0}' was not trusted")]
     SshHostKeyRejected(String),
 
+    #[error("Command on instance '{0}' failed: {1}")]
+    SshCommandFailed(String, String),
+
     #[error("SSH Error: {0}")]
     Ssh(#[from] russh::keys::ssh_key::Error),
 
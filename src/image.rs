@@ -8,6 +8,7 @@ mod image_factory;
 mod image_fetcher;
 mod image_provider;
 mod image_store;
+mod local_images;
 mod opensuse_image_provider;
 mod rockylinux_image_provider;
 mod ubuntu_image_provider;
@@ -22,6 +23,7 @@ pub use image_factory::*;
 pub use image_fetcher::*;
 pub use image_provider::*;
 pub use image_store::*;
+pub use local_images::*;
 pub use opensuse_image_provider::*;
 pub use rockylinux_image_provider::*;
 pub use ubuntu_image_provider::*;
@@ -147,6 +147,7 @@ hash_alg = "Sha512"
                 checksum_url: "checksumurl".to_string(),
                 hash_alg: HashAlg::Sha256,
                 size: None,
+                local: false,
             }],
             timestamp: 1000,
         }
@@ -179,6 +180,7 @@ hash_alg = "Sha256"
             checksum_url: "checksumurl".to_string(),
             hash_alg: HashAlg::Sha256,
             size: None,
+            local: false,
         }]);
 
         cache.write_to_file(&system, Path::new("/cache/images.toml"));
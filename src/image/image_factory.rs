@@ -1,5 +1,5 @@
 use crate::error::{Error, Result};
-use crate::image::{self, ImageCache};
+use crate::image::{self, ImageCache, ImageStore, LocalImages};
 use crate::models::{Arch, Environment, Image, ImageName};
 use crate::platform::System;
 use crate::util;
@@ -98,6 +98,7 @@ impl<'a> ImageFactory<'a> {
                             ),
                             hash_alg: image_provider.get_checksum_alg(),
                             size: Some(size),
+                            local: false,
                         })
                     } else {
                         None
@@ -215,11 +216,26 @@ impl<'a> ImageFactory<'a> {
         )
     }
 
+    fn read_local_images(&self) -> LocalImages {
+        LocalImages::read_from_file(self.system, Path::new(&self.env.get_local_image_file()))
+    }
+
     pub fn get_all_images(&self, console: &mut Console<'_>) -> Result<Vec<Image>> {
-        self.read_images(console, None)
+        let mut images = self.read_images(console, None)?;
+        images.extend(self.read_local_images().images);
+        images.sort();
+        Ok(images)
     }
 
     pub fn find_image(&self, console: &mut Console<'_>, name: &ImageName) -> Result<Image> {
+        // A committed image shadows the providers, as long as its file is left
+        if let Some(image) = self.read_local_images().find(name)
+            && ImageStore::new().exists(self.system, &self.env, image)
+        {
+            console.debug(&format!("Using local image '{name}'"));
+            return Ok(image.clone());
+        }
+
         self.read_images(console, Some(name.clone()))
             .and_then(|images| {
                 images
@@ -233,7 +249,8 @@ impl<'a> ImageFactory<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::HashAlg;
+    use crate::models::{HashAlg, UserName};
+    use crate::platform::SystemMock;
     use std::str::FromStr;
 
     fn build_image(vendor: &str, names: &[&str], arch: Arch) -> Image {
@@ -245,6 +262,7 @@ mod tests {
             checksum_url: "checksum_url".to_string(),
             hash_alg: HashAlg::Sha256,
             size: None,
+            local: false,
         }
     }
 
@@ -292,6 +310,32 @@ mod tests {
         assert_eq!(ImageFactory::find_matching_image(&[], &filter), None);
     }
 
+    #[test]
+    fn test_find_image_prefers_local_image() {
+        let env = Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            String::new(),
+            "/cache".to_string(),
+        );
+        let image = Image {
+            local: true,
+            ..build_image("debian", &["tools"], Arch::AMD64)
+        };
+        let system = SystemMock::new().add_file("/cache/images/local_debian_tools_amd64", b"");
+        LocalImages {
+            images: vec![image.clone()],
+        }
+        .write_to_file(&system, Path::new(&env.get_local_image_file()))
+        .unwrap();
+        let console = &mut Console::new(&system);
+
+        let found = ImageFactory::new(&system, &env)
+            .find_image(console, &ImageName::from_str("debian:tools:amd64").unwrap())
+            .unwrap();
+
+        assert_eq!(found, image);
+    }
+
     #[test]
     fn test_filter_arch_without_filter_keeps_all_arches() {
         assert_eq!(
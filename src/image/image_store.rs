@@ -39,6 +39,7 @@ mod tests {
             checksum_url: String::new(),
             hash_alg: HashAlg::Sha512,
             size: None,
+            local: false,
         }
     }
 
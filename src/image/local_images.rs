@@ -0,0 +1,141 @@
+use crate::error::{Error, Result};
+use crate::models::{Image, ImageName};
+use crate::platform::System;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Images committed from instances. Unlike the image cache, the list never
+/// expires, because no provider could fetch it again.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct LocalImages {
+    #[serde(default)]
+    pub images: Vec<Image>,
+}
+
+impl LocalImages {
+    pub fn read_from_file(system: &dyn System, path: &Path) -> Self {
+        system
+            .open_file(path)
+            .ok()
+            .and_then(|mut reader| Self::deserialize(&mut *reader))
+            .unwrap_or_default()
+    }
+
+    pub fn write_to_file(&self, system: &dyn System, path: &Path) -> Result<()> {
+        let mut file = system.create_file(path)?;
+        self.serialize(&mut *file)
+    }
+
+    pub fn find(&self, name: &ImageName) -> Option<&Image> {
+        self.images.iter().find(|image| {
+            image.vendor == name.get_vendor()
+                && image.arch == name.get_arch()
+                && image.names.iter().any(|n| n == name.get_name())
+        })
+    }
+
+    /// Adds the image, replacing a previous commit of the same name
+    pub fn add(&mut self, image: Image) {
+        self.images.retain(|other| {
+            other.vendor != image.vendor || other.arch != image.arch || other.names != image.names
+        });
+        self.images.push(image);
+        self.images.sort();
+    }
+
+    fn deserialize(reader: &mut dyn Read) -> Option<Self> {
+        let mut data = String::new();
+        reader.read_to_string(&mut data).ok()?;
+        toml::from_str(&data).ok()
+    }
+
+    fn serialize(&self, writer: &mut dyn Write) -> Result<()> {
+        toml::to_string(self)
+            .map(|content| writer.write_all(&content.into_bytes()))
+            .map(|_| ())
+            .map_err(Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Arch, HashAlg};
+    use crate::platform::SystemMock;
+    use std::str::FromStr;
+
+    fn build_image(name: &str, size: u64) -> Image {
+        Image {
+            vendor: "debian".to_string(),
+            names: vec![name.to_string()],
+            arch: Arch::AMD64,
+            image_url: String::new(),
+            checksum_url: String::new(),
+            hash_alg: HashAlg::Sha256,
+            size: Some(size),
+            local: true,
+        }
+    }
+
+    #[test]
+    fn test_read_from_file_returns_empty_list_when_missing() {
+        let system = SystemMock::new();
+
+        assert_eq!(
+            LocalImages::read_from_file(&system, Path::new("/cache/images/local.toml")),
+            LocalImages::default()
+        );
+    }
+
+    #[test]
+    fn test_write_to_file_then_read_from_file_round_trips() {
+        let system = SystemMock::new();
+        let mut images = LocalImages::default();
+        images.add(build_image("tools", 1024));
+
+        images
+            .write_to_file(&system, Path::new("/cache/images/local.toml"))
+            .unwrap();
+
+        assert_eq!(
+            LocalImages::read_from_file(&system, Path::new("/cache/images/local.toml")),
+            images
+        );
+    }
+
+    #[test]
+    fn test_add_replaces_image_of_same_name() {
+        let mut images = LocalImages::default();
+        images.add(build_image("tools", 1024));
+        images.add(build_image("build", 1024));
+        images.add(build_image("tools", 2048));
+
+        assert_eq!(
+            images.images,
+            [build_image("build", 1024), build_image("tools", 2048)]
+        );
+    }
+
+    #[test]
+    fn test_find_matches_vendor_name_and_arch() {
+        let mut images = LocalImages::default();
+        images.add(build_image("tools", 1024));
+
+        assert!(
+            images
+                .find(&ImageName::from_str("debian:tools:amd64").unwrap())
+                .is_some()
+        );
+        assert!(
+            images
+                .find(&ImageName::from_str("debian:tools:arm64").unwrap())
+                .is_none()
+        );
+        assert!(
+            images
+                .find(&ImageName::from_str("ubuntu:tools:amd64").unwrap())
+                .is_none()
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use crate::error::{Error, Result};
 use crate::instance::{InstanceSerializer, InstanceStore, TomlInstanceDeserializer};
-use crate::models::{DataSize, Environment, Instance, InstanceName};
+use crate::models::{DataSize, DiskEncryption, Environment, Instance, InstanceName};
 use crate::platform::System;
 use crate::qemu::QemuImg;
 use crate::qemu::QemuMonitorClient;
+use crate::qemu::Swtpm;
 use std::path::Path;
 use std::rc::Rc;
 use std::str;
@@ -120,18 +121,84 @@ impl InstanceStore for InstanceDao {
             Err(Error::InstanceNotStopped(instance.name.to_string()))
         } else if instance.disk_capacity.get_bytes() >= size as usize {
             Err(Error::CannotShrinkDisk(instance.name.to_string()))
+        } else {
+            let qemu_img = QemuImg::new(self.system.as_ref());
+            let image = self.env.get_instance_image_file(&instance.name);
+            match instance.encryption {
+                None => qemu_img.resize(&image, size)?,
+                Some(DiskEncryption::File) => qemu_img.resize_encrypted(
+                    &image,
+                    size,
+                    &self.env.get_instance_disk_secret_file(&instance.name),
+                )?,
+                Some(DiskEncryption::Prompt) => {
+                    return Err(Error::EncryptedDiskNotSupported(
+                        instance.name.to_string(),
+                        "resize".to_string(),
+                    ));
+                }
+            }
+            instance.disk_capacity = DataSize::new(size as usize);
+            Ok(())
+        }
+    }
+
+    fn shrink(&self, instance: &mut Instance, size: u64) -> Result<()> {
+        if self.is_running(instance) {
+            Err(Error::InstanceNotStopped(instance.name.to_string()))
+        } else if instance.disk_capacity.get_bytes() <= size as usize {
+            self.resize(instance, size)
+        } else if instance.encryption.is_some() {
+            Err(Error::EncryptedDiskNotSupported(
+                instance.name.to_string(),
+                "shrink".to_string(),
+            ))
         } else {
             QemuImg::new(self.system.as_ref())
-                .resize(&self.env.get_instance_image_file(&instance.name), size)?;
+                .shrink(&self.env.get_instance_image_file(&instance.name), size)?;
             instance.disk_capacity = DataSize::new(size as usize);
             Ok(())
         }
     }
 
+    // Rewriting the image leaves out the clusters the guest discarded
+    fn compact(&self, instance: &Instance) -> Result<()> {
+        if self.is_running(instance) {
+            return Err(Error::InstanceNotStopped(instance.name.to_string()));
+        }
+
+        if instance.encryption.is_some() {
+            return Err(Error::EncryptedDiskNotSupported(
+                instance.name.to_string(),
+                "compact".to_string(),
+            ));
+        }
+
+        let image = self.env.get_instance_image_file(&instance.name);
+        let tmp_image = format!("{image}.compact");
+        let result = QemuImg::new(self.system.as_ref())
+            .convert(&image, &tmp_image)
+            .and_then(|_| {
+                self.system
+                    .rename_file(Path::new(&tmp_image), Path::new(&image))
+            });
+        if result.is_err() {
+            self.system.remove_file(Path::new(&tmp_image)).ok();
+        }
+        result
+    }
+
     fn delete(&self, instance: &Instance) -> Result<()> {
         if self.is_running(instance) {
             Err(Error::InstanceNotStopped(instance.name.to_string()))
         } else {
+            // A swtpm that never saw QEMU connect would outlive the directory
+            Swtpm::new(
+                self.system.as_ref(),
+                Path::new(&self.env.get_instance_tpm_dir(&instance.name)),
+            )
+            .stop()
+            .ok();
             self.system
                 .remove_dir(Path::new(&self.env.get_instance_dir2(&instance.name)))
                 .ok();
@@ -166,6 +233,14 @@ impl InstanceStore for InstanceDao {
             self.system
                 .remove_file(Path::new(&self.env.get_qemu_pid_file(&instance.name)))
                 .ok();
+            if instance.tpm {
+                Swtpm::new(
+                    self.system.as_ref(),
+                    Path::new(&self.env.get_instance_tpm_dir(&instance.name)),
+                )
+                .stop()
+                .ok();
+            }
         }
         result
     }
@@ -173,6 +248,10 @@ impl InstanceStore for InstanceDao {
     fn get_monitor(&self, instance: &Instance) -> Result<QemuMonitorClient> {
         QemuMonitorClient::new(&self.env, instance)
     }
+
+    fn get_event_listener(&self, instance: &Instance) -> Result<QemuMonitorClient> {
+        QemuMonitorClient::new_event_listener(&self.env, instance)
+    }
 }
 
 #[cfg(test)]
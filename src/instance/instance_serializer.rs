@@ -21,7 +21,7 @@ impl InstanceSerializer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{Arch, DataSize, UserName};
+    use crate::models::{Arch, DataSize, DiskCache, DiskEncryption, DiskSettings, UserName};
     use std::str::FromStr;
 
     #[test]
@@ -57,6 +57,11 @@ disk_capacity = 1000
 ssh_port = 10000
 hostfwd = []
 isolate = false
+reclaim_memory = false
+secure_boot = false
+tpm = false
+display = false
+disks = []
 "#
         );
     }
@@ -78,7 +83,15 @@ isolate = false
                     hostfwd: Vec::new(),
                     execute: Some("echo hello world".to_string()),
                     isolate: true,
+                    reclaim_memory: true,
+                    encryption: Some(DiskEncryption::File),
                     ssh_host_key: Some("ssh-ed25519 AAAA".to_string()),
+                    secure_boot: true,
+                    disk_settings: DiskSettings {
+                        cache: Some(DiskCache::None),
+                        iothread: true,
+                        ..DiskSettings::default()
+                    },
                     ..Instance::default()
                 },
                 &mut writer,
@@ -97,7 +110,17 @@ ssh_port = 10000
 hostfwd = []
 execute = "echo hello world"
 isolate = true
+reclaim_memory = true
+encryption = "file"
 ssh_host_key = "ssh-ed25519 AAAA"
+secure_boot = true
+tpm = false
+display = false
+disks = []
+
+[disk_settings]
+cache = "none"
+iothread = true
 "#
         );
     }
@@ -11,6 +11,8 @@ pub trait InstanceStore {
 
     fn rename(&self, instance: &mut Instance, new_name: &str) -> Result<()>;
     fn resize(&self, instance: &mut Instance, size: u64) -> Result<()>;
+    fn shrink(&self, instance: &mut Instance, size: u64) -> Result<()>;
+    fn compact(&self, instance: &Instance) -> Result<()>;
     fn delete(&self, instance: &Instance) -> Result<()>;
 
     fn is_running(&self, instance: &Instance) -> bool;
@@ -18,4 +20,6 @@ pub trait InstanceStore {
     fn kill(&self, instance: &Instance) -> Result<()>;
 
     fn get_monitor(&self, instance: &Instance) -> Result<QemuMonitorClient>;
+
+    fn get_event_listener(&self, instance: &Instance) -> Result<QemuMonitorClient>;
 }
@@ -68,6 +68,14 @@ pub mod tests {
             Ok(())
         }
 
+        fn shrink(&self, _instance: &mut Instance, _size: u64) -> Result<()> {
+            Ok(())
+        }
+
+        fn compact(&self, _instance: &Instance) -> Result<()> {
+            Ok(())
+        }
+
         fn delete(&self, _instance: &Instance) -> Result<()> {
             Ok(())
         }
@@ -91,5 +99,9 @@ pub mod tests {
         fn get_monitor(&self, instance: &Instance) -> Result<QemuMonitorClient> {
             Err(Error::InstanceNotRunning(instance.name.clone()))
         }
+
+        fn get_event_listener(&self, instance: &Instance) -> Result<QemuMonitorClient> {
+            Err(Error::InstanceNotRunning(instance.name.clone()))
+        }
     }
 }
@@ -29,7 +29,7 @@ impl TomlInstanceDeserializer {
 #[cfg(test)]
 mod tests {
     use super::*;
-
+    use crate::models::{DiskAio, DiskBus};
     use std::io::BufReader;
 
     #[test]
@@ -81,6 +81,10 @@ hostfwd = ["tcp:127.0.0.1:8000-:8000", "tcp:127.0.0.1:9000-:10000"]
 execute = "sudo apt update"
 isolate = true
 ssh_host_key = "ssh-ed25519 AAAA"
+
+[disk_settings]
+aio = "io_uring"
+bus = "virtio-scsi"
 "#
             .as_bytes(),
         );
@@ -105,6 +109,8 @@ ssh_host_key = "ssh-ed25519 AAAA"
         assert_eq!(instance.execute, Some("sudo apt update".to_string()));
         assert!(instance.isolate);
         assert_eq!(instance.ssh_host_key, Some("ssh-ed25519 AAAA".to_string()));
+        assert_eq!(instance.disk_settings.aio, Some(DiskAio::IoUring));
+        assert_eq!(instance.disk_settings.get_bus(), DiskBus::VirtioScsi);
     }
 
     #[test]
@@ -128,4 +134,23 @@ ssh_port = 14357
         assert_eq!(instance.cpus, 4);
         assert_eq!(instance.ssh_port, 14357);
     }
+
+    #[test]
+    fn test_deserialize_rejects_a_boot_file_outside_the_instance_dir() {
+        let reader = &mut BufReader::new(
+            r#"
+cpus = 1
+mem = 1073741824
+disk_capacity = 2361393152
+ssh_port = 14357
+kernel = "../../vmlinuz"
+"#
+            .as_bytes(),
+        );
+
+        assert!(matches!(
+            TomlInstanceDeserializer::new().deserialize("test", reader),
+            Err(Error::InvalidInstanceConfig { name, .. }) if name == "test"
+        ));
+    }
 }
@@ -46,8 +46,10 @@ mod tests {
     #[test]
     fn test_write_dir_record() {
         let writer = &mut BinaryWriter::new(Cursor::new(Vec::new()));
-        let mut dir = DirRecord::default();
-        dir.len = 34;
+        let mut dir = DirRecord {
+            len: 34,
+            ..DirRecord::default()
+        };
         dir.extend_attr_len = 1;
         dir.extend_loc = 0xABCDEF01;
         dir.data_len = 0x98765432;
@@ -80,9 +82,11 @@ mod tests {
     #[test]
     fn test_write_dir_record_with_odd_file_id_skips_padding() {
         let writer = &mut BinaryWriter::new(Cursor::new(Vec::new()));
-        let mut dir = DirRecord::default();
-        dir.file_id_len = 5;
-        dir.file_id = "fooba".to_string();
+        let dir = DirRecord {
+            file_id_len: 5,
+            file_id: "fooba".to_string(),
+            ..DirRecord::default()
+        };
         dir.write(writer).unwrap();
 
         let result = writer.get_writer().get_ref();
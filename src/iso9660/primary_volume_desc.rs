@@ -80,8 +80,10 @@ mod tests {
 
     #[test]
     fn test_write() {
-        let mut pvd = PrimaryVolumeDesc::default();
-        pvd.vd = VolumeDesc::new(0x01);
+        let mut pvd = PrimaryVolumeDesc {
+            vd: VolumeDesc::new(0x01),
+            ..PrimaryVolumeDesc::default()
+        };
         pvd.system_id = "system id".to_string();
         pvd.volume_id = "volume id".to_string();
         pvd.volume_space_size = 0x12345678;
@@ -7,10 +7,15 @@ mod image;
 mod instance;
 mod iso9660;
 mod models;
+mod oci;
+mod ova;
 mod platform;
 mod qemu;
+mod recording;
+mod screenshot;
 mod ssh;
 mod util;
+mod vagrant;
 mod view;
 mod web;
 
@@ -1,31 +1,49 @@
 mod arch;
+mod backup;
 mod data_size;
+mod disk;
+mod disk_encryption;
+mod disk_layout;
+mod disk_settings;
 mod environment;
+mod export_format;
 mod image;
 mod image_name;
 mod instance;
 mod instance_cert_paths;
 mod instance_image_name;
 mod instance_name;
+mod key_combo;
 mod port_forward;
 mod resource_allocator;
 mod target;
 mod target_instance_path;
 mod target_path;
+mod time_span;
 mod user_name;
+mod vagrant_box;
 
 pub use arch::*;
+pub use backup::*;
 pub use data_size::*;
+pub use disk::*;
+pub use disk_encryption::*;
+pub use disk_layout::*;
+pub use disk_settings::*;
 pub use environment::*;
+pub use export_format::*;
 pub use image::*;
 pub use image_name::*;
 pub use instance::*;
 pub use instance_cert_paths::*;
 pub use instance_image_name::*;
 pub use instance_name::*;
+pub use key_combo::*;
 pub use port_forward::*;
 pub use resource_allocator::*;
 pub use target::*;
 pub use target_instance_path::*;
 pub use target_path::*;
+pub use time_span::*;
 pub use user_name::*;
+pub use vagrant_box::*;
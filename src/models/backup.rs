@@ -0,0 +1,213 @@
+use crate::platform::System;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackupKind {
+    Full,
+    Incremental,
+}
+
+impl BackupKind {
+    fn get_suffix(&self) -> &str {
+        match self {
+            BackupKind::Full => "full",
+            BackupKind::Incremental => "inc",
+        }
+    }
+}
+
+impl fmt::Display for BackupKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupKind::Full => write!(f, "full"),
+            BackupKind::Incremental => write!(f, "incremental"),
+        }
+    }
+}
+
+/// One image of a backup chain, named `<id>-full.qcow2` or `<id>-inc.qcow2`.
+/// The id is the creation time in seconds since the Unix epoch. Every
+/// incremental image has the one before it as its backing file, so the chain
+/// up to any image reads as the disk at that time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backup {
+    pub id: u64,
+    pub kind: BackupKind,
+    pub path: PathBuf,
+}
+
+impl Backup {
+    pub fn new(dir: &Path, id: u64, kind: BackupKind) -> Self {
+        Self {
+            id,
+            kind,
+            path: dir.join(format!("{id}-{}.qcow2", kind.get_suffix())),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.strip_suffix(".qcow2")?;
+        let (id, suffix) = name.split_once('-')?;
+        let kind = match suffix {
+            "full" => BackupKind::Full,
+            "inc" => BackupKind::Incremental,
+            _ => return None,
+        };
+        Some(Self {
+            id: id.parse().ok()?,
+            kind,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Backups of an instance live in a directory named after it
+    pub fn get_instance_dir(dir: &Path, instance: &str) -> PathBuf {
+        dir.join(instance)
+    }
+
+    /// The instance config at the time of the backup, named `<id>.toml`
+    pub fn get_config_file(&self) -> PathBuf {
+        self.path.with_file_name(format!("{}.toml", self.id))
+    }
+
+    /// Copy of a kernel or initrd from the instance directory, named
+    /// `<id>-<file>`
+    pub fn get_boot_file(&self, file: &str) -> PathBuf {
+        self.path.with_file_name(format!("{}-{file}", self.id))
+    }
+
+    /// Copy of the UEFI variable store, named `<id>.nvram.fd`
+    pub fn get_nvram_file(&self) -> PathBuf {
+        self.path.with_file_name(format!("{}.nvram.fd", self.id))
+    }
+
+    /// Copy of a file of the TPM state, named `<id>.tpm.<file>`
+    pub fn get_tpm_file(&self, file: &str) -> PathBuf {
+        self.path.with_file_name(format!("{}.tpm.{file}", self.id))
+    }
+
+    /// The TPM state files kept with this backup, along with the name each
+    /// has in the state directory
+    pub fn read_tpm_files(&self, system: &dyn System) -> Vec<(PathBuf, String)> {
+        let prefix = format!("{}.tpm.", self.id);
+        system
+            .read_dir(self.path.parent().unwrap_or(Path::new("")))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|path| {
+                let file = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_prefix(&prefix)?
+                    .to_string();
+                Some((path, file))
+            })
+            .collect()
+    }
+
+    pub fn get_file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// The backups in a directory, oldest first
+    pub fn read_chain(system: &dyn System, dir: &Path) -> Vec<Backup> {
+        let mut chain = system
+            .read_dir(dir)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|path| Backup::from_path(path))
+            .collect::<Vec<_>>();
+        chain.sort_by_key(|backup| backup.id);
+        chain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::SystemMock;
+
+    #[test]
+    fn test_new_names_the_file_after_id_and_kind() {
+        let backup = Backup::new(
+            Path::new("/backup/test"),
+            1760000000,
+            BackupKind::Incremental,
+        );
+
+        assert_eq!(backup.get_file_name(), "1760000000-inc.qcow2");
+    }
+
+    #[test]
+    fn test_config_and_boot_files_are_named_after_the_id() {
+        let backup = Backup::new(Path::new("/backup/test"), 1760000000, BackupKind::Full);
+
+        assert_eq!(
+            backup.get_config_file(),
+            Path::new("/backup/test/1760000000.toml")
+        );
+        assert_eq!(
+            backup.get_boot_file("vmlinuz"),
+            Path::new("/backup/test/1760000000-vmlinuz")
+        );
+        assert_eq!(
+            backup.get_nvram_file(),
+            Path::new("/backup/test/1760000000.nvram.fd")
+        );
+    }
+
+    #[test]
+    fn test_read_tpm_files_keeps_to_its_own_id() {
+        let system = SystemMock::new()
+            .add_file("/backup/test/1760000000.tpm.tpm2-00.permall", b"")
+            .add_file("/backup/test/1760000200.tpm.tpm2-00.permall", b"")
+            .add_file("/backup/test/1760000000-full.qcow2", b"");
+        let backup = Backup::new(Path::new("/backup/test"), 1760000000, BackupKind::Full);
+
+        assert_eq!(
+            backup.read_tpm_files(&system),
+            [(
+                PathBuf::from("/backup/test/1760000000.tpm.tpm2-00.permall"),
+                "tpm2-00.permall".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_from_path_rejects_foreign_files() {
+        assert!(Backup::from_path(Path::new("/backup/test/1760000000.toml")).is_none());
+        assert!(Backup::from_path(Path::new("/backup/test/notes-full.qcow2")).is_none());
+        assert!(Backup::from_path(Path::new("/backup/test/1760000000-diff.qcow2")).is_none());
+    }
+
+    #[test]
+    fn test_read_chain_sorts_by_id() {
+        let system = SystemMock::new()
+            .add_file("/backup/test/1760000200-inc.qcow2", b"")
+            .add_file("/backup/test/1760000000-full.qcow2", b"")
+            .add_file("/backup/test/1760000000.toml", b"");
+
+        let chain = Backup::read_chain(&system, Path::new("/backup/test"));
+
+        assert_eq!(
+            chain,
+            [
+                Backup::new(Path::new("/backup/test"), 1760000000, BackupKind::Full),
+                Backup::new(
+                    Path::new("/backup/test"),
+                    1760000200,
+                    BackupKind::Incremental
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_chain_of_missing_dir_is_empty() {
+        assert!(Backup::read_chain(&SystemMock::new(), Path::new("/backup/test")).is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::models::Environment;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+static DISK_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new("^[\\w_-]+$").unwrap());
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiskName {
+    name: String,
+}
+
+impl DiskName {
+    pub fn as_str(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+impl FromStr for DiskName {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if DISK_NAME_REGEX.is_match(name) {
+            Ok(Self {
+                name: name.to_string(),
+            })
+        } else {
+            Err("Disk name must only contain letters, numbers, underlines and dashes".to_string())
+        }
+    }
+}
+
+impl fmt::Display for DiskName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// A data disk next to the system disk of an instance
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Disk {
+    pub name: String,
+    pub format: String,
+    /// Path of an attached existing file. None for a volume that cubic
+    /// created in the instance directory, which goes away with the instance.
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+impl Disk {
+    pub fn get_path(&self, env: &Environment, instance: &str) -> String {
+        self.file
+            .clone()
+            .unwrap_or_else(|| env.get_instance_disk_file(instance, &self.name))
+    }
+
+    pub fn is_owned(&self) -> bool {
+        self.file.is_none()
+    }
+
+    // Names both the block node and the device in QEMU
+    pub fn get_qemu_id(&self) -> String {
+        format!("disk-{}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::UserName;
+    use std::path::PathBuf;
+
+    fn build_env() -> Environment {
+        Environment::new(
+            UserName::from_str("cubic").unwrap(),
+            "/data".to_string(),
+            "/cache".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_owned_disk_lives_in_the_instance_dir() {
+        let disk = Disk {
+            name: "data".to_string(),
+            format: "qcow2".to_string(),
+            file: None,
+        };
+
+        assert!(disk.is_owned());
+        assert_eq!(
+            PathBuf::from(disk.get_path(&build_env(), "test")),
+            PathBuf::from("/data")
+                .join("machines")
+                .join("test")
+                .join("disk-data.qcow2")
+        );
+    }
+
+    #[test]
+    fn test_attached_disk_keeps_its_file() {
+        let disk = Disk {
+            name: "data".to_string(),
+            format: "raw".to_string(),
+            file: Some("/srv/data.img".to_string()),
+        };
+
+        assert!(!disk.is_owned());
+        assert_eq!(disk.get_path(&build_env(), "test"), "/srv/data.img");
+    }
+
+    #[test]
+    fn test_disk_name_rejects_path_traversal() {
+        assert!(DiskName::from_str("../etc").is_err());
+    }
+
+    #[test]
+    fn test_disk_name_accepts_dashes() {
+        assert_eq!(DiskName::from_str("pg-data").unwrap().as_str(), "pg-data");
+    }
+}
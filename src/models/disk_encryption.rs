@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where the passphrase of an encrypted system disk comes from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskEncryption {
+    /// Kept in the instance directory, readable by the owner only
+    File,
+    /// Asked for on every start
+    Prompt,
+}
+
+impl fmt::Display for DiskEncryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskEncryption::File => write!(f, "LUKS (passphrase file)"),
+            DiskEncryption::Prompt => write!(f, "LUKS (passphrase prompt)"),
+        }
+    }
+}
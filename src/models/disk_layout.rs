@@ -0,0 +1,222 @@
+const SECTOR_SIZE: u64 = 512;
+const GPT_SIGNATURE: &[u8] = b"EFI PART";
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+const MBR_PROTECTIVE_TYPE: u8 = 0xee;
+const EXT4_SUPERBLOCK: usize = 1024;
+const EXT4_MAGIC: u16 = 0xef53;
+const EXT4_FEATURE_INCOMPAT_64BIT: u32 = 0x80;
+
+/// Where the data on a guest disk ends, read from the first bytes of the
+/// disk without booting the guest. Sectors are taken to be 512 bytes, which
+/// is what QEMU presents to the guest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiskLayout {
+    /// GUID partition table. Its backup header sits in the last sector, so a
+    /// shrink cuts it off. The guest boots from the primary header, and
+    /// growpart or `sgdisk -e` writes the backup at the new end.
+    Gpt { end: u64 },
+    /// DOS partition table
+    Mbr { end: u64 },
+    /// ext4 filesystem without a partition table, as on images built from
+    /// OCI containers
+    Ext4 { end: u64 },
+}
+
+impl DiskLayout {
+    /// Bytes from the start of the disk that hold everything `parse` reads
+    pub const HEAD_SIZE: u64 = 1024 * 1024;
+
+    pub fn parse(head: &[u8]) -> Option<Self> {
+        Self::parse_gpt(head)
+            .map(|end| DiskLayout::Gpt { end })
+            .or_else(|| Self::parse_mbr(head).map(|end| DiskLayout::Mbr { end }))
+            .or_else(|| Self::parse_ext4(head).map(|end| DiskLayout::Ext4 { end }))
+    }
+
+    /// First byte past the last partition or filesystem block
+    pub fn get_end(&self) -> u64 {
+        match self {
+            DiskLayout::Gpt { end } | DiskLayout::Mbr { end } | DiskLayout::Ext4 { end } => *end,
+        }
+    }
+
+    fn parse_gpt(head: &[u8]) -> Option<u64> {
+        let header = head.get(SECTOR_SIZE as usize..)?;
+        if !header.starts_with(GPT_SIGNATURE) {
+            return None;
+        }
+        let entries_lba = read_u64(header, 72)?;
+        let entry_count = read_u32(header, 80)? as u64;
+        let entry_size = read_u32(header, 84)? as u64;
+        if entry_size < 48 {
+            return None;
+        }
+
+        // Entries past the head leave the end unknown
+        let mut end =
+            (entries_lba + (entry_count * entry_size).div_ceil(SECTOR_SIZE)) * SECTOR_SIZE;
+        for index in 0..entry_count {
+            let offset = usize::try_from(entries_lba * SECTOR_SIZE + index * entry_size).ok()?;
+            let entry = head.get(offset..offset + entry_size as usize)?;
+            if entry[..16].iter().all(|byte| *byte == 0) {
+                continue;
+            }
+            end = end.max((read_u64(entry, 40)? + 1) * SECTOR_SIZE);
+        }
+        Some(end)
+    }
+
+    fn parse_mbr(head: &[u8]) -> Option<u64> {
+        if head.get(510..512)? != MBR_SIGNATURE {
+            return None;
+        }
+
+        let mut end = None;
+        for index in 0..4 {
+            let entry = head.get(446 + index * 16..446 + (index + 1) * 16)?;
+            match entry[4] {
+                0 => continue,
+                // A protective entry without a GPT header behind it
+                MBR_PROTECTIVE_TYPE => return None,
+                _ => {}
+            }
+            let start = read_u32(entry, 8)? as u64;
+            let sectors = read_u32(entry, 12)? as u64;
+            end = end.max(Some((start + sectors) * SECTOR_SIZE));
+        }
+        end
+    }
+
+    fn parse_ext4(head: &[u8]) -> Option<u64> {
+        let superblock = head.get(EXT4_SUPERBLOCK..)?;
+        if read_u16(superblock, 0x38)? != EXT4_MAGIC {
+            return None;
+        }
+
+        let mut blocks = read_u32(superblock, 0x04)? as u64;
+        if read_u32(superblock, 0x60)? & EXT4_FEATURE_INCOMPAT_64BIT != 0 {
+            blocks |= (read_u32(superblock, 0x150)? as u64) << 32;
+        }
+        let block_size = 1024u64.checked_shl(read_u32(superblock, 0x18)?)?;
+        blocks.checked_mul(block_size)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A GPT disk with one partition per (first, last) LBA
+    fn build_gpt(partitions: &[(u64, u64)]) -> Vec<u8> {
+        let mut head = vec![0; DiskLayout::HEAD_SIZE as usize];
+        head[510..512].copy_from_slice(&MBR_SIGNATURE);
+        head[446 + 4] = MBR_PROTECTIVE_TYPE;
+        head[512..520].copy_from_slice(GPT_SIGNATURE);
+        head[512 + 72..512 + 80].copy_from_slice(&2u64.to_le_bytes());
+        head[512 + 80..512 + 84].copy_from_slice(&128u32.to_le_bytes());
+        head[512 + 84..512 + 88].copy_from_slice(&128u32.to_le_bytes());
+        for (index, (first, last)) in partitions.iter().enumerate() {
+            let entry = 1024 + index * 128;
+            head[entry..entry + 16].fill(0xaa);
+            head[entry + 32..entry + 40].copy_from_slice(&first.to_le_bytes());
+            head[entry + 40..entry + 48].copy_from_slice(&last.to_le_bytes());
+        }
+        head
+    }
+
+    #[test]
+    fn test_gpt_ends_after_the_last_partition() {
+        let head = build_gpt(&[(2048, 4095), (4096, 2097151)]);
+
+        assert_eq!(
+            DiskLayout::parse(&head),
+            Some(DiskLayout::Gpt { end: 2097152 * 512 })
+        );
+    }
+
+    #[test]
+    fn test_gpt_without_partitions_ends_after_its_entries() {
+        assert_eq!(
+            DiskLayout::parse(&build_gpt(&[])),
+            Some(DiskLayout::Gpt { end: 34 * 512 })
+        );
+    }
+
+    #[test]
+    fn test_gpt_with_entries_past_the_head_is_unknown() {
+        let mut head = build_gpt(&[(2048, 4095)]);
+        head[512 + 72..512 + 80].copy_from_slice(&4096u64.to_le_bytes());
+
+        assert_eq!(DiskLayout::parse(&head), None);
+    }
+
+    #[test]
+    fn test_mbr_ends_after_the_last_partition() {
+        let mut head = vec![0; 1024];
+        head[510..512].copy_from_slice(&MBR_SIGNATURE);
+        head[446 + 4] = 0x83;
+        head[446 + 8..446 + 12].copy_from_slice(&2048u32.to_le_bytes());
+        head[446 + 12..446 + 16].copy_from_slice(&4096u32.to_le_bytes());
+        head[462 + 4] = 0x82;
+        head[462 + 8..462 + 12].copy_from_slice(&6144u32.to_le_bytes());
+        head[462 + 12..462 + 16].copy_from_slice(&2048u32.to_le_bytes());
+
+        assert_eq!(
+            DiskLayout::parse(&head),
+            Some(DiskLayout::Mbr { end: 8192 * 512 })
+        );
+    }
+
+    #[test]
+    fn test_ext4_ends_after_its_last_block() {
+        let mut head = vec![0; 4096];
+        head[1024 + 0x04..1024 + 0x08].copy_from_slice(&1000u32.to_le_bytes());
+        head[1024 + 0x18..1024 + 0x1c].copy_from_slice(&2u32.to_le_bytes());
+        head[1024 + 0x38..1024 + 0x3a].copy_from_slice(&EXT4_MAGIC.to_le_bytes());
+
+        assert_eq!(
+            DiskLayout::parse(&head),
+            Some(DiskLayout::Ext4 { end: 1000 * 4096 })
+        );
+    }
+
+    #[test]
+    fn test_ext4_reads_the_high_block_count_of_64bit_filesystems() {
+        let mut head = vec![0; 4096];
+        head[1024 + 0x18..1024 + 0x1c].copy_from_slice(&2u32.to_le_bytes());
+        head[1024 + 0x38..1024 + 0x3a].copy_from_slice(&EXT4_MAGIC.to_le_bytes());
+        head[1024 + 0x60..1024 + 0x64].copy_from_slice(&EXT4_FEATURE_INCOMPAT_64BIT.to_le_bytes());
+        head[1024 + 0x150..1024 + 0x154].copy_from_slice(&1u32.to_le_bytes());
+
+        assert_eq!(
+            DiskLayout::parse(&head),
+            Some(DiskLayout::Ext4 {
+                end: (1 << 32) * 4096
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_layout() {
+        assert_eq!(DiskLayout::parse(&[0; 4096]), None);
+        assert_eq!(DiskLayout::parse(&[]), None);
+    }
+}
@@ -0,0 +1,249 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Host page cache mode of a disk
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskCache {
+    None,
+    Writeback,
+    Writethrough,
+    Directsync,
+    Unsafe,
+}
+
+impl DiskCache {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::None => "none",
+            Self::Writeback => "writeback",
+            Self::Writethrough => "writethrough",
+            Self::Directsync => "directsync",
+            Self::Unsafe => "unsafe",
+        }
+    }
+
+    /// Bypasses the host page cache. This and the two flags below are what a
+    /// cache mode stands for, since -blockdev takes no cache mode.
+    pub fn is_direct(&self) -> bool {
+        matches!(self, Self::None | Self::Directsync)
+    }
+
+    /// Ignores the flush requests of the guest
+    pub fn is_no_flush(&self) -> bool {
+        *self == Self::Unsafe
+    }
+
+    /// Reports a write cache to the guest, which then has to flush it
+    pub fn has_write_cache(&self) -> bool {
+        !matches!(self, Self::Writethrough | Self::Directsync)
+    }
+}
+
+/// Host I/O backend of a disk
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskAio {
+    Threads,
+    Native,
+    #[value(name = "io_uring")]
+    IoUring,
+}
+
+impl DiskAio {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Threads => "threads",
+            Self::Native => "native",
+            Self::IoUring => "io_uring",
+        }
+    }
+}
+
+/// What becomes of the blocks a guest trims
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskDiscard {
+    /// Free the blocks in the image
+    Unmap,
+    /// Keep the blocks
+    Ignore,
+}
+
+impl DiskDiscard {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Unmap => "unmap",
+            Self::Ignore => "ignore",
+        }
+    }
+}
+
+/// Whether QEMU looks for zeroed blocks in guest writes
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectZeroes {
+    Off,
+    On,
+    Unmap,
+}
+
+impl DetectZeroes {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Off => "off",
+            Self::On => "on",
+            Self::Unmap => "unmap",
+        }
+    }
+}
+
+/// Controller the system disk sits on
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiskBus {
+    VirtioBlk,
+    VirtioScsi,
+}
+
+impl DiskBus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::VirtioBlk => "virtio-blk",
+            Self::VirtioScsi => "virtio-scsi",
+        }
+    }
+}
+
+/// I/O tuning of the instance disks. Unset values keep the QEMU defaults,
+/// except for discard, which defaults to unmap.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiskSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<DiskCache>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aio: Option<DiskAio>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discard: Option<DiskDiscard>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detect_zeroes: Option<DetectZeroes>,
+    /// Run the disk I/O in a thread of its own instead of the main loop
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub iothread: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bus: Option<DiskBus>,
+}
+
+impl DiskSettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn get_discard(&self) -> DiskDiscard {
+        self.discard.unwrap_or(DiskDiscard::Unmap)
+    }
+
+    pub fn get_bus(&self) -> DiskBus {
+        self.bus.unwrap_or(DiskBus::VirtioBlk)
+    }
+
+    // Linux only accepts O_DIRECT requests for native AIO
+    pub fn is_valid(&self) -> bool {
+        self.aio != Some(DiskAio::Native) || self.cache.is_some_and(|cache| cache.is_direct())
+    }
+
+    /// The settings as -drive options, e.g. "cache=none,aio=io_uring,discard=unmap"
+    pub fn get_drive_options(&self) -> String {
+        let mut options = Vec::new();
+        if let Some(cache) = self.cache {
+            options.push(format!("cache={}", cache.as_str()));
+        }
+        if let Some(aio) = self.aio {
+            options.push(format!("aio={}", aio.as_str()));
+        }
+        options.push(format!("discard={}", self.get_discard().as_str()));
+        if let Some(detect_zeroes) = self.detect_zeroes {
+            options.push(format!("detect-zeroes={}", detect_zeroes.as_str()));
+        }
+        options.join(",")
+    }
+}
+
+impl fmt::Display for DiskSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_drive_options())?;
+        if self.iothread {
+            write!(f, ",iothread")?;
+        }
+        write!(f, ",bus={}", self.get_bus().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_io_uring() {
+        assert_eq!(
+            DiskAio::from_str("io_uring", false).unwrap(),
+            DiskAio::IoUring
+        );
+    }
+
+    #[test]
+    fn test_default_drive_options_unmap_trimmed_blocks() {
+        assert_eq!(DiskSettings::default().get_drive_options(), "discard=unmap");
+    }
+
+    #[test]
+    fn test_drive_options() {
+        let settings = DiskSettings {
+            cache: Some(DiskCache::None),
+            aio: Some(DiskAio::IoUring),
+            discard: Some(DiskDiscard::Ignore),
+            detect_zeroes: Some(DetectZeroes::Unmap),
+            ..DiskSettings::default()
+        };
+
+        assert_eq!(
+            settings.get_drive_options(),
+            "cache=none,aio=io_uring,discard=ignore,detect-zeroes=unmap"
+        );
+    }
+
+    #[test]
+    fn test_display_names_iothread_and_bus() {
+        let settings = DiskSettings {
+            iothread: true,
+            bus: Some(DiskBus::VirtioScsi),
+            ..DiskSettings::default()
+        };
+
+        assert_eq!(
+            settings.to_string(),
+            "discard=unmap,iothread,bus=virtio-scsi"
+        );
+    }
+
+    #[test]
+    fn test_native_aio_needs_direct_cache() {
+        let mut settings = DiskSettings {
+            aio: Some(DiskAio::Native),
+            ..DiskSettings::default()
+        };
+        assert!(!settings.is_valid());
+
+        settings.cache = Some(DiskCache::Directsync);
+        assert!(settings.is_valid());
+    }
+
+    #[test]
+    fn test_cache_flags() {
+        assert!(DiskCache::None.is_direct());
+        assert!(DiskCache::None.has_write_cache());
+        assert!(!DiskCache::Directsync.has_write_cache());
+        assert!(DiskCache::Unsafe.is_no_flush());
+    }
+}
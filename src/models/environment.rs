@@ -54,6 +54,14 @@ impl Environment {
             .into_owned()
     }
 
+    /// Registry of the images committed from instances
+    pub fn get_local_image_file(&self) -> String {
+        PathBuf::from(self.get_image_dir())
+            .join("local.toml")
+            .to_string_lossy()
+            .into_owned()
+    }
+
     pub fn get_instance_dir2(&self, instance: &str) -> String {
         PathBuf::from(self.get_instance_dir())
             .join(instance)
@@ -75,6 +83,29 @@ impl Environment {
             .into_owned()
     }
 
+    pub fn get_instance_disk_file(&self, instance: &str, disk: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join(format!("disk-{disk}.qcow2"))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn get_instance_disk_secret_file(&self, instance: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join("disk_secret")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Raw copy of the start of the system disk, kept while a shrink reads
+    /// its partition table
+    pub fn get_instance_disk_head_file(&self, instance: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join("disk_head")
+            .to_string_lossy()
+            .into_owned()
+    }
+
     pub fn get_cloud_init_file(&self, instance: &str) -> String {
         PathBuf::from(self.get_instance_dir2(instance))
             .join("cloud-init.iso")
@@ -96,6 +127,49 @@ impl Environment {
             .into_owned()
     }
 
+    /// Vagrant insecure key of an instance created from a box, kept until
+    /// the first login replaces it with the cubic key
+    pub fn get_vagrant_key_file(&self, instance: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join("vagrant_insecure_key")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Serial console output of the current run, next to the one before
+    /// with the suffix `.1`
+    pub fn get_instance_console_log_file(&self, instance: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join("console.log")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// UEFI variable store of an instance, copied from the firmware template
+    pub fn get_instance_nvram_file(&self, instance: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join("nvram.fd")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// State of the software TPM of an instance
+    pub fn get_instance_tpm_dir(&self, instance: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join("tpm")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Kernel or initrd of an instance. A relative path names a file in the
+    /// instance directory, so it moves along with a rename.
+    pub fn get_instance_boot_file(&self, instance: &str, file: &str) -> String {
+        PathBuf::from(self.get_instance_dir2(instance))
+            .join(file)
+            .to_string_lossy()
+            .into_owned()
+    }
+
     pub fn get_home_ssh_private_key_paths(&self, system: &dyn System) -> Vec<String> {
         let mut private_keys = Vec::new();
 
@@ -172,6 +246,10 @@ mod tests {
             PathBuf::from(env.get_image_cache_file()),
             PathBuf::from("/cache/cubic").join("images.cache")
         );
+        assert_eq!(
+            PathBuf::from(env.get_local_image_file()),
+            join_all("/cache/cubic", &["images", "local.toml"])
+        );
         assert_eq!(
             PathBuf::from(env.get_instance_dir2("mymachine")),
             join_all("/data/cubic", &["machines", "mymachine"])
@@ -184,10 +262,30 @@ mod tests {
             PathBuf::from(env.get_instance_image_file("mymachine")),
             join_all("/data/cubic", &["machines", "mymachine", "machine.img"])
         );
+        assert_eq!(
+            PathBuf::from(env.get_instance_disk_file("mymachine", "data")),
+            join_all("/data/cubic", &["machines", "mymachine", "disk-data.qcow2"])
+        );
+        assert_eq!(
+            PathBuf::from(env.get_instance_disk_secret_file("mymachine")),
+            join_all("/data/cubic", &["machines", "mymachine", "disk_secret"])
+        );
         assert_eq!(
             PathBuf::from(env.get_cloud_init_file("mymachine")),
             join_all("/data/cubic", &["machines", "mymachine", "cloud-init.iso"])
         );
+        assert_eq!(
+            PathBuf::from(env.get_instance_nvram_file("mymachine")),
+            join_all("/data/cubic", &["machines", "mymachine", "nvram.fd"])
+        );
+        assert_eq!(
+            PathBuf::from(env.get_instance_console_log_file("mymachine")),
+            join_all("/data/cubic", &["machines", "mymachine", "console.log"])
+        );
+        assert_eq!(
+            PathBuf::from(env.get_instance_tpm_dir("mymachine")),
+            join_all("/data/cubic", &["machines", "mymachine", "tpm"])
+        );
         assert_eq!(
             PathBuf::from(env.get_qemu_pid_file("mymachine")),
             join_all("/data/cubic", &["machines", "mymachine", "qemu.pid"])
@@ -0,0 +1,71 @@
+use clap::ValueEnum;
+
+/// Disk format for handing an instance to another hypervisor
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Vmdk,
+    Vdi,
+    Vhdx,
+    Raw,
+    Ova,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Vmdk => "vmdk",
+            Self::Vdi => "vdi",
+            Self::Vhdx => "vhdx",
+            Self::Raw => "raw",
+            Self::Ova => "ova",
+        }
+    }
+
+    pub fn get_extension(&self) -> &str {
+        match self {
+            Self::Raw => "img",
+            _ => self.as_str(),
+        }
+    }
+
+    /// The format qemu-img writes. An OVA carries its disk as VMDK.
+    pub fn get_image_format(&self) -> &str {
+        match self {
+            Self::Ova => "vmdk",
+            _ => self.as_str(),
+        }
+    }
+
+    /// The `-o` options of qemu-img for the format, if any
+    pub fn get_image_options(&self) -> Option<&str> {
+        match self {
+            // OVF only knows the stream optimized flavour of VMDK
+            Self::Ova => Some("subformat=streamOptimized"),
+            // Dynamic images only take up the space the guest wrote to
+            Self::Vhdx => Some("subformat=dynamic"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_export_format() {
+        assert_eq!(
+            ExportFormat::from_str("vhdx", false).unwrap(),
+            ExportFormat::Vhdx
+        );
+    }
+
+    #[test]
+    fn test_ova_disk_is_stream_optimized_vmdk() {
+        assert_eq!(ExportFormat::Ova.get_image_format(), "vmdk");
+        assert_eq!(
+            ExportFormat::Ova.get_image_options(),
+            Some("subformat=streamOptimized")
+        );
+    }
+}
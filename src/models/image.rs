@@ -28,6 +28,9 @@ pub struct Image {
     pub checksum_url: String,
     pub hash_alg: HashAlg,
     pub size: Option<u64>,
+    /// Committed from an instance rather than downloaded
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub local: bool,
 }
 
 impl Image {
@@ -60,7 +63,9 @@ impl Image {
     }
 
     pub fn to_file_name(&self) -> String {
-        format!("{}_{}_{}", self.vendor, self.get_name(), self.arch)
+        // A local image must never overwrite a download of the same name
+        let prefix = if self.local { "local_" } else { "" };
+        format!("{prefix}{}_{}_{}", self.vendor, self.get_name(), self.arch)
     }
 }
 
@@ -104,6 +109,7 @@ mod tests {
             checksum_url: String::new(),
             hash_alg: HashAlg::Sha512,
             size: None,
+            local: false,
         }
     }
 
@@ -160,6 +166,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_to_file_name_keeps_local_images_apart() {
+        let image = Image {
+            local: true,
+            ..build_image("debian", &["tools"])
+        };
+
+        assert_eq!(image.to_file_name(), "local_debian_tools_amd64");
+    }
+
     #[test]
     fn test_ord_compares_numeric_versions_numerically() {
         assert!(build_image("debian", &["10"]) > build_image("debian", &["9"]));
@@ -1,5 +1,10 @@
-use crate::models::{Arch, DataSize, PortForward, UserName};
-use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::models::{
+    Arch, DataSize, Disk, DiskEncryption, DiskSettings, PortForward, UserName, VagrantBox,
+};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::{Component, Path};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Instance {
@@ -20,12 +25,127 @@ pub struct Instance {
     #[serde(default)]
     pub console_port: Option<u16>,
     #[serde(default)]
+    pub events_port: Option<u16>,
+    #[serde(default)]
+    pub display_port: Option<u16>,
+    #[serde(default)]
     pub hostfwd: Vec<PortForward>,
     #[serde(default)]
     pub execute: Option<String>,
     #[serde(default)]
     pub isolate: bool,
+    /// Hand free guest memory back to the host through the balloon
+    #[serde(default)]
+    pub reclaim_memory: bool,
+    /// LUKS encryption of the system disk
+    #[serde(default)]
+    pub encryption: Option<DiskEncryption>,
     /// Guest SSH host key, pinned on the first connect
     #[serde(default)]
     pub ssh_host_key: Option<String>,
+    /// Latest backup file, the one the dirty bitmap tracks writes since
+    #[serde(default)]
+    pub backup_point: Option<String>,
+    /// I/O tuning of the disks, a table of its own once set
+    #[serde(default, skip_serializing_if = "DiskSettings::is_default")]
+    pub disk_settings: DiskSettings,
+    /// Vagrant box the instance was created from
+    #[serde(default)]
+    pub vagrant_box: Option<VagrantBox>,
+    /// OCI image the instance was created from
+    #[serde(default)]
+    pub oci_image: Option<String>,
+    /// Kernel QEMU boots directly, bypassing the firmware boot loader. A
+    /// relative path is a file name in the instance directory.
+    #[serde(default, deserialize_with = "deserialize_boot_file")]
+    pub kernel: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_boot_file")]
+    pub initrd: Option<String>,
+    /// Kernel command line
+    #[serde(default)]
+    pub append: Option<String>,
+    /// Firmware QEMU boots in place of the one it comes with
+    #[serde(default)]
+    pub firmware: Option<String>,
+    /// Boot a firmware with Secure Boot and the Microsoft keys enrolled
+    #[serde(default)]
+    pub secure_boot: bool,
+    /// Attach a software TPM 2.0, whose state lives in the instance directory
+    #[serde(default)]
+    pub tpm: bool,
+    /// Add a graphics card, shown by a VNC server on localhost
+    #[serde(default)]
+    pub display: bool,
+    /// Installer ISO that boots before the disk until the VM shuts down
+    #[serde(default)]
+    pub iso: Option<String>,
+    /// Data disks, kept last since TOML puts tables after plain values
+    #[serde(default)]
+    pub disks: Vec<Disk>,
+}
+
+impl Instance {
+    /// Kernel and initrd that live in the instance directory, which a clone
+    /// needs copies of. A relative path that climbs out of it is refused.
+    pub fn get_owned_boot_files(&self) -> Result<Vec<&str>> {
+        let mut files = Vec::new();
+        for file in [&self.kernel, &self.initrd].into_iter().flatten() {
+            if Path::new(file).is_absolute() {
+                continue;
+            }
+            if !is_boot_file_name(file) {
+                return Err(Error::InvalidBootFile(self.name.clone(), file.clone()));
+            }
+            files.push(file.as_str());
+        }
+        Ok(files)
+    }
+
+    /// amd64 machines come with a PS/2 keyboard, while arm64 ones only get
+    /// the USB keyboard that goes along with a display
+    pub fn has_keyboard(&self) -> bool {
+        self.arch == Arch::AMD64 || self.display
+    }
+
+    /// QEMU only takes an initrd and a command line along with a kernel
+    pub fn check_kernel(&self) -> Result<()> {
+        if self.kernel.is_none() {
+            if self.initrd.is_some() {
+                return Err(Error::KernelRequired(
+                    self.name.clone(),
+                    "initrd".to_string(),
+                ));
+            }
+            if self.append.is_some() {
+                return Err(Error::KernelRequired(
+                    self.name.clone(),
+                    "command line".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// A single name, so the file stays in the instance directory
+fn is_boot_file_name(file: &str) -> bool {
+    let mut components = Path::new(file).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+fn deserialize_boot_file<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    let file = Option::<String>::deserialize(deserializer)?;
+    match &file {
+        Some(name) if !Path::new(name).is_absolute() && !is_boot_file_name(name) => {
+            Err(D::Error::custom(format!(
+                "boot file '{name}' is neither an absolute path nor a file name"
+            )))
+        }
+        _ => Ok(file),
+    }
 }
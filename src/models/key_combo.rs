@@ -0,0 +1,164 @@
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+const FORMAT_ERROR: &str =
+    "Must comply with format: key[-key...] with QEMU key names (e.g. ctrl-alt-delete, ret or f12)";
+
+// Common names for keys QEMU calls differently
+const ALIASES: [(&str, &str); 7] = [
+    ("enter", "ret"),
+    ("return", "ret"),
+    ("space", "spc"),
+    ("escape", "esc"),
+    ("del", "delete"),
+    ("control", "ctrl"),
+    ("win", "meta_l"),
+];
+
+// Characters that sit on the same key as the one on the left, one level up,
+// on a US keyboard
+const SHIFTED: [(char, char); 21] = [
+    ('!', '1'),
+    ('@', '2'),
+    ('#', '3'),
+    ('$', '4'),
+    ('%', '5'),
+    ('^', '6'),
+    ('&', '7'),
+    ('*', '8'),
+    ('(', '9'),
+    (')', '0'),
+    ('_', '-'),
+    ('+', '='),
+    ('{', '['),
+    ('}', ']'),
+    ('|', '\\'),
+    (':', ';'),
+    ('"', '\''),
+    ('~', '`'),
+    ('<', ','),
+    ('>', '.'),
+    ('?', '/'),
+];
+
+/// Keys pressed at the same time, named by their QEMU key codes (qcodes)
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyCombo {
+    keys: Vec<String>,
+}
+
+impl KeyCombo {
+    pub fn get_keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Keys that type the character on a US keyboard
+    pub fn from_char(c: char) -> Option<Self> {
+        let (shift, c) = match SHIFTED.iter().find(|(shifted, _)| *shifted == c) {
+            Some((_, key)) => (true, *key),
+            None if c.is_ascii_uppercase() => (true, c.to_ascii_lowercase()),
+            None => (false, c),
+        };
+        let key = match c {
+            'a'..='z' | '0'..='9' => c.to_string(),
+            ' ' => "spc".to_string(),
+            '\n' => "ret".to_string(),
+            '\t' => "tab".to_string(),
+            '-' => "minus".to_string(),
+            '=' => "equal".to_string(),
+            '[' => "bracket_left".to_string(),
+            ']' => "bracket_right".to_string(),
+            '\\' => "backslash".to_string(),
+            ';' => "semicolon".to_string(),
+            '\'' => "apostrophe".to_string(),
+            '`' => "grave_accent".to_string(),
+            ',' => "comma".to_string(),
+            '.' => "dot".to_string(),
+            '/' => "slash".to_string(),
+            _ => return None,
+        };
+
+        let mut keys = Vec::new();
+        if shift {
+            keys.push("shift".to_string());
+        }
+        keys.push(key);
+        Some(Self { keys })
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let keys = value
+            .to_ascii_lowercase()
+            .split('-')
+            .map(|key| {
+                ALIASES
+                    .iter()
+                    .find(|(alias, _)| *alias == key)
+                    .map_or(key, |(_, qcode)| qcode)
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        let is_valid = |key: &String| {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        };
+        if keys.iter().all(is_valid) {
+            Ok(Self { keys })
+        } else {
+            Err(FORMAT_ERROR.to_string())
+        }
+    }
+}
+
+impl Display for KeyCombo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.keys.join("-"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_combo() {
+        assert_eq!(
+            KeyCombo::from_str("ctrl-alt-delete").unwrap().get_keys(),
+            ["ctrl", "alt", "delete"]
+        );
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        assert_eq!(
+            KeyCombo::from_str("Control-Enter").unwrap().get_keys(),
+            ["ctrl", "ret"]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(KeyCombo::from_str("").is_err());
+        assert!(KeyCombo::from_str("ctrl--c").is_err());
+        assert!(KeyCombo::from_str("ctrl-ä").is_err());
+    }
+
+    #[test]
+    fn test_from_char() {
+        assert_eq!(KeyCombo::from_char('a').unwrap().get_keys(), ["a"]);
+        assert_eq!(KeyCombo::from_char('A').unwrap().get_keys(), ["shift", "a"]);
+        assert_eq!(
+            KeyCombo::from_char('?').unwrap().get_keys(),
+            ["shift", "slash"]
+        );
+        assert_eq!(KeyCombo::from_char('\n').unwrap().get_keys(), ["ret"]);
+        assert_eq!(KeyCombo::from_char('é'), None);
+    }
+}
@@ -1,17 +1,32 @@
+use crate::platform::System;
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{Display, Error, Formatter};
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
+use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::LazyLock;
 
-static QEMU_PORT_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^(\w+)?:([\d.:]+)?:(\d+)-:(\d+)$").unwrap());
-static PORT_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^(([\d.:]+):)?(\d+):(\d+)(/(\w+))?$").unwrap());
-
-const FORMAT_ERROR: &str = "Must comply with format: [host_ip:]host_port:guest_port[/(udp|tcp)] (e.g. -p 8000:80 or -p 127.0.0.1:9000:90/tcp)";
+static QEMU_PORT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<protocol>\w+)?:(?P<ip>[\d.:]+)?:(?P<host>\d+(?:-\d+)?)-:(?P<guest>\d+(?:-\d+)?)$",
+    )
+    .unwrap()
+});
+static PORT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^((?P<ip>[\d.:]+):)?(?P<host>\d*(?:-\d+)?):(?P<guest>\d+(?:-\d+)?)(?:/(?P<protocol>\w+))?$",
+    )
+    .unwrap()
+});
+
+/// Number of host ports an instance forwards at most. QEMU takes all of them
+/// in a single -netdev argument, which Linux limits to 128 KiB.
+pub const MAX_FORWARDED_PORTS: u16 = 1024;
+const FORMAT_ERROR: &str = "Must comply with format: [host_ip:][host_port[-end]]:guest_port[-end][/(udp|tcp)] with ranges of the same length of at most 1024 ports (e.g. -p 8000:80, -p :80, -p 127.0.0.1:9000:90/tcp or -p 30000-30100:30000-30100)";
+// Marks a stored rule whose host port cubic picks, which QEMU has no syntax for
+const AUTO_SUFFIX: &str = ",auto";
 const QEMU_FORMAT_ERROR: &str = "Must comply with format: [tcp|udp]:[hostaddr]:hostport-[guestaddr]:guestport (e.g. ::8000-:80 or -p tcp:127.0.0.1:9000-:90)";
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -48,9 +63,13 @@ impl Display for Protocol {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PortForward {
     host_ip: IpAddr,
+    /// 0 while an automatic rule has no port yet
     host_port: u16,
     guest_port: u16,
     protocol: Protocol,
+    auto: bool,
+    /// Number of consecutive ports from the first host and guest port on
+    count: u16,
 }
 
 impl PortForward {
@@ -60,6 +79,16 @@ impl PortForward {
             host_port,
             guest_port,
             protocol,
+            auto: false,
+            count: 1,
+        }
+    }
+
+    /// Rule whose host port is picked from the free ones
+    pub fn new_auto(host_ip: IpAddr, guest_port: u16, protocol: Protocol) -> Self {
+        Self {
+            auto: true,
+            ..Self::new(host_ip, 0, guest_port, protocol)
         }
     }
 
@@ -79,63 +108,190 @@ impl PortForward {
         self.protocol
     }
 
+    pub fn is_auto(&self) -> bool {
+        self.auto
+    }
+
+    /// Host ports as a single port or a range (e.g. 30000-30100)
+    pub fn format_host_ports(&self) -> String {
+        format_ports(self.host_port, self.count)
+    }
+
+    /// Guest ports as a single port or a range (e.g. 30000-30100)
+    pub fn format_guest_ports(&self) -> String {
+        format_ports(self.guest_port, self.count)
+    }
+
+    /// Single-port rules that make up the rule, since QEMU knows no ranges
+    pub fn expand(&self) -> Vec<PortForward> {
+        (0..self.count)
+            .map(|offset| Self {
+                host_port: self.host_port + offset,
+                guest_port: self.guest_port + offset,
+                count: 1,
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// All forwarded ports of an instance have to fit into the -netdev
+    /// argument of QEMU
+    pub fn check_count(instance: &str, rules: &[PortForward]) -> crate::error::Result<()> {
+        let count: u32 = rules.iter().map(|rule| u32::from(rule.count)).sum();
+        if count > u32::from(MAX_FORWARDED_PORTS) {
+            return Err(crate::error::Error::TooManyForwardedPorts(
+                instance.to_string(),
+                MAX_FORWARDED_PORTS,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Picks a free host port for an automatic rule on its own address and
+    /// protocol. Rules with a fixed port keep it.
+    pub fn allocate_host_port(&mut self, system: &dyn System) -> crate::error::Result<()> {
+        if self.auto {
+            self.host_port = self.bind_host_port(system, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the host port of the rule is set and nothing else holds it
+    pub fn is_host_port_free(&self, system: &dyn System) -> bool {
+        self.host_port != 0 && self.bind_host_port(system, self.host_port).is_ok()
+    }
+
+    fn bind_host_port(&self, system: &dyn System, port: u16) -> crate::error::Result<u16> {
+        let addr = SocketAddr::new(self.host_ip, port);
+        match self.protocol {
+            Protocol::Tcp => system.bind_tcp_address(addr),
+            Protocol::Udp => system.bind_udp_address(addr),
+        }
+    }
+
+    /// Whether the rule is the one this rule names for removal. An automatic
+    /// rule is named by its guest side, since its host port may change.
+    pub fn matches(&self, rule: &PortForward) -> bool {
+        self.host_ip == rule.host_ip
+            && self.guest_port == rule.guest_port
+            && self.count == rule.count
+            && self.protocol == rule.protocol
+            && if self.auto && self.host_port == 0 {
+                rule.auto
+            } else {
+                self.host_port == rule.host_port
+            }
+    }
+
     fn from_value(
         protocol: Option<&str>,
         host_ip: Option<&str>,
-        host_port: &str,
-        guest_port: &str,
+        host_ports: &str,
+        guest_ports: &str,
     ) -> Result<Self, ()> {
-        Ok(Self::new(
-            if let Some(ip) = host_ip {
-                ip.parse::<IpAddr>().map_err(|_| ())?
-            } else {
-                IpAddr::V4(Ipv4Addr::LOCALHOST)
-            },
-            host_port.parse::<u16>().map_err(|_| ())?,
-            guest_port.parse::<u16>().map_err(|_| ())?,
-            if let Some(protocol) = protocol {
-                protocol.parse().map_err(|_| ())?
-            } else {
-                Protocol::Tcp
-            },
-        ))
+        let host_ip = if let Some(ip) = host_ip {
+            ip.parse::<IpAddr>().map_err(|_| ())?
+        } else {
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        };
+        let (guest_port, count) = parse_ports(guest_ports)?;
+        let protocol = if let Some(protocol) = protocol {
+            protocol.parse().map_err(|_| ())?
+        } else {
+            Protocol::Tcp
+        };
+        match host_ports {
+            // A free range cannot be claimed at once, so only single ports
+            // are picked
+            "" | "0" if count == 1 => Ok(Self::new_auto(host_ip, guest_port, protocol)),
+            "" | "0" => Err(()),
+            _ => {
+                let (host_port, host_count) = parse_ports(host_ports)?;
+                if host_count != count || host_port == 0 {
+                    return Err(());
+                }
+                Ok(Self {
+                    count,
+                    ..Self::new(host_ip, host_port, guest_port, protocol)
+                })
+            }
+        }
+    }
+
+    fn from_captures(regex: &Regex, value: &str) -> Result<Self, ()> {
+        let caps = regex.captures(value).ok_or(())?;
+        let get = |name| caps.name(name).map(|m| m.as_str());
+        Self::from_value(
+            get("protocol"),
+            get("ip"),
+            get("host").ok_or(())?,
+            get("guest").ok_or(())?,
+        )
     }
 
+    /// Parses the QEMU syntax, in which cubic stores rules, with port ranges
+    /// in place of single ports (e.g. tcp::30000-30100-:30000-30100)
     pub fn from_qemu(value: &str) -> Result<Self, String> {
-        let caps: Vec<_> = QEMU_PORT_REGEX
-            .captures(value)
-            .ok_or_else(|| QEMU_FORMAT_ERROR.to_string())?
-            .iter()
-            .collect();
-
-        if let &[_, protocol, host_ip, Some(host_port), Some(guest_port)] = caps.as_slice() {
-            Self::from_value(
-                protocol.map(|p| p.as_str()),
-                host_ip.map(|ip| ip.as_str()),
-                host_port.as_str(),
-                guest_port.as_str(),
-            )
-            .map_err(|_| QEMU_FORMAT_ERROR.to_string())
-        } else {
-            Err(QEMU_FORMAT_ERROR.to_string())
-        }
+        Self::from_captures(&QEMU_PORT_REGEX, value).map_err(|_| QEMU_FORMAT_ERROR.to_string())
     }
 
+    /// Rule for QEMU, which knows single ports only. Ranges are expanded
+    /// first.
     pub fn to_qemu(&self) -> String {
         format!(
             "{}:{}:{}-:{}",
             self.protocol, self.host_ip, self.host_port, self.guest_port
         )
     }
+
+    // Like `to_qemu`, but keeps a range in one rule
+    fn to_config(&self) -> String {
+        format!(
+            "{}:{}:{}-:{}",
+            self.protocol,
+            self.host_ip,
+            self.format_host_ports(),
+            self.format_guest_ports()
+        )
+    }
+}
+
+// First port and number of ports of a port or a range
+fn parse_ports(value: &str) -> Result<(u16, u16), ()> {
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (start, end),
+        None => (value, value),
+    };
+    let start = start.parse::<u16>().map_err(|_| ())?;
+    let end = end.parse::<u16>().map_err(|_| ())?;
+    if end < start || end - start >= MAX_FORWARDED_PORTS {
+        return Err(());
+    }
+    Ok((start, end - start + 1))
+}
+
+fn format_ports(start: u16, count: u16) -> String {
+    if count > 1 {
+        format!("{start}-{}", start + (count - 1))
+    } else {
+        start.to_string()
+    }
 }
 
 impl Display for PortForward {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(
-            f,
-            "{}:{}:{}/{}",
-            self.host_ip, self.host_port, self.guest_port, self.protocol,
-        )
+        if self.auto && self.host_port == 0 {
+            write!(f, "{}::{}/{}", self.host_ip, self.guest_port, self.protocol)
+        } else {
+            write!(
+                f,
+                "{}:{}:{}/{}",
+                self.host_ip,
+                self.format_host_ports(),
+                self.format_guest_ports(),
+                self.protocol,
+            )
+        }
     }
 }
 
@@ -143,32 +299,7 @@ impl FromStr for PortForward {
     type Err = String;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
-        let caps: Vec<_> = PORT_REGEX
-            .captures(value)
-            .ok_or_else(|| FORMAT_ERROR.to_string())?
-            .iter()
-            .collect();
-
-        if let &[
-            _,
-            _,
-            host_ip,
-            Some(host_port),
-            Some(guest_port),
-            _,
-            protocol,
-        ] = caps.as_slice()
-        {
-            Self::from_value(
-                protocol.map(|p| p.as_str()),
-                host_ip.map(|ip| ip.as_str()),
-                host_port.as_str(),
-                guest_port.as_str(),
-            )
-            .map_err(|_| FORMAT_ERROR.to_string())
-        } else {
-            Err(FORMAT_ERROR.to_string())
-        }
+        Self::from_captures(&PORT_REGEX, value).map_err(|_| FORMAT_ERROR.to_string())
     }
 }
 
@@ -177,7 +308,11 @@ impl Serialize for PortForward {
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_qemu())
+        if self.auto {
+            serializer.serialize_str(&format!("{}{AUTO_SUFFIX}", self.to_config()))
+        } else {
+            serializer.serialize_str(&self.to_config())
+        }
     }
 }
 
@@ -187,13 +322,18 @@ impl<'de> Deserialize<'de> for PortForward {
         D: Deserializer<'de>,
     {
         let value = String::deserialize(deserializer)?;
-        Self::from_qemu(&value).map_err(serde::de::Error::custom)
+        match value.strip_suffix(AUTO_SUFFIX) {
+            Some(rule) => Self::from_qemu(rule).map(|rule| Self { auto: true, ..rule }),
+            None => Self::from_qemu(&value),
+        }
+        .map_err(serde::de::Error::custom)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::platform::SystemMock;
 
     #[test]
     fn test_reject_unparsable_forward() {
@@ -247,6 +387,29 @@ mod tests {
         assert_eq!(deserialized, forward);
     }
 
+    #[test]
+    fn test_serde_round_trip_keeps_auto() {
+        let mut forward: PortForward = ":80".parse().unwrap();
+        forward.host_port = 41000;
+
+        let serialized = serde_json::to_string(&forward).unwrap();
+        assert_eq!(serialized, "\"tcp:127.0.0.1:41000-:80,auto\"");
+
+        let deserialized: PortForward = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, forward);
+    }
+
+    #[test]
+    fn test_serde_round_trip_keeps_range() {
+        let forward: PortForward = "30000-30100:30000-30100".parse().unwrap();
+
+        let serialized = serde_json::to_string(&forward).unwrap();
+        assert_eq!(serialized, "\"tcp:127.0.0.1:30000-30100-:30000-30100\"");
+
+        let deserialized: PortForward = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, forward);
+    }
+
     #[test]
     fn test_deserialize_rejects_invalid_forward() {
         assert!(serde_json::from_str::<PortForward>("\"garbage\"").is_err());
@@ -265,6 +428,122 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_range_parsing() {
+        let forward: PortForward = "30000-30100:40000-40100/udp".parse().unwrap();
+
+        assert_eq!(forward.get_host_port(), 30000);
+        assert_eq!(forward.get_guest_port(), 40000);
+        assert_eq!(forward.format_host_ports(), "30000-30100");
+        assert_eq!(forward.get_protocol(), Protocol::Udp);
+        assert_eq!(forward.to_string(), "127.0.0.1:30000-30100:40000-40100/udp");
+    }
+
+    #[test]
+    fn test_reject_invalid_ranges() {
+        assert!("30000-30100:30000-30050".parse::<PortForward>().is_err());
+        assert!("30100-30000:30100-30000".parse::<PortForward>().is_err());
+        assert!("8000-8010:80".parse::<PortForward>().is_err());
+        assert!(":30000-30100".parse::<PortForward>().is_err());
+        assert!("0-10:0-10".parse::<PortForward>().is_err());
+        assert!("65530-65540:65530-65540".parse::<PortForward>().is_err());
+    }
+
+    #[test]
+    fn test_reject_ranges_beyond_the_port_limit() {
+        assert!("30000-31023:30000-31023".parse::<PortForward>().is_ok());
+        assert!("30000-31024:30000-31024".parse::<PortForward>().is_err());
+        assert!("1-65535:1-65535".parse::<PortForward>().is_err());
+    }
+
+    #[test]
+    fn test_check_count_sums_the_ports_of_all_rules() {
+        let rules = [
+            "30000-31022:30000-31022".parse::<PortForward>().unwrap(),
+            "8000:80".parse::<PortForward>().unwrap(),
+        ];
+        assert!(PortForward::check_count("test", &rules).is_ok());
+
+        let rules = [rules[0].clone(), rules[1].clone(), ":443".parse().unwrap()];
+        assert!(matches!(
+            PortForward::check_count("test", &rules),
+            Err(crate::error::Error::TooManyForwardedPorts(name, MAX_FORWARDED_PORTS)) if name == "test"
+        ));
+    }
+
+    #[test]
+    fn test_expand_range() {
+        let rules = "127.0.0.1:8000-8002:80-82"
+            .parse::<PortForward>()
+            .unwrap()
+            .expand()
+            .iter()
+            .map(|rule| rule.to_qemu())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rules,
+            [
+                "tcp:127.0.0.1:8000-:80",
+                "tcp:127.0.0.1:8001-:81",
+                "tcp:127.0.0.1:8002-:82"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_parsing() {
+        let auto = PortForward::new_auto(IpAddr::V4(Ipv4Addr::LOCALHOST), 80, Protocol::Tcp);
+        assert_eq!(":80".parse(), Ok(auto.clone()));
+        assert_eq!("0:80".parse(), Ok(auto.clone()));
+        assert_eq!("127.0.0.1::80".parse(), Ok(auto));
+        assert_eq!(
+            "0.0.0.0:0:53/udp".parse(),
+            Ok(PortForward::new_auto(
+                IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+                53,
+                Protocol::Udp
+            ))
+        );
+    }
+
+    #[test]
+    fn test_allocate_host_port_only_for_auto() {
+        let system = SystemMock::new();
+        let mut auto: PortForward = ":80".parse().unwrap();
+        let mut fixed: PortForward = "8000:80".parse().unwrap();
+
+        auto.allocate_host_port(&system).unwrap();
+        fixed.allocate_host_port(&system).unwrap();
+
+        assert_ne!(auto.get_host_port(), 0);
+        assert_eq!(fixed.get_host_port(), 8000);
+    }
+
+    #[test]
+    fn test_auto_rule_matches_by_guest_port() {
+        let mut rule: PortForward = ":80".parse().unwrap();
+        rule.host_port = 41000;
+
+        assert!(":80".parse::<PortForward>().unwrap().matches(&rule));
+        assert!("41000:80".parse::<PortForward>().unwrap().matches(&rule));
+        assert!(!":81".parse::<PortForward>().unwrap().matches(&rule));
+        assert!(
+            !":80"
+                .parse::<PortForward>()
+                .unwrap()
+                .matches(&"8000:80".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_auto_to_string() {
+        assert_eq!(
+            ":80".parse::<PortForward>().unwrap().to_string(),
+            "127.0.0.1::80/tcp"
+        );
+    }
+
     #[test]
     fn test_localhost_parsing() {
         assert_eq!(
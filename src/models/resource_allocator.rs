@@ -77,6 +77,16 @@ impl ResourceAllocator {
         (budget >= 512 * MIB).then(|| Self::resources_for_level(budget / GIB))
     }
 
+    /// Return the vCPU count and memory a machine may grow to while running,
+    /// which is the whole host. Memory is cut down to whole MiB, as QEMU wants
+    /// an aligned ceiling.
+    pub fn get_hotplug_limits(&self) -> (u16, DataSize) {
+        (
+            self.host_threads,
+            DataSize::new(self.host_mem_bytes / MIB * MIB),
+        )
+    }
+
     pub fn is_disk_space_low(system: &dyn System, env: &Environment) -> bool {
         system
             .get_available_space(Path::new(&env.get_instance_dir()))
@@ -123,6 +133,16 @@ mod tests {
         assert_eq!(mem.get_bytes(), 3 * GIB);
     }
 
+    #[test]
+    fn test_hotplug_limits_cover_the_whole_host_in_whole_mib() {
+        let allocator = ResourceAllocator::new(16 * GIB + 123, 12);
+
+        let (cpus, mem) = allocator.get_hotplug_limits();
+
+        assert_eq!(cpus, 12);
+        assert_eq!(mem.get_bytes(), 16 * GIB);
+    }
+
     #[test]
     fn test_level_8() {
         assert_resources(36, 36, 16, 8 * GIB);
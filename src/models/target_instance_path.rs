@@ -90,8 +90,10 @@ mod tests {
 
     #[test]
     fn test_to_pathbuf_tilde_with_user_and_instance() {
-        let mut instance = Instance::default();
-        instance.user = UserName::from_str("root").unwrap();
+        let instance = Instance {
+            user: UserName::from_str("root").unwrap(),
+            ..Instance::default()
+        };
         assert_eq!(
             TargetInstancePath {
                 user: Some("tux".to_string()),
@@ -107,8 +109,10 @@ mod tests {
 
     #[test]
     fn test_to_pathbuf_tilde_with_instance_without_user() {
-        let mut instance = Instance::default();
-        instance.user = UserName::from_str("root").unwrap();
+        let instance = Instance {
+            user: UserName::from_str("root").unwrap(),
+            ..Instance::default()
+        };
         assert_eq!(
             TargetInstancePath {
                 user: None,
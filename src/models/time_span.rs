@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TimeSpan {
+    secs: u64,
+}
+
+impl TimeSpan {
+    pub fn from_secs(secs: u64) -> Self {
+        Self { secs }
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        Duration::from_secs(self.secs)
+    }
+}
+
+impl FromStr for TimeSpan {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = format!(
+            "Cannot parse time span '{value}'. The input should be a number optionally followed by a letter (s, m or h) for seconds, minutes or hours. Example: 2m for two minutes."
+        );
+
+        // A bare number counts as seconds
+        let (number, factor) = match value.chars().next_back() {
+            Some('s') => (&value[..value.len() - 1], 1),
+            Some('m') => (&value[..value.len() - 1], 60),
+            Some('h') => (&value[..value.len() - 1], 3600),
+            _ => (value, 1),
+        };
+
+        number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(factor))
+            .map(|secs| Self { secs })
+            .ok_or(error)
+    }
+}
+
+impl fmt::Display for TimeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}s", self.secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bare_number() {
+        assert_eq!(
+            TimeSpan::from_str("30").unwrap().get_duration(),
+            Duration::from_secs(30)
+        )
+    }
+
+    #[test]
+    fn test_from_seconds() {
+        assert_eq!(
+            TimeSpan::from_str("45s").unwrap().get_duration(),
+            Duration::from_secs(45)
+        )
+    }
+
+    #[test]
+    fn test_from_minutes() {
+        assert_eq!(
+            TimeSpan::from_str("2m").unwrap().get_duration(),
+            Duration::from_secs(120)
+        )
+    }
+
+    #[test]
+    fn test_from_hours() {
+        assert_eq!(
+            TimeSpan::from_str("1h").unwrap().get_duration(),
+            Duration::from_secs(3600)
+        )
+    }
+
+    #[test]
+    fn test_from_unknown_suffix() {
+        assert!(TimeSpan::from_str("10d").is_err())
+    }
+
+    #[test]
+    fn test_from_only_suffix() {
+        assert!(TimeSpan::from_str("s").is_err())
+    }
+
+    #[test]
+    fn test_from_overflow() {
+        assert!(TimeSpan::from_str("99999999999999999999h").is_err())
+    }
+
+    #[test]
+    fn test_from_empty() {
+        assert!(TimeSpan::from_str("").is_err())
+    }
+
+    #[test]
+    fn test_display_in_seconds() {
+        assert_eq!(TimeSpan::from_secs(90).to_string(), "90s")
+    }
+}
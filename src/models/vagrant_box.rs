@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// The Vagrant box an instance was created from
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VagrantBox {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub provider: String,
+    /// The guest still trusts the Vagrant insecure key instead of the cubic
+    /// key, until the first login swaps them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub insecure_key: bool,
+}
+
+impl VagrantBox {
+    /// Names the box after its source. A Vagrant Cloud download URL such as
+    /// `https://vagrantcloud.com/<org>/boxes/<box>/versions/<version>/...`
+    /// gives the full name and the version, anything else its file stem.
+    pub fn from_source(source: &str, provider: &str) -> Self {
+        let parts: Vec<&str> = source.split('/').collect();
+        let cloud_name = parts
+            .iter()
+            .position(|part| *part == "boxes")
+            .and_then(|pos| {
+                let org = parts.get(pos.checked_sub(1)?)?;
+                let name = parts.get(pos + 1)?;
+                let version = (parts.get(pos + 2) == Some(&"versions"))
+                    .then(|| parts.get(pos + 3).map(|version| version.to_string()))
+                    .flatten();
+                Some((format!("{org}/{name}"), version))
+            });
+
+        let (name, version) = cloud_name.unwrap_or_else(|| {
+            let name = Path::new(source)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| source.to_string());
+            (name, None)
+        });
+
+        Self {
+            name,
+            version,
+            provider: provider.to_string(),
+            insecure_key: true,
+        }
+    }
+}
+
+impl fmt::Display for VagrantBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, " {version}")?;
+        }
+        write!(f, " ({})", self.provider)
+    }
+}
+
+/// The `metadata.json` every box carries
+#[derive(Debug, Deserialize)]
+pub struct VagrantBoxMetadata {
+    pub provider: String,
+    /// Format of the disk, only set by libvirt boxes
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub architecture: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_cloud_url() {
+        let vagrant_box = VagrantBox::from_source(
+            "https://vagrantcloud.com/generic/boxes/debian12/versions/4.3.12/providers/libvirt/amd64/vagrant.box",
+            "libvirt",
+        );
+        assert_eq!(vagrant_box.name, "generic/debian12");
+        assert_eq!(vagrant_box.version.as_deref(), Some("4.3.12"));
+        assert_eq!(vagrant_box.to_string(), "generic/debian12 4.3.12 (libvirt)");
+    }
+
+    #[test]
+    fn test_from_file() {
+        let vagrant_box = VagrantBox::from_source("/home/user/legacy-dev.box", "virtualbox");
+        assert_eq!(vagrant_box.name, "legacy-dev");
+        assert_eq!(vagrant_box.version, None);
+        assert!(vagrant_box.insecure_key);
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata: VagrantBoxMetadata = serde_json::from_str(
+            r#"{"provider": "libvirt", "format": "qcow2", "virtual_size": 128}"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.provider, "libvirt");
+        assert_eq!(metadata.format.as_deref(), Some("qcow2"));
+    }
+}
@@ -0,0 +1,15 @@
+mod ext4_image;
+mod image_layout;
+mod image_reference;
+mod image_source;
+mod layer_flattener;
+mod manifest;
+mod registry_client;
+
+pub use ext4_image::{Ext4Image, ROOTFS_LABEL};
+pub use image_layout::ImageLayout;
+pub use image_reference::ImageReference;
+pub use image_source::{ImageSource, get_blob_path};
+pub use layer_flattener::{LayerFlattener, Rootfs};
+pub use manifest::{MANIFEST_MEDIA_TYPES, Manifest};
+pub use registry_client::RegistryClient;
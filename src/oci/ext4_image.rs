@@ -0,0 +1,151 @@
+use crate::error::{Error, Result};
+use crate::platform::System;
+use crate::util::SystemCommand;
+use std::path::{Path, PathBuf};
+
+/// Label of the root filesystem, which the kernel command line mounts
+pub const ROOTFS_LABEL: &str = "cubic-root";
+
+/// mke2fs lives in sbin, which is not on the PATH of every user
+const SBIN_DIRS: &[&str] = &[
+    "/usr/sbin",
+    "/sbin",
+    "/opt/homebrew/opt/e2fsprogs/sbin", // Homebrew (Apple Silicon)
+    "/usr/local/opt/e2fsprogs/sbin",    // Homebrew (Intel macOS)
+];
+
+/// First e2fsprogs release whose mke2fs takes a tar archive for -d
+const MIN_MKE2FS_VERSION: [u32; 3] = [1, 47, 1];
+
+/// Raw disk image holding a single ext4 filesystem without partitions
+pub struct Ext4Image<'a> {
+    system: &'a dyn System,
+}
+
+impl<'a> Ext4Image<'a> {
+    pub fn new(system: &'a dyn System) -> Self {
+        Self { system }
+    }
+
+    /// Creates the image with the content of a tar archive. mke2fs reads the
+    /// archive itself, so owners and device files survive without root.
+    pub fn create(&self, tar: &Path, image: &Path, size: u64) -> Result<()> {
+        self.check_version()?;
+
+        let mut command = self.build_command();
+        command
+            .arg("-q")
+            .arg("-F")
+            .arg("-t")
+            .arg("ext4")
+            .arg("-L")
+            .arg(ROOTFS_LABEL)
+            .arg("-d")
+            .arg(tar)
+            .arg(image)
+            .arg(format!("{}k", size / 1024));
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    // Older releases take a directory only and fail on the archive with an
+    // error that names neither the archive nor the version
+    fn check_version(&self) -> Result<()> {
+        let mut command = self.build_command();
+        command.arg("-V");
+        let output = self
+            .system
+            .run_command_stderr(&command)
+            .map_err(Self::map_error)?;
+        let output = String::from_utf8_lossy(&output);
+        // e.g. "mke2fs 1.47.1 (20-May-2024)"
+        let version = output
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or("an unknown version");
+        let parts = version
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<std::result::Result<Vec<_>, _>>();
+        match parts {
+            Ok(parts) if parts.as_slice() >= MIN_MKE2FS_VERSION.as_slice() => Ok(()),
+            _ => Err(Error::Mke2fsTooOld(version.to_string())),
+        }
+    }
+
+    fn build_command(&self) -> SystemCommand {
+        let mut dirs: Vec<PathBuf> = self
+            .system
+            .read_env_var("PATH")
+            .map(|path| std::env::split_paths(&path).collect())
+            .unwrap_or_default();
+        dirs.extend(SBIN_DIRS.iter().map(PathBuf::from));
+
+        let mut command = SystemCommand::new("mke2fs");
+        command.set_env("PATH", std::env::join_paths(dirs).unwrap_or_default());
+        command
+    }
+
+    fn map_error(error: Error) -> Error {
+        match error {
+            Error::SystemCommandNotFound(_) => Error::Mke2fsNotFound,
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::SystemMock;
+
+    #[test]
+    fn test_create_passes_the_archive_to_mke2fs() {
+        let system = SystemMock::new()
+            .add_command_output("mke2fs -V", b"mke2fs 1.47.1 (20-May-2024)\n")
+            .add_command_output(
+                "mke2fs -q -F -t ext4 -L cubic-root -d /tmp/rootfs.tar /tmp/rootfs.raw 1048576k",
+                b"",
+            );
+
+        Ext4Image::new(&system)
+            .create(
+                Path::new("/tmp/rootfs.tar"),
+                Path::new("/tmp/rootfs.raw"),
+                1024 * 1024 * 1024,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_create_reports_missing_mke2fs() {
+        let system = SystemMock::new();
+
+        let result = Ext4Image::new(&system).create(
+            Path::new("/tmp/rootfs.tar"),
+            Path::new("/tmp/rootfs.raw"),
+            1024,
+        );
+
+        assert!(matches!(result, Err(Error::Mke2fsNotFound)));
+    }
+
+    #[test]
+    fn test_create_rejects_mke2fs_without_tar_support() {
+        let system = SystemMock::new().add_command_output(
+            "mke2fs -V",
+            b"mke2fs 1.47.0 (5-Feb-2023)\n\tUsing EXT2FS Library version 1.47.0\n",
+        );
+
+        let result = Ext4Image::new(&system).create(
+            Path::new("/tmp/rootfs.tar"),
+            Path::new("/tmp/rootfs.raw"),
+            1024,
+        );
+
+        assert!(matches!(result, Err(Error::Mke2fsTooOld(version)) if version == "1.47.0"));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::error::{Error, Result};
+use crate::oci::{ImageSource, get_blob_path};
+use crate::platform::System;
+use crate::view::TransferView;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// An image in an OCI layout directory, as written by `skopeo copy` or
+/// `docker buildx build --output type=oci`
+pub struct ImageLayout {
+    dir: PathBuf,
+}
+
+impl ImageLayout {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn invalid(&self, reason: &str) -> Error {
+        Error::InvalidOciImage(self.get_name(), reason.to_string())
+    }
+
+    fn get_blob(&self, system: &dyn System, digest: &str) -> Result<PathBuf> {
+        get_blob_path(&self.dir, digest)
+            .filter(|path| system.exists_path(path))
+            .ok_or_else(|| self.invalid(&format!("blob {digest} is missing")))
+    }
+}
+
+impl ImageSource for ImageLayout {
+    fn get_name(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    fn get_root_manifest(&mut self, system: &dyn System) -> Result<Vec<u8>> {
+        if !system.exists_path(&self.dir.join("oci-layout")) {
+            return Err(self.invalid("not an OCI layout, oci-layout is missing"));
+        }
+        system
+            .read_file_to_string(&self.dir.join("index.json"))
+            .map(String::into_bytes)
+            .map_err(|_| self.invalid("index.json is missing"))
+    }
+
+    fn get_manifest(&mut self, system: &dyn System, digest: &str) -> Result<Vec<u8>> {
+        let path = self.get_blob(system, digest)?;
+        system.read_file_to_string(&path).map(String::into_bytes)
+    }
+
+    fn fetch_blob(
+        &mut self,
+        system: &dyn System,
+        digest: &str,
+        _size: u64,
+        _view: Arc<Mutex<TransferView>>,
+    ) -> Result<PathBuf> {
+        self.get_blob(system, digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::SystemMock;
+
+    #[test]
+    fn test_read_index_of_layout() {
+        let system = SystemMock::new()
+            .add_file("/layout/oci-layout", br#"{"imageLayoutVersion": "1.0.0"}"#)
+            .add_file("/layout/index.json", br#"{"manifests": []}"#);
+        let mut layout = ImageLayout::new(Path::new("/layout"));
+
+        assert_eq!(
+            layout.get_root_manifest(&system).unwrap(),
+            br#"{"manifests": []}"#
+        );
+    }
+
+    #[test]
+    fn test_reject_directory_without_layout() {
+        let system = SystemMock::new().add_file("/layout/index.json", b"{}");
+        let mut layout = ImageLayout::new(Path::new("/layout"));
+
+        assert!(matches!(
+            layout.get_root_manifest(&system),
+            Err(Error::InvalidOciImage(..))
+        ));
+    }
+}
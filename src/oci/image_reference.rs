@@ -0,0 +1,167 @@
+use std::fmt;
+use std::str::FromStr;
+
+const DOCKER_HUB: &str = "docker.io";
+const DOCKER_HUB_API: &str = "registry-1.docker.io";
+const DEFAULT_TAG: &str = "latest";
+
+/// Reference to an image in a registry, such as `docker.io/library/debian:bookworm`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageReference {
+    pub registry: String,
+    pub repository: String,
+    /// A tag or a `sha256:` digest
+    pub reference: String,
+}
+
+impl ImageReference {
+    /// Docker Hub answers the API on a host of its own
+    pub fn get_api_host(&self) -> &str {
+        if self.registry == DOCKER_HUB {
+            DOCKER_HUB_API
+        } else {
+            &self.registry
+        }
+    }
+
+    /// A registry on the host itself usually runs without TLS
+    pub fn get_base_url(&self) -> String {
+        let host = self.get_api_host();
+        let name = host
+            .rsplit_once(':')
+            .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+            .map_or(host, |(name, _)| name);
+        let scheme = match name {
+            "localhost" | "127.0.0.1" | "[::1]" => "http",
+            _ => "https",
+        };
+        format!("{scheme}://{host}/v2/{}", self.repository)
+    }
+}
+
+impl FromStr for ImageReference {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("'{value}' is not an image reference (e.g. docker.io/library/debian:bookworm)")
+        };
+
+        let (name, digest) = match value.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (value, None),
+        };
+
+        // Only the last component may carry a tag, a colon before is a port
+        let (name, tag) = match name.rsplit_once(':') {
+            Some((repo, tag)) if !tag.contains('/') => (repo, Some(tag)),
+            _ => (name, None),
+        };
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), path.to_string())
+            }
+            _ => (DOCKER_HUB.to_string(), name.to_string()),
+        };
+        let repository = if registry == DOCKER_HUB && !repository.contains('/') {
+            format!("library/{repository}")
+        } else {
+            repository
+        };
+
+        let valid_repository = !repository.is_empty()
+            && repository.split('/').all(|part| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c))
+            });
+        if !valid_repository || tag.is_some_and(str::is_empty) {
+            return Err(invalid());
+        }
+
+        let reference = match (digest, tag) {
+            (Some(digest), _) if digest.starts_with("sha256:") => digest.to_string(),
+            (Some(_), _) => return Err(invalid()),
+            (None, Some(tag)) => tag.to_string(),
+            (None, None) => DEFAULT_TAG.to_string(),
+        };
+
+        Ok(Self {
+            registry,
+            repository,
+            reference,
+        })
+    }
+}
+
+impl fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.reference.starts_with("sha256:") {
+            '@'
+        } else {
+            ':'
+        };
+        write!(
+            f,
+            "{}/{}{separator}{}",
+            self.registry, self.repository, self.reference
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_reference() {
+        let reference = ImageReference::from_str("docker.io/library/debian:bookworm").unwrap();
+        assert_eq!(reference.registry, "docker.io");
+        assert_eq!(reference.repository, "library/debian");
+        assert_eq!(reference.reference, "bookworm");
+        assert_eq!(
+            reference.get_base_url(),
+            "https://registry-1.docker.io/v2/library/debian"
+        );
+    }
+
+    #[test]
+    fn test_parse_short_reference() {
+        let reference = ImageReference::from_str("debian").unwrap();
+        assert_eq!(reference.to_string(), "docker.io/library/debian:latest");
+    }
+
+    #[test]
+    fn test_parse_local_registry_with_port() {
+        let reference = ImageReference::from_str("localhost:5000/tools/vm:1.0").unwrap();
+        assert_eq!(reference.registry, "localhost:5000");
+        assert_eq!(reference.repository, "tools/vm");
+        assert_eq!(reference.reference, "1.0");
+        assert_eq!(
+            reference.get_base_url(),
+            "http://localhost:5000/v2/tools/vm"
+        );
+    }
+
+    #[test]
+    fn test_parse_digest() {
+        let reference =
+            ImageReference::from_str("quay.io/fedora/fedora-bootc@sha256:0123abcd").unwrap();
+        assert_eq!(reference.reference, "sha256:0123abcd");
+        assert_eq!(
+            reference.to_string(),
+            "quay.io/fedora/fedora-bootc@sha256:0123abcd"
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_reference() {
+        assert!(ImageReference::from_str("Debian:bookworm").is_err());
+        assert!(ImageReference::from_str("debian:").is_err());
+        assert!(ImageReference::from_str("quay.io//debian").is_err());
+    }
+}
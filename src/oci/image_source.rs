@@ -0,0 +1,58 @@
+use crate::error::Result;
+use crate::platform::System;
+use crate::view::TransferView;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Where the manifests and blobs of an image come from
+pub trait ImageSource {
+    /// Name of the image in messages
+    fn get_name(&self) -> String;
+
+    /// The manifest the image is known by: the one of the tag, or the index
+    /// of a layout
+    fn get_root_manifest(&mut self, system: &dyn System) -> Result<Vec<u8>>;
+
+    fn get_manifest(&mut self, system: &dyn System, digest: &str) -> Result<Vec<u8>>;
+
+    /// Makes the blob available as a local file and returns its path
+    fn fetch_blob(
+        &mut self,
+        system: &dyn System,
+        digest: &str,
+        size: u64,
+        view: Arc<Mutex<TransferView>>,
+    ) -> Result<PathBuf>;
+}
+
+/// Path of a blob in a content addressed store, the layout every OCI layout
+/// uses as well. The digest must be a plain hex string, so a manifest cannot
+/// point outside of the store.
+pub fn get_blob_path(store: &Path, digest: &str) -> Option<PathBuf> {
+    let (alg, hex) = digest.split_once(':')?;
+    let valid = alg == "sha256"
+        && hex.len() == 64
+        && hex
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+    valid.then(|| store.join("blobs").join(alg).join(hex))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_blob_path() {
+        let digest = format!("sha256:{}", "ab".repeat(32));
+        assert_eq!(
+            get_blob_path(Path::new("/store"), &digest),
+            Some(PathBuf::from(format!(
+                "/store/blobs/sha256/{}",
+                "ab".repeat(32)
+            )))
+        );
+        assert_eq!(get_blob_path(Path::new("/store"), "sha256:../../etc"), None);
+        assert_eq!(get_blob_path(Path::new("/store"), "md5:abc"), None);
+    }
+}
@@ -0,0 +1,483 @@
+use flate2::read::GzDecoder;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Component, Path};
+use tar::{Archive, Builder, EntryType};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const WHITEOUT_PREFIX: &str = ".wh.";
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// Where an init system may sit, with or without a merged /usr
+const INIT_PATHS: &[&str] = &["sbin/init", "usr/sbin/init", "usr/lib/systemd/systemd"];
+const CLOUD_INIT_PATHS: &[&str] = &["usr/bin/cloud-init", "bin/cloud-init"];
+
+/// The merged tree of an image, as found by `LayerFlattener::scan`
+pub struct Rootfs {
+    /// Entries that make it into the tree, by layer and position
+    kept: HashSet<(usize, usize)>,
+    paths: HashMap<String, EntryType>,
+    pub kernel: Option<String>,
+    pub initrd: Option<String>,
+}
+
+impl Rootfs {
+    pub fn contains(&self, path: &str) -> bool {
+        self.paths.contains_key(path)
+    }
+
+    pub fn has_init(&self) -> bool {
+        INIT_PATHS.iter().any(|path| self.contains(path))
+    }
+
+    pub fn has_cloud_init(&self) -> bool {
+        CLOUD_INIT_PATHS.iter().any(|path| self.contains(path))
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.paths.get(path) == Some(&EntryType::Regular)
+    }
+
+    /// Picks the newest kernel of the tree and the initrd built for it.
+    /// Distributions keep it in /boot, image mode ones next to the modules.
+    fn find_kernel(&mut self) {
+        let mut kernels: Vec<(String, String)> = self
+            .paths
+            .keys()
+            .filter(|path| self.is_file(path))
+            .filter_map(|path| {
+                let version = match path.strip_prefix("boot/vmlinuz-") {
+                    Some(version) => version,
+                    None => path
+                        .strip_prefix("usr/lib/modules/")
+                        .or_else(|| path.strip_prefix("lib/modules/"))?
+                        .strip_suffix("/vmlinuz")?,
+                };
+                Some((version.to_string(), path.clone()))
+            })
+            .collect();
+        if kernels.is_empty() && self.is_file("boot/vmlinuz") {
+            kernels.push((String::new(), "boot/vmlinuz".to_string()));
+        }
+        kernels.sort_by(|(a, _), (b, _)| compare_versions(a, b));
+        let Some((version, kernel)) = kernels.pop() else {
+            return;
+        };
+
+        let mut candidates = Vec::new();
+        if let Some(dir) = kernel.strip_suffix("/vmlinuz").filter(|dir| *dir != "boot") {
+            candidates.push(format!("{dir}/initramfs.img"));
+        }
+        if version.is_empty() {
+            candidates.push("boot/initrd.img".to_string());
+            candidates.push("boot/initramfs.img".to_string());
+        } else {
+            candidates.push(format!("boot/initrd.img-{version}"));
+            candidates.push(format!("boot/initramfs-{version}.img"));
+            candidates.push(format!("boot/initrd-{version}"));
+        }
+
+        self.initrd = candidates.into_iter().find(|path| self.is_file(path));
+        self.kernel = Some(kernel);
+    }
+}
+
+/// Compares kernel versions piece by piece, numbers by value, so 6.10 comes
+/// after 6.9
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn pieces(version: &str) -> Vec<&str> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        let mut digits = None;
+        for (index, c) in version.char_indices() {
+            if digits.is_some_and(|digits| digits != c.is_ascii_digit()) {
+                pieces.push(&version[start..index]);
+                start = index;
+            }
+            digits = Some(c.is_ascii_digit());
+        }
+        pieces.push(&version[start..]);
+        pieces
+    }
+
+    for (a, b) in pieces(a).into_iter().zip(pieces(b)) {
+        let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// Turns a tar path into the form the merged tree uses, `usr/bin/env`.
+/// A path that climbs out of the tree is dropped.
+fn normalize(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Parents of a path, innermost first, ending with the root as ""
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.rmatch_indices('/')
+        .map(|(index, _)| &path[..index])
+        .chain([""])
+}
+
+fn decompress(reader: Box<dyn Read>) -> io::Result<Box<dyn Read>> {
+    let mut reader = BufReader::new(reader);
+    Ok(if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Box::new(GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Merges the layers of an image into a single tar, the way an overlay
+/// filesystem shows them: upper layers replace files of lower ones, and
+/// whiteout files remove them.
+pub struct LayerFlattener<'a> {
+    layer_count: usize,
+    open: &'a dyn Fn(usize) -> io::Result<Box<dyn Read>>,
+}
+
+impl<'a> LayerFlattener<'a> {
+    /// `open` hands out the layer of an index, the lowest first. Layers may be
+    /// gzip compressed.
+    pub fn new(layer_count: usize, open: &'a dyn Fn(usize) -> io::Result<Box<dyn Read>>) -> Self {
+        Self { layer_count, open }
+    }
+
+    /// Walks the layers from the top, so the first layer seen with a path is
+    /// the one that wins
+    pub fn scan(&self) -> io::Result<Rootfs> {
+        let mut rootfs = Rootfs {
+            kept: HashSet::new(),
+            paths: HashMap::new(),
+            kernel: None,
+            initrd: None,
+        };
+        let mut removed: HashSet<String> = HashSet::new();
+        let mut opaque: HashSet<String> = HashSet::new();
+
+        for layer in (0..self.layer_count).rev() {
+            // Whiteouts only hide what lies below their own layer
+            let mut layer_removed = Vec::new();
+            let mut layer_opaque = Vec::new();
+
+            let mut archive = Archive::new(decompress((self.open)(layer)?)?);
+            let mut entries = Vec::new();
+            for (position, entry) in archive.entries()?.enumerate() {
+                let entry = entry?;
+                if let Some(path) = normalize(&entry.path()?) {
+                    entries.push((position, path, entry.header().entry_type()));
+                }
+            }
+            // A layer may hold a path more than once, and as on extraction
+            // the last entry wins
+            let last: HashMap<&str, usize> = entries
+                .iter()
+                .map(|(position, path, _)| (path.as_str(), *position))
+                .collect();
+
+            for (position, path, entry_type) in &entries {
+                if last[path.as_str()] != *position {
+                    continue;
+                }
+                let (parent, name) = match path.rsplit_once('/') {
+                    Some((parent, name)) => (parent, name),
+                    None => ("", path.as_str()),
+                };
+
+                if name == OPAQUE_WHITEOUT {
+                    layer_opaque.push(parent.to_string());
+                    continue;
+                }
+                if let Some(hidden) = name.strip_prefix(WHITEOUT_PREFIX) {
+                    layer_removed.push(match parent {
+                        "" => hidden.to_string(),
+                        parent => format!("{parent}/{hidden}"),
+                    });
+                    continue;
+                }
+
+                let hidden = rootfs.paths.contains_key(path)
+                    || removed.contains(path)
+                    || ancestors(path).any(|dir| {
+                        removed.contains(dir)
+                            || opaque.contains(dir)
+                            // An upper layer put something else in place of
+                            // the directory
+                            || rootfs
+                                .paths
+                                .get(dir)
+                                .is_some_and(|entry_type| *entry_type != EntryType::Directory)
+                    });
+                if hidden {
+                    continue;
+                }
+
+                rootfs.kept.insert((layer, *position));
+                rootfs.paths.insert(path.clone(), *entry_type);
+            }
+
+            removed.extend(layer_removed);
+            opaque.extend(layer_opaque);
+        }
+
+        rootfs.find_kernel();
+        Ok(rootfs)
+    }
+
+    /// Writes the merged tree as a tar, lowest layer first, so every
+    /// directory comes before its content. The kernel and the initrd also go
+    /// to `extract`.
+    pub fn write<W: Write>(
+        &self,
+        rootfs: &Rootfs,
+        output: W,
+        extract: &mut dyn FnMut(&str, &[u8]) -> io::Result<()>,
+    ) -> io::Result<W> {
+        let mut builder = Builder::new(output);
+        for layer in 0..self.layer_count {
+            let mut archive = Archive::new(decompress((self.open)(layer)?)?);
+            for (position, entry) in archive.entries()?.enumerate() {
+                if !rootfs.kept.contains(&(layer, position)) {
+                    continue;
+                }
+                let mut entry = entry?;
+                let Some(path) = normalize(&entry.path()?) else {
+                    continue;
+                };
+                let mut header = entry.header().clone();
+
+                match header.entry_type() {
+                    EntryType::Symlink => {
+                        let target = entry.link_name()?.unwrap_or_default().into_owned();
+                        builder.append_link(&mut header, &path, target)?;
+                    }
+                    // Hard links name a path of the tree, in the same form
+                    EntryType::Link => {
+                        let target = entry
+                            .link_name()?
+                            .and_then(|target| normalize(&target))
+                            .unwrap_or_default();
+                        builder.append_link(&mut header, &path, target)?;
+                    }
+                    _ if Some(&path) == rootfs.kernel.as_ref()
+                        || Some(&path) == rootfs.initrd.as_ref() =>
+                    {
+                        let mut content = Vec::new();
+                        entry.read_to_end(&mut content)?;
+                        extract(&path, &content)?;
+                        builder.append_data(&mut header, &path, content.as_slice())?;
+                    }
+                    _ => builder.append_data(&mut header, &path, &mut entry)?,
+                }
+            }
+        }
+        builder.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tar::Header;
+
+    enum Node<'a> {
+        File(&'a str, &'a str),
+        Dir(&'a str),
+        Symlink(&'a str, &'a str),
+    }
+    use Node::*;
+
+    fn build_layer(nodes: &[Node]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for node in nodes {
+            let mut header = Header::new_gnu();
+            header.set_mode(0o755);
+            match node {
+                File(path, content) => {
+                    header.set_size(content.len() as u64);
+                    builder
+                        .append_data(&mut header, path, content.as_bytes())
+                        .unwrap();
+                }
+                Dir(path) => {
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_size(0);
+                    builder.append_data(&mut header, path, &[][..]).unwrap();
+                }
+                Symlink(path, target) => {
+                    header.set_entry_type(EntryType::Symlink);
+                    header.set_size(0);
+                    builder.append_link(&mut header, path, target).unwrap();
+                }
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn flatten(layers: Vec<Vec<u8>>) -> (Rootfs, Vec<(String, String)>, Vec<String>) {
+        let open = |index: usize| -> io::Result<Box<dyn Read>> {
+            Ok(Box::new(io::Cursor::new(layers[index].clone())))
+        };
+        let flattener = LayerFlattener::new(layers.len(), &open);
+        let rootfs = flattener.scan().unwrap();
+
+        let mut extracted = Vec::new();
+        let tar = flattener
+            .write(&rootfs, Vec::new(), &mut |path, _| {
+                extracted.push(path.to_string());
+                Ok(())
+            })
+            .unwrap();
+
+        let mut entries = Vec::new();
+        let mut archive = Archive::new(tar.as_slice());
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            entries.push((path, content));
+        }
+        (rootfs, entries, extracted)
+    }
+
+    #[test]
+    fn test_upper_layer_replaces_file() {
+        let (_, entries, _) = flatten(vec![
+            build_layer(&[Dir("etc/"), File("etc/os-release", "old")]),
+            build_layer(&[File("etc/os-release", "new")]),
+        ]);
+
+        assert_eq!(
+            entries,
+            vec![
+                ("etc".to_string(), String::new()),
+                ("etc/os-release".to_string(), "new".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_last_entry_of_a_layer_wins() {
+        let (_, entries, _) = flatten(vec![build_layer(&[
+            File("etc/hostname", "first"),
+            File("etc/hostname", "second"),
+        ])]);
+
+        assert_eq!(
+            entries,
+            vec![("etc/hostname".to_string(), "second".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_whiteout_removes_lower_file() {
+        let (rootfs, entries, _) = flatten(vec![
+            build_layer(&[File("./tmp/a", "a"), File("./tmp/b", "b")]),
+            build_layer(&[File("./tmp/.wh.a", "")]),
+        ]);
+
+        assert!(!rootfs.contains("tmp/a"));
+        assert_eq!(entries, vec![("tmp/b".to_string(), "b".to_string())]);
+    }
+
+    #[test]
+    fn test_opaque_whiteout_hides_lower_directory_content() {
+        let (_, entries, _) = flatten(vec![
+            build_layer(&[Dir("var/cache/"), File("var/cache/old", "old")]),
+            build_layer(&[
+                Dir("var/cache/"),
+                File("var/cache/.wh..wh..opq", ""),
+                File("var/cache/new", "new"),
+            ]),
+        ]);
+
+        let paths: Vec<&str> = entries.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["var/cache", "var/cache/new"]);
+    }
+
+    #[test]
+    fn test_symlink_replaces_directory() {
+        let (rootfs, entries, _) = flatten(vec![
+            build_layer(&[Dir("lib/"), File("lib/libc.so", "libc")]),
+            build_layer(&[Symlink("lib", "usr/lib")]),
+        ]);
+
+        assert!(!rootfs.contains("lib/libc.so"));
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn test_find_newest_kernel_in_boot() {
+        let (rootfs, _, extracted) = flatten(vec![build_layer(&[
+            File("boot/vmlinuz-6.9.0-amd64", "k1"),
+            File("boot/initrd.img-6.9.0-amd64", "i1"),
+            File("boot/vmlinuz-6.10.0-amd64", "k2"),
+            File("boot/initrd.img-6.10.0-amd64", "i2"),
+            Symlink("vmlinuz", "boot/vmlinuz-6.10.0-amd64"),
+            File("sbin/init", ""),
+        ])]);
+
+        assert_eq!(rootfs.kernel.as_deref(), Some("boot/vmlinuz-6.10.0-amd64"));
+        assert_eq!(
+            rootfs.initrd.as_deref(),
+            Some("boot/initrd.img-6.10.0-amd64")
+        );
+        assert_eq!(
+            extracted,
+            vec!["boot/vmlinuz-6.10.0-amd64", "boot/initrd.img-6.10.0-amd64"]
+        );
+        assert!(rootfs.has_init());
+        assert!(!rootfs.has_cloud_init());
+    }
+
+    #[test]
+    fn test_find_kernel_next_to_modules() {
+        let (rootfs, _, _) = flatten(vec![build_layer(&[
+            File("usr/lib/modules/6.11.4-301.fc41.x86_64/vmlinuz", "k"),
+            File("usr/lib/modules/6.11.4-301.fc41.x86_64/initramfs.img", "i"),
+        ])]);
+
+        assert_eq!(
+            rootfs.kernel.as_deref(),
+            Some("usr/lib/modules/6.11.4-301.fc41.x86_64/vmlinuz")
+        );
+        assert_eq!(
+            rootfs.initrd.as_deref(),
+            Some("usr/lib/modules/6.11.4-301.fc41.x86_64/initramfs.img")
+        );
+    }
+
+    #[test]
+    fn test_image_without_kernel() {
+        let (rootfs, _, extracted) = flatten(vec![build_layer(&[File("bin/sh", "")])]);
+
+        assert_eq!(rootfs.kernel, None);
+        assert!(extracted.is_empty());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("6.10.0", "6.9.0"), Ordering::Greater);
+        assert_eq!(
+            compare_versions("0-rescue-4f0a1b", "6.11.4-301.fc41.x86_64"),
+            Ordering::Less
+        );
+        assert_eq!(compare_versions("5.15", "5.15"), Ordering::Equal);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::models::Arch;
+use serde::Deserialize;
+
+/// Media types a registry is asked for, indexes first so it does not pick a
+/// single platform on its own
+pub const MANIFEST_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+];
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Platform {
+    pub architecture: String,
+    pub os: String,
+}
+
+/// Pointer to a blob by its digest
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    #[serde(default)]
+    pub media_type: String,
+    pub digest: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub platform: Option<Platform>,
+}
+
+impl Descriptor {
+    pub fn is_zstd(&self) -> bool {
+        self.media_type.ends_with("zstd")
+    }
+}
+
+/// An image index, which lists a manifest per platform, or an image manifest,
+/// which lists the layers. Docker's formats share the field names.
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub manifests: Vec<Descriptor>,
+    #[serde(default)]
+    pub layers: Vec<Descriptor>,
+}
+
+impl Manifest {
+    pub fn is_index(&self) -> bool {
+        !self.manifests.is_empty()
+    }
+
+    /// Picks the Linux manifest of the arch. A layout written for a single
+    /// platform may leave the platform out.
+    pub fn select_platform(&self, arch: Arch) -> Option<&Descriptor> {
+        let matching = self.manifests.iter().find(|manifest| {
+            manifest.platform.as_ref().is_some_and(|platform| {
+                platform.os == "linux" && platform.architecture == arch.as_vendor_str()
+            })
+        });
+        match self.manifests.as_slice() {
+            [single] if single.platform.is_none() => Some(single),
+            _ => matching,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_platform_of_index() {
+        let index: Manifest = serde_json::from_str(
+            r#"{
+                "schemaVersion": 2,
+                "mediaType": "application/vnd.oci.image.index.v1+json",
+                "manifests": [
+                    {"digest": "sha256:aaa", "size": 1, "platform": {"architecture": "arm64", "os": "linux"}},
+                    {"digest": "sha256:bbb", "size": 1, "platform": {"architecture": "amd64", "os": "linux"}},
+                    {"digest": "sha256:ccc", "size": 1, "platform": {"architecture": "unknown", "os": "unknown"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(index.is_index());
+        assert_eq!(
+            index.select_platform(Arch::AMD64).unwrap().digest,
+            "sha256:bbb"
+        );
+        assert_eq!(
+            index.select_platform(Arch::ARM64).unwrap().digest,
+            "sha256:aaa"
+        );
+    }
+
+    #[test]
+    fn test_parse_image_manifest() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "schemaVersion": 2,
+                "config": {"mediaType": "application/vnd.oci.image.config.v1+json", "digest": "sha256:cfg", "size": 2},
+                "layers": [
+                    {"mediaType": "application/vnd.oci.image.layer.v1.tar+gzip", "digest": "sha256:l1", "size": 10},
+                    {"mediaType": "application/vnd.oci.image.layer.v1.tar+zstd", "digest": "sha256:l2", "size": 20}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(!manifest.is_index());
+        assert_eq!(manifest.layers.len(), 2);
+        assert!(!manifest.layers[0].is_zstd());
+        assert!(manifest.layers[1].is_zstd());
+    }
+}
@@ -0,0 +1,387 @@
+use crate::error::{Error, FsOperation, Result};
+use crate::models::HashAlg;
+use crate::oci::{ImageReference, ImageSource, MANIFEST_MEDIA_TYPES, get_blob_path};
+use crate::platform::System;
+use crate::view::TransferView;
+use crate::web::Hasher;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{ACCEPT, WWW_AUTHENTICATE};
+use serde::Deserialize;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CONNECT_TIMEOUT_SEC: u64 = 30;
+const BUFFER_SIZE: usize = 1 << 16;
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    access_token: Option<String>,
+}
+
+/// Client of the OCI distribution API. Anonymous pulls work against Docker
+/// Hub and other registries that hand out bearer tokens, and against local
+/// registries that want no token at all.
+pub struct RegistryClient {
+    client: Client,
+    reference: ImageReference,
+    cache_dir: PathBuf,
+    token: Option<String>,
+}
+
+impl RegistryClient {
+    /// Blobs are kept in `cache_dir` by digest, so a layer shared by several
+    /// images is only downloaded once
+    pub fn new(reference: &ImageReference, cache_dir: &Path) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent("cubic")
+                .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SEC))
+                // Layers may take longer than any fixed timeout
+                .timeout(None)
+                .build()
+                .map_err(Error::from)?,
+            reference: reference.clone(),
+            cache_dir: cache_dir.to_path_buf(),
+            token: None,
+        })
+    }
+
+    fn invalid(&self, reason: &str) -> Error {
+        Error::InvalidOciImage(self.reference.to_string(), reason.to_string())
+    }
+
+    fn get(&mut self, path: &str, accept: &[&str]) -> Result<Response> {
+        let url = format!("{}/{path}", self.reference.get_base_url());
+        let send = |token: Option<&str>| {
+            let mut request = self.client.get(&url).header(ACCEPT, accept.join(", "));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            request.send().map_err(Error::from)
+        };
+
+        let mut response = send(self.token.as_deref())?;
+        if response.status() == StatusCode::UNAUTHORIZED && self.token.is_none() {
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            self.token = Some(self.request_token(&challenge)?);
+            response = send(self.token.as_deref())?;
+        }
+
+        if !response.status().is_success() {
+            return Err(self.invalid(&format!(
+                "the registry answered {} for {url}",
+                response.status()
+            )));
+        }
+        Ok(response)
+    }
+
+    /// Asks the realm of a `Bearer` challenge for an anonymous pull token
+    fn request_token(&self, challenge: &str) -> Result<String> {
+        let params = Self::parse_challenge(challenge)
+            .ok_or_else(|| self.invalid("the registry asks for an unsupported authentication"))?;
+        let realm = params
+            .iter()
+            .find(|(key, _)| key == "realm")
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| self.invalid("the registry names no token realm"))?;
+        let mut query: Vec<(String, String)> = params
+            .into_iter()
+            .filter(|(key, _)| key == "service" || key == "scope")
+            .collect();
+        if !query.iter().any(|(key, _)| key == "scope") {
+            query.push((
+                "scope".to_string(),
+                format!("repository:{}:pull", self.reference.repository),
+            ));
+        }
+
+        let content = self
+            .client
+            .get(&realm)
+            .query(&query)
+            .send()
+            .and_then(Response::error_for_status)
+            .and_then(Response::bytes)
+            .map_err(Error::from)?;
+        let response: TokenResponse = serde_json::from_slice(&content)
+            .map_err(|_| self.invalid("the registry handed out no token"))?;
+        response
+            .token
+            .or(response.access_token)
+            .ok_or_else(|| self.invalid("the registry handed out no token"))
+    }
+
+    /// Splits `Bearer realm="...",service="..."` into its parameters. Values
+    /// are quoted and may hold commas, as a scope with several actions does.
+    fn parse_challenge(challenge: &str) -> Option<Vec<(String, String)>> {
+        let (scheme, rest) = challenge.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return None;
+        }
+
+        let mut params = Vec::new();
+        let mut rest = rest.trim();
+        while let Some((key, value)) = rest.split_once('=') {
+            let key = key.trim().trim_start_matches(',').trim().to_lowercase();
+            let (value, tail) = match value.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"')?;
+                    (&quoted[..end], &quoted[end + 1..])
+                }
+                None => value.split_once(',').unwrap_or((value, "")),
+            };
+            params.push((key, value.to_string()));
+            rest = tail.trim();
+        }
+        Some(params)
+    }
+
+    fn download_blob(
+        &mut self,
+        system: &dyn System,
+        digest: &str,
+        path: &Path,
+        view: Arc<Mutex<TransferView>>,
+    ) -> Result<()> {
+        let mut response = self.get(&format!("blobs/{digest}"), &[])?;
+        let temp_file = path.with_extension("tmp");
+        let result = Self::copy_blob(&mut response, system.create_file(&temp_file)?, view)
+            .map_err(|error| Error::from_fs(FsOperation::WriteFile, path, error))
+            .and_then(|hash| {
+                if format!("sha256:{hash}") == digest {
+                    Ok(())
+                } else {
+                    Err(self.invalid(&format!("blob {digest} does not match its digest")))
+                }
+            });
+
+        match result {
+            Ok(()) => system.rename_file(&temp_file, path),
+            Err(error) => {
+                system.remove_file(&temp_file).ok();
+                Err(error)
+            }
+        }
+    }
+
+    /// Copies the blob to the file and returns its sha256 hash
+    fn copy_blob(
+        response: &mut Response,
+        mut file: Box<dyn Write>,
+        view: Arc<Mutex<TransferView>>,
+    ) -> io::Result<String> {
+        let size = response.content_length();
+        let mut hasher = Hasher::new(HashAlg::Sha256);
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let mut written = 0;
+        loop {
+            let count = response.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            hasher.update(&buffer[..count]);
+            file.write_all(&buffer[..count])?;
+            written += count as u64;
+            view.lock().unwrap().set_progress(written, size);
+        }
+        file.flush()?;
+        Ok(hasher.finalize())
+    }
+}
+
+impl ImageSource for RegistryClient {
+    fn get_name(&self) -> String {
+        self.reference.to_string()
+    }
+
+    fn get_root_manifest(&mut self, system: &dyn System) -> Result<Vec<u8>> {
+        let reference = self.reference.reference.clone();
+        self.get_manifest(system, &reference)
+    }
+
+    fn get_manifest(&mut self, _system: &dyn System, reference: &str) -> Result<Vec<u8>> {
+        self.get(&format!("manifests/{reference}"), MANIFEST_MEDIA_TYPES)?
+            .bytes()
+            .map(|bytes| bytes.to_vec())
+            .map_err(Error::from)
+    }
+
+    fn fetch_blob(
+        &mut self,
+        system: &dyn System,
+        digest: &str,
+        _size: u64,
+        view: Arc<Mutex<TransferView>>,
+    ) -> Result<PathBuf> {
+        let path = get_blob_path(&self.cache_dir, digest)
+            .ok_or_else(|| self.invalid(&format!("'{digest}' is not a sha256 digest")))?;
+        if !system.exists_path(&path) {
+            if let Some(dir) = path.parent() {
+                system.create_dir(dir)?;
+            }
+            self.download_blob(system, digest, &path, view)?;
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::SystemMock;
+    use crate::util::hex_encode;
+    use sha2::{Digest, Sha256};
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::thread;
+
+    /// Stand-in for a local registry that wants a token first, as Docker Hub
+    /// does. Answers `count` requests and returns the requests it saw.
+    fn serve(count: usize, blob: &'static [u8]) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let realm = format!("http://{host}/token");
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut lines = Vec::new();
+                let mut reader = BufReader::new(&stream);
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    lines.push(line.trim().to_string());
+                }
+                let path = lines[0].split(' ').nth(1).unwrap().to_string();
+                let authorized = lines
+                    .iter()
+                    .any(|line| line.to_lowercase() == "authorization: bearer secret");
+
+                let (status, headers, body): (&str, String, Vec<u8>) = if path.starts_with("/token")
+                {
+                    ("200 OK", String::new(), br#"{"token": "secret"}"#.to_vec())
+                } else if !authorized {
+                    (
+                        "401 Unauthorized",
+                        format!(
+                            "WWW-Authenticate: Bearer realm=\"{realm}\",service=\"test\",scope=\"repository:tools/vm:pull\"\r\n"
+                        ),
+                        Vec::new(),
+                    )
+                } else if path.starts_with("/v2/tools/vm/manifests/") {
+                    ("200 OK", String::new(), br#"{"layers": []}"#.to_vec())
+                } else if path.starts_with("/v2/tools/vm/blobs/") {
+                    ("200 OK", String::new(), blob.to_vec())
+                } else {
+                    ("404 Not Found", String::new(), Vec::new())
+                };
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+                requests.push(path);
+            }
+            requests
+        });
+        (host, handle)
+    }
+
+    fn digest_of(content: &[u8]) -> String {
+        format!("sha256:{}", hex_encode(&Sha256::digest(content)))
+    }
+
+    fn build_client(host: &str) -> RegistryClient {
+        let reference = ImageReference::from_str(&format!("{host}/tools/vm:1.0")).unwrap();
+        RegistryClient::new(&reference, Path::new("/cache/oci")).unwrap()
+    }
+
+    #[test]
+    fn test_get_manifest_with_token() {
+        let (host, server) = serve(3, b"");
+        let system = SystemMock::new();
+
+        let manifest = build_client(&host).get_root_manifest(&system).unwrap();
+
+        assert_eq!(manifest, br#"{"layers": []}"#);
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0], "/v2/tools/vm/manifests/1.0");
+        assert_eq!(
+            requests[1],
+            "/token?service=test&scope=repository%3Atools%2Fvm%3Apull"
+        );
+        assert_eq!(requests[2], "/v2/tools/vm/manifests/1.0");
+    }
+
+    #[test]
+    fn test_fetch_blob_into_cache() {
+        let (host, server) = serve(3, b"layer");
+        let system = SystemMock::new();
+        let digest = digest_of(b"layer");
+        let view = Arc::new(Mutex::new(TransferView::new("Downloading")));
+
+        let path = build_client(&host)
+            .fetch_blob(&system, &digest, 5, view)
+            .unwrap();
+
+        server.join().unwrap();
+        assert_eq!(
+            path,
+            get_blob_path(Path::new("/cache/oci"), &digest).unwrap()
+        );
+        assert_eq!(
+            system.get_written_file(&path.to_string_lossy()).unwrap(),
+            b"layer"
+        );
+    }
+
+    #[test]
+    fn test_fetch_blob_rejects_wrong_content() {
+        let (host, server) = serve(3, b"tampered");
+        let system = SystemMock::new();
+        let view = Arc::new(Mutex::new(TransferView::new("Downloading")));
+
+        let result = build_client(&host).fetch_blob(&system, &digest_of(b"layer"), 5, view);
+
+        server.join().unwrap();
+        assert!(matches!(result, Err(Error::InvalidOciImage(..))));
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        assert_eq!(
+            RegistryClient::parse_challenge(
+                r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/debian:pull,push""#
+            )
+            .unwrap(),
+            vec![
+                ("realm".to_string(), "https://auth.docker.io/token".to_string()),
+                ("service".to_string(), "registry.docker.io".to_string()),
+                (
+                    "scope".to_string(),
+                    "repository:library/debian:pull,push".to_string()
+                ),
+            ]
+        );
+        assert!(RegistryClient::parse_challenge(r#"Basic realm="registry""#).is_none());
+    }
+}
@@ -0,0 +1,5 @@
+mod ova_writer;
+mod ovf_descriptor;
+
+pub use ova_writer::OvaWriter;
+pub use ovf_descriptor::OvfDescriptor;
@@ -0,0 +1,95 @@
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tar::{Builder, Header};
+
+/// Writes an OVA, a tar archive that starts with the OVF descriptor and holds
+/// the files it references in the order they are listed.
+pub struct OvaWriter {
+    name: String,
+}
+
+impl OvaWriter {
+    /// `name` is the stem of the descriptor file inside the archive
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+
+    pub fn get_descriptor_name(&self) -> String {
+        format!("{}.ovf", self.name)
+    }
+
+    pub fn get_disk_name(&self) -> String {
+        format!("{}-disk1.vmdk", self.name)
+    }
+
+    pub fn create_ova<W: Write>(
+        &self,
+        writer: W,
+        descriptor: &str,
+        disk: &mut dyn Read,
+        disk_size: u64,
+    ) -> io::Result<()> {
+        let mut builder = Builder::new(writer);
+        builder.append_data(
+            &mut Self::build_header(descriptor.len() as u64),
+            self.get_descriptor_name(),
+            descriptor.as_bytes(),
+        )?;
+        builder.append_data(
+            &mut Self::build_header(disk_size),
+            self.get_disk_name(),
+            disk,
+        )?;
+        builder.into_inner()?.flush()
+    }
+
+    fn build_header(size: u64) -> Header {
+        let mut header = Header::new_ustar();
+        header.set_size(size);
+        header.set_mode(0o644);
+        header.set_mtime(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+        );
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tar::Archive;
+
+    #[test]
+    fn test_descriptor_comes_first() {
+        let mut ova = Vec::new();
+        OvaWriter::new("test")
+            .create_ova(&mut ova, "<Envelope/>", &mut &b"disk"[..], 4)
+            .unwrap();
+
+        let mut archive = Archive::new(&ova[..]);
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().into_owned();
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                (path, content)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("test.ovf".to_string(), "<Envelope/>".to_string()),
+                ("test-disk1.vmdk".to_string(), "disk".to_string()),
+            ]
+        );
+    }
+}
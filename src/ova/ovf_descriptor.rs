@@ -0,0 +1,148 @@
+use crate::models::Instance;
+
+const MIB: u64 = 1024 * 1024;
+// CIM operating system id of a 64-bit Linux, which both VirtualBox and VMware
+// map to a generic Linux guest
+const OS_LINUX_64: u32 = 101;
+
+/// OVF 1.0 descriptor of an instance with a single stream optimized VMDK disk
+pub struct OvfDescriptor {
+    name: String,
+    cpus: u16,
+    mem_mib: u64,
+    disk_file: String,
+    disk_file_size: u64,
+    disk_capacity: u64,
+}
+
+impl OvfDescriptor {
+    pub fn new(
+        instance: &Instance,
+        disk_file: &str,
+        disk_file_size: u64,
+        disk_capacity: u64,
+    ) -> Self {
+        Self {
+            name: instance.name.clone(),
+            cpus: instance.cpus,
+            mem_mib: instance.mem.get_bytes() as u64 / MIB,
+            disk_file: disk_file.to_string(),
+            disk_file_size,
+            disk_capacity,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let OvfDescriptor {
+            name,
+            cpus,
+            mem_mib,
+            disk_file,
+            disk_file_size,
+            disk_capacity,
+        } = self;
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Envelope ovf:version="1.0" xml:lang="en-US" xmlns="http://schemas.dmtf.org/ovf/envelope/1" xmlns:ovf="http://schemas.dmtf.org/ovf/envelope/1" xmlns:rasd="http://schemas.dmtf.org/wbem/wscim/1/cim-schema/2/CIM_ResourceAllocationSettingData" xmlns:vssd="http://schemas.dmtf.org/wbem/wscim/1/cim-schema/2/CIM_VirtualSystemSettingData" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <References>
+    <File ovf:id="file1" ovf:href="{disk_file}" ovf:size="{disk_file_size}"/>
+  </References>
+  <DiskSection>
+    <Info>Virtual disk information</Info>
+    <Disk ovf:capacity="{disk_capacity}" ovf:capacityAllocationUnits="byte" ovf:diskId="vmdisk1" ovf:fileRef="file1" ovf:format="http://www.vmware.com/interfaces/specifications/vmdk.html#streamOptimized"/>
+  </DiskSection>
+  <NetworkSection>
+    <Info>The list of logical networks</Info>
+    <Network ovf:name="NAT">
+      <Description>The NAT network</Description>
+    </Network>
+  </NetworkSection>
+  <VirtualSystem ovf:id="{name}">
+    <Info>A virtual machine</Info>
+    <Name>{name}</Name>
+    <OperatingSystemSection ovf:id="{OS_LINUX_64}">
+      <Info>The kind of installed guest operating system</Info>
+      <Description>Linux 64-Bit</Description>
+    </OperatingSystemSection>
+    <VirtualHardwareSection>
+      <Info>Virtual hardware requirements</Info>
+      <System>
+        <vssd:ElementName>Virtual Hardware Family</vssd:ElementName>
+        <vssd:InstanceID>0</vssd:InstanceID>
+        <vssd:VirtualSystemIdentifier>{name}</vssd:VirtualSystemIdentifier>
+        <vssd:VirtualSystemType>vmx-10</vssd:VirtualSystemType>
+      </System>
+      <Item>
+        <rasd:AllocationUnits>hertz * 10^6</rasd:AllocationUnits>
+        <rasd:Description>Number of Virtual CPUs</rasd:Description>
+        <rasd:ElementName>{cpus} virtual CPU(s)</rasd:ElementName>
+        <rasd:InstanceID>1</rasd:InstanceID>
+        <rasd:ResourceType>3</rasd:ResourceType>
+        <rasd:VirtualQuantity>{cpus}</rasd:VirtualQuantity>
+      </Item>
+      <Item>
+        <rasd:AllocationUnits>byte * 2^20</rasd:AllocationUnits>
+        <rasd:Description>Memory Size</rasd:Description>
+        <rasd:ElementName>{mem_mib} MB of memory</rasd:ElementName>
+        <rasd:InstanceID>2</rasd:InstanceID>
+        <rasd:ResourceType>4</rasd:ResourceType>
+        <rasd:VirtualQuantity>{mem_mib}</rasd:VirtualQuantity>
+      </Item>
+      <Item>
+        <rasd:Address>0</rasd:Address>
+        <rasd:Description>SCSI Controller</rasd:Description>
+        <rasd:ElementName>SCSI Controller 0</rasd:ElementName>
+        <rasd:InstanceID>3</rasd:InstanceID>
+        <rasd:ResourceSubType>lsilogic</rasd:ResourceSubType>
+        <rasd:ResourceType>6</rasd:ResourceType>
+      </Item>
+      <Item>
+        <rasd:AddressOnParent>0</rasd:AddressOnParent>
+        <rasd:ElementName>Hard Disk 1</rasd:ElementName>
+        <rasd:HostResource>ovf:/disk/vmdisk1</rasd:HostResource>
+        <rasd:InstanceID>4</rasd:InstanceID>
+        <rasd:Parent>3</rasd:Parent>
+        <rasd:ResourceType>17</rasd:ResourceType>
+      </Item>
+      <Item>
+        <rasd:AutomaticAllocation>true</rasd:AutomaticAllocation>
+        <rasd:Connection>NAT</rasd:Connection>
+        <rasd:ElementName>Ethernet adapter on 'NAT'</rasd:ElementName>
+        <rasd:InstanceID>5</rasd:InstanceID>
+        <rasd:ResourceSubType>E1000</rasd:ResourceSubType>
+        <rasd:ResourceType>10</rasd:ResourceType>
+      </Item>
+    </VirtualHardwareSection>
+  </VirtualSystem>
+</Envelope>
+"#
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DataSize;
+
+    #[test]
+    fn test_render_carries_the_shape_of_the_instance() {
+        let instance = Instance {
+            name: "test".to_string(),
+            cpus: 4,
+            mem: DataSize::new(2 * 1024 * 1024 * 1024),
+            ..Instance::default()
+        };
+
+        let ovf = OvfDescriptor::new(&instance, "test-disk1.vmdk", 1234, 10737418240).render();
+
+        assert!(
+            ovf.contains(r#"<File ovf:id="file1" ovf:href="test-disk1.vmdk" ovf:size="1234"/>"#)
+        );
+        assert!(ovf.contains(r#"ovf:capacity="10737418240""#));
+        assert!(ovf.contains("<Name>test</Name>"));
+        assert!(ovf.contains("<rasd:VirtualQuantity>4</rasd:VirtualQuantity>"));
+        assert!(ovf.contains("<rasd:VirtualQuantity>2048</rasd:VirtualQuantity>"));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::Result;
 use crate::platform::ReadWrite;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 pub trait Network {
@@ -8,4 +9,9 @@ pub trait Network {
     fn connect_port(&self, port: u16, timeout: Duration) -> Result<Box<dyn ReadWrite>>;
     // Takes a free loopback port from the host and reports its number.
     fn bind_port(&self) -> Result<u16>;
+    // Binds a TCP listener to `addr` and reports its port, a free one if
+    // `addr` names port 0. The port is released right away, like above.
+    fn bind_tcp_address(&self, addr: SocketAddr) -> Result<u16>;
+    // The same for a UDP socket.
+    fn bind_udp_address(&self, addr: SocketAddr) -> Result<u16>;
 }
@@ -1,6 +1,7 @@
 use crate::error::{Error, Result};
 use crate::platform::{Network, ReadWrite, SystemMock};
 use std::io::{Cursor, Read, Write};
+use std::net::SocketAddr;
 use std::time::Duration;
 
 // Where a bind starts looking. A bind skips every port the host already knows
@@ -34,6 +35,8 @@ enum PortState {
 pub struct NetworkMock {
     ports: Vec<(u16, PortState)>,
     connected: Vec<u16>,
+    // UDP ports in use. Nothing connects to them, they can only be bound.
+    udp_ports: Vec<u16>,
 }
 
 impl NetworkMock {
@@ -69,6 +72,39 @@ impl NetworkMock {
         Ok(port)
     }
 
+    // Claims the port of the address, or the lowest free one for port 0. The
+    // address itself plays no part, every port counts as taken on all of them.
+    fn bind_tcp(&mut self, addr: SocketAddr) -> Result<u16> {
+        match addr.port() {
+            0 => self.bind(),
+            port if self.find(port).is_some() => Err(Error::BindFailed(
+                addr,
+                std::io::ErrorKind::AddrInUse.into(),
+            )),
+            port => {
+                self.add(port, PortState::Bound);
+                Ok(port)
+            }
+        }
+    }
+
+    fn bind_udp(&mut self, addr: SocketAddr) -> Result<u16> {
+        let port = match addr.port() {
+            0 => (FIRST_FREE_PORT..=u16::MAX)
+                .find(|port| !self.udp_ports.contains(port))
+                .ok_or(Error::NoPortAvailable)?,
+            port if self.udp_ports.contains(&port) => {
+                return Err(Error::BindFailed(
+                    addr,
+                    std::io::ErrorKind::AddrInUse.into(),
+                ));
+            }
+            port => port,
+        };
+        self.udp_ports.push(port);
+        Ok(port)
+    }
+
     fn find(&self, port: u16) -> Option<PortState> {
         self.ports
             .iter()
@@ -125,6 +161,12 @@ impl SystemMock {
         self.add_port_state(port, PortState::Silent)
     }
 
+    // A UDP port some other program has bound.
+    pub fn add_udp_port(self, port: u16) -> Self {
+        self.network.borrow_mut().udp_ports.push(port);
+        self
+    }
+
     fn add_port_state(self, port: u16, state: PortState) -> Self {
         self.network.borrow_mut().add(port, state);
         self
@@ -146,6 +188,14 @@ impl Network for SystemMock {
     fn bind_port(&self) -> Result<u16> {
         self.network.borrow_mut().bind()
     }
+
+    fn bind_tcp_address(&self, addr: SocketAddr) -> Result<u16> {
+        self.network.borrow_mut().bind_tcp(addr)
+    }
+
+    fn bind_udp_address(&self, addr: SocketAddr) -> Result<u16> {
+        self.network.borrow_mut().bind_udp(addr)
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 use crate::error::{Error, Result};
 use crate::platform::{Network, OsSystem, ReadWrite};
-use std::net::{TcpListener, TcpStream};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
 use std::time::Duration;
 
 impl Network for OsSystem {
@@ -25,4 +25,18 @@ impl Network for OsSystem {
             .map(|addr| addr.port())
             .map_err(|_| Error::NoPortAvailable)
     }
+
+    fn bind_tcp_address(&self, addr: SocketAddr) -> Result<u16> {
+        TcpListener::bind(addr)
+            .and_then(|listener| listener.local_addr())
+            .map(|addr| addr.port())
+            .map_err(|e| Error::BindFailed(addr, e))
+    }
+
+    fn bind_udp_address(&self, addr: SocketAddr) -> Result<u16> {
+        UdpSocket::bind(addr)
+            .and_then(|socket| socket.local_addr())
+            .map(|addr| addr.port())
+            .map_err(|e| Error::BindFailed(addr, e))
+    }
 }
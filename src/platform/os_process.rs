@@ -72,6 +72,25 @@ impl Process for OsSystem {
             })
     }
 
+    fn run_command_stderr(&self, command: &SystemCommand) -> Result<Vec<u8>> {
+        Self::build_process(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| Self::map_spawn_error(command, e))
+            .and_then(|out| {
+                if out.status.success() {
+                    Ok(out.stderr)
+                } else {
+                    Err(Error::SystemCommandFailed(
+                        command.get_command(),
+                        from_utf8(&out.stderr).unwrap_or_default().to_string(),
+                    ))
+                }
+            })
+    }
+
     // Reads rather than polls, so the command is only kept alive until it says
     // it is up. Stdin stays open, or a command that reads it sees an early end
     // of file and stops on its own.
@@ -129,6 +148,62 @@ impl Process for OsSystem {
         ))
     }
 
+    fn run_command_streaming(
+        &self,
+        command: &SystemCommand,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        let mut child = Self::build_process(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Self::map_spawn_error(command, e))?;
+
+        // Stderr is drained on its own thread, so a chatty command never
+        // blocks on a full pipe while stdout is read here.
+        let collector = child.stderr.take().map(|stderr| {
+            thread::spawn(move || {
+                let mut text = String::new();
+                BufReader::new(stderr).read_to_string(&mut text).ok();
+                text
+            })
+        });
+
+        if let Some(stdout) = child.stdout.take() {
+            let mut line = Vec::new();
+            for byte in BufReader::new(stdout).bytes() {
+                let Ok(byte) = byte else {
+                    break;
+                };
+                if byte == b'\r' || byte == b'\n' {
+                    if !line.is_empty() {
+                        on_line(&String::from_utf8_lossy(&line));
+                        line.clear();
+                    }
+                } else {
+                    line.push(byte);
+                }
+            }
+            if !line.is_empty() {
+                on_line(&String::from_utf8_lossy(&line));
+            }
+        }
+
+        let status = child
+            .wait()
+            .map_err(|e| Error::SystemCommandFailed(command.get_command(), e.to_string()))?;
+        if status.success() {
+            return Ok(());
+        }
+        Err(Error::SystemCommandFailed(
+            command.get_command(),
+            collector
+                .and_then(|collector| collector.join().ok())
+                .unwrap_or_default(),
+        ))
+    }
+
     fn spawn_command(&self, command: &SystemCommand) -> Result<()> {
         let mut process = Self::build_process(command);
 
@@ -36,6 +36,14 @@ impl Terminal for OsSystem {
         reply.trim().to_string()
     }
 
+    fn read_line(&self) -> Option<String> {
+        let mut reply = String::new();
+        match stdin().read_line(&mut reply) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(reply.trim().to_string()),
+        }
+    }
+
     // Reads a password character by character in raw mode, without echoing
     // input back to the terminal (not even as masking characters).
     fn read_secret(&self) -> std::result::Result<String, ()> {
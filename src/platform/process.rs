@@ -4,6 +4,9 @@ use std::time::Duration;
 
 pub trait Process {
     fn run_command(&self, command: &SystemCommand) -> Result<Vec<u8>>;
+    // Like `run_command`, but returns what the command wrote to stderr, which
+    // is where some tools report their version.
+    fn run_command_stderr(&self, command: &SystemCommand) -> Result<Vec<u8>>;
     // Runs a command that is not expected to end by itself and waits for a
     // marker on its stdout. The marker means the command came up, so it is
     // killed right away. A command that ends first, or one that never prints
@@ -14,6 +17,14 @@ pub trait Process {
         marker: &str,
         timeout: Duration,
     ) -> Result<()>;
+    // Runs a command to its end and hands every line of its stdout to
+    // `on_line` as it arrives. A carriage return ends a line too, since that
+    // is how tools redraw their progress in place.
+    fn run_command_streaming(
+        &self,
+        command: &SystemCommand,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<()>;
     fn spawn_command(&self, command: &SystemCommand) -> Result<()>;
 
     fn exists_process(&self, pid: u64) -> bool;
@@ -145,6 +145,11 @@ impl Process for SystemMock {
         self.commands.borrow_mut().run(command)
     }
 
+    // The seeded output stands for stderr here.
+    fn run_command_stderr(&self, command: &SystemCommand) -> Result<Vec<u8>> {
+        self.commands.borrow_mut().run(command)
+    }
+
     // A seeded command stands for one that comes up and prints the marker, so
     // neither the marker nor the deadline matters here.
     fn run_command_until_output(
@@ -156,6 +161,21 @@ impl Process for SystemMock {
         self.commands.borrow_mut().run(command).map(|_| ())
     }
 
+    // The seeded stdout arrives all at once, split the same way as a live
+    // command would deliver it.
+    fn run_command_streaming(
+        &self,
+        command: &SystemCommand,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        let stdout = self.commands.borrow_mut().run(command)?;
+        String::from_utf8_lossy(&stdout)
+            .split(['\r', '\n'])
+            .filter(|line| !line.is_empty())
+            .for_each(on_line);
+        Ok(())
+    }
+
     // A detached start has nothing to wait for, so it only reports whether the
     // host could launch the command at all.
     fn spawn_command(&self, command: &SystemCommand) -> Result<()> {
@@ -300,6 +320,21 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn run_command_streaming_splits_lines_at_carriage_returns() {
+        let system =
+            SystemMock::new().add_command_output("qemu-img", b"(1.00/100%)\r(50.00/100%)\r\n");
+
+        let mut lines = Vec::new();
+        system
+            .run_command_streaming(&SystemCommand::new("qemu-img"), &mut |line| {
+                lines.push(line.to_string())
+            })
+            .unwrap();
+
+        assert_eq!(lines, vec!["(1.00/100%)", "(50.00/100%)"]);
+    }
+
     #[test]
     fn get_executed_commands_records_every_attempt_in_order() {
         let system = SystemMock::new().add_command_output("echo one", b"");
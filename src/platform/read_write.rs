@@ -1,6 +1,7 @@
 use std::io::{Read, Write};
 
 // A stream that can be both read and written, so a connection can be handed
-// out as a trait object without naming the transport behind it.
-pub trait ReadWrite: Read + Write {}
-impl<T: Read + Write> ReadWrite for T {}
+// out as a trait object without naming the transport behind it. It may move
+// to another thread, e.g. to wait for a monitor reply off the runtime.
+pub trait ReadWrite: Read + Write + Send {}
+impl<T: Read + Write + Send> ReadWrite for T {}
@@ -7,6 +7,9 @@ pub trait Terminal {
     fn is_terminal(&self, stream: Stream) -> bool;
 
     fn read_input(&self) -> String;
+    // Like read_input, but None at the end of the input (e.g. Ctrl+D), which
+    // a prompt loop needs to tell apart from an empty line.
+    fn read_line(&self) -> Option<String>;
     fn read_secret(&self) -> std::result::Result<String, ()>;
 
     fn raw_mode(&self);
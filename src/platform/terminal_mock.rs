@@ -63,6 +63,15 @@ impl Terminal for SystemMock {
         self.terminal.borrow_mut().pop_input().trim().to_string()
     }
 
+    fn read_line(&self) -> Option<String> {
+        let mut terminal = self.terminal.borrow_mut();
+        if terminal.input.is_empty() {
+            None
+        } else {
+            Some(terminal.pop_input().trim().to_string())
+        }
+    }
+
     fn read_secret(&self) -> std::result::Result<String, ()> {
         Ok(self.terminal.borrow_mut().pop_input())
     }
@@ -96,4 +105,13 @@ mod tests {
         assert_eq!(system.read_input(), "second");
         assert_eq!(system.read_input(), "");
     }
+
+    #[test]
+    fn read_line_ends_once_input_is_drained() {
+        let system = SystemMock::new();
+        system.push_input("first");
+
+        assert_eq!(system.read_line().as_deref(), Some("first"));
+        assert_eq!(system.read_line(), None);
+    }
 }
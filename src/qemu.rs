@@ -6,13 +6,15 @@ mod qemu_monitor_client;
 mod qemu_path_builder;
 mod qemu_system;
 mod qmp_message;
+mod swtpm;
 mod tls_client;
 
 pub use qemu_accelerator::QemuAcceleratorProbe;
-pub use qemu_firmware::{QemuFirmware, QemuInstall};
+pub use qemu_firmware::{FirmwareFiles, QemuFirmware, QemuInstall};
 pub use qemu_img::*;
 pub use qemu_monitor_client::*;
 pub use qemu_path_builder::QemuPathBuilder;
 pub use qemu_system::*;
 pub use qmp_message::*;
+pub use swtpm::*;
 pub use tls_client::*;
@@ -8,13 +8,30 @@ use crate::qemu::qemu_path_builder::find_in_dir;
 
 pub struct QemuFirmware;
 
+/// Firmware code and the template of its variable store
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirmwareFiles {
+    pub code: PathBuf,
+    pub nvram_template: Option<PathBuf>,
+}
+
 impl QemuFirmware {
-    pub fn locate(system: &dyn System, dirs: &[PathBuf], arch: Arch) -> Option<PathBuf> {
+    pub fn locate(
+        system: &dyn System,
+        dirs: &[PathBuf],
+        arch: Arch,
+        secure_boot: bool,
+    ) -> Option<FirmwareFiles> {
+        // The override names a plain firmware, it says nothing about its keys
         let var = format!("CUBIC_QEMU_FW_{}", arch.as_vendor_str().to_uppercase());
-        if let Some(fw) = system.read_env_var(&var) {
-            return Some(PathBuf::from(fw)); // trust the override as-is
+        if !secure_boot && let Some(fw) = system.read_env_var(&var) {
+            // trust the override as-is
+            return Some(FirmwareFiles {
+                code: PathBuf::from(fw),
+                nvram_template: None,
+            });
         }
-        QemuInstall::find(system, dirs)?.find_firmware(arch)
+        QemuInstall::find(system, dirs)?.find_firmware(arch, secure_boot)
     }
 }
 
@@ -81,12 +98,17 @@ impl<'a> QemuInstall<'a> {
             .any(|path| path.extension().is_some_and(|ext| ext == "so"))
     }
 
-    fn find_firmware(&self, arch: Arch) -> Option<PathBuf> {
+    fn find_firmware(&self, arch: Arch, secure_boot: bool) -> Option<FirmwareFiles> {
         self.collect_descriptors()
             .into_iter()
-            .filter(|descriptor| descriptor.matches(arch))
-            .map(|descriptor| descriptor.build_code_path(&self.prefix))
-            .find(|code| self.system.exists_path(code))
+            .filter(|descriptor| descriptor.matches(arch, secure_boot))
+            .map(|descriptor| FirmwareFiles {
+                code: descriptor.build_code_path(&self.prefix),
+                nvram_template: descriptor
+                    .build_nvram_template_path(&self.prefix)
+                    .filter(|template| self.system.exists_path(template)),
+            })
+            .find(|firmware| self.system.exists_path(&firmware.code))
     }
 
     fn collect_descriptors(&self) -> Vec<QemuFirmwareDescriptor> {
@@ -157,16 +179,22 @@ mod tests {
     fn test_locate_trusts_override_env_var_without_scanning_dirs() {
         let system = SystemMock::new().add_env_var("CUBIC_QEMU_FW_AMD64", "/custom/fw.bin");
 
-        let firmware = QemuFirmware::locate(&system, &[], Arch::AMD64);
+        let firmware = QemuFirmware::locate(&system, &[], Arch::AMD64, false);
 
-        assert_eq!(firmware, Some(PathBuf::from("/custom/fw.bin")));
+        assert_eq!(
+            firmware,
+            Some(FirmwareFiles {
+                code: PathBuf::from("/custom/fw.bin"),
+                nvram_template: None,
+            })
+        );
     }
 
     #[test]
     fn test_locate_returns_none_when_no_override_and_no_install_found() {
         let system = SystemMock::new();
 
-        let firmware = QemuFirmware::locate(&system, &[], Arch::AMD64);
+        let firmware = QemuFirmware::locate(&system, &[], Arch::AMD64, false);
 
         assert_eq!(firmware, None);
     }
@@ -313,11 +341,69 @@ mod tests {
             prefix: PathBuf::from("/prefix"),
         };
 
-        let firmware = install.find_firmware(Arch::AMD64);
+        let firmware = install.find_firmware(Arch::AMD64, false);
 
         assert_eq!(
             firmware,
-            Some(PathBuf::from("/prefix/share/qemu/firmware/code.bin"))
+            Some(FirmwareFiles {
+                code: PathBuf::from("/prefix/share/qemu/firmware/code.bin"),
+                nvram_template: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_firmware_picks_secure_boot_build_with_its_vars() {
+        let descriptor = |code: &str, vars: &str, features: &str| {
+            format!(
+                r#"{{
+                    "interface-types": ["uefi"],
+                    "mapping": {{
+                        "device": "flash",
+                        "executable": {{ "filename": "/usr/share/OVMF/{code}" }},
+                        "nvram-template": {{ "filename": "/usr/share/OVMF/{vars}" }}
+                    }},
+                    "targets": [{{ "architecture": "x86_64", "machines": ["pc-q35-*"] }}],
+                    "features": [{features}]
+                }}"#
+            )
+        };
+        let system = SystemMock::new()
+            .add_file(
+                "/etc/qemu/firmware/30-ovmf-ms.json",
+                descriptor(
+                    "OVMF_CODE_4M.ms.fd",
+                    "OVMF_VARS_4M.ms.fd",
+                    r#""enrolled-keys", "requires-smm", "secure-boot""#,
+                )
+                .as_bytes(),
+            )
+            .add_file(
+                "/etc/qemu/firmware/60-ovmf.json",
+                descriptor("OVMF_CODE_4M.fd", "OVMF_VARS_4M.fd", "").as_bytes(),
+            )
+            .add_file("/usr/share/OVMF/OVMF_CODE_4M.ms.fd", b"")
+            .add_file("/usr/share/OVMF/OVMF_VARS_4M.ms.fd", b"")
+            .add_file("/usr/share/OVMF/OVMF_CODE_4M.fd", b"")
+            .add_file("/usr/share/OVMF/OVMF_VARS_4M.fd", b"");
+        let install = QemuInstall {
+            system: &system,
+            prefix: PathBuf::from("/usr"),
+        };
+
+        assert_eq!(
+            install.find_firmware(Arch::AMD64, true),
+            Some(FirmwareFiles {
+                code: PathBuf::from("/usr/share/OVMF/OVMF_CODE_4M.ms.fd"),
+                nvram_template: Some(PathBuf::from("/usr/share/OVMF/OVMF_VARS_4M.ms.fd")),
+            })
+        );
+        assert_eq!(
+            install.find_firmware(Arch::AMD64, false),
+            Some(FirmwareFiles {
+                code: PathBuf::from("/usr/share/OVMF/OVMF_CODE_4M.fd"),
+                nvram_template: Some(PathBuf::from("/usr/share/OVMF/OVMF_VARS_4M.fd")),
+            })
         );
     }
 }
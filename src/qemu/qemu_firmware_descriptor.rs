@@ -21,6 +21,7 @@ pub struct QemuFirmwareDescriptor {
 struct Mapping {
     device: String,
     executable: FileRef,
+    nvram_template: Option<FileRef>,
 }
 
 #[derive(Deserialize)]
@@ -35,24 +36,33 @@ struct Target {
     machines: Vec<String>,
 }
 
-const EXCLUDED_FEATURES: &[&str] = &["secure-boot", "requires-smm", "amd-sev-snp", "intel-tdx"];
+const EXCLUDED_FEATURES: &[&str] = &["amd-sev-snp", "intel-tdx"];
+// Set by the firmware builds that boot with Secure Boot. Plain firmware must
+// have none of them, a Secure Boot firmware all but SMM, which only amd64 has.
+const SECURE_BOOT_FEATURES: &[&str] = &["secure-boot", "enrolled-keys", "requires-smm"];
 
 impl QemuFirmwareDescriptor {
     pub fn parse(json: &str) -> Option<Self> {
         serde_json::from_str(json).ok()
     }
 
-    pub fn matches(&self, arch: Arch) -> bool {
+    pub fn matches(&self, arch: Arch, secure_boot: bool) -> bool {
         let machine = match arch {
             Arch::AMD64 => "q35",
             Arch::ARM64 => "virt",
         };
+        let has_feature = |feature: &str| self.features.iter().any(|f| f == feature);
+        let secure_boot_matches = if secure_boot {
+            has_feature("secure-boot") && has_feature("enrolled-keys")
+        } else {
+            !SECURE_BOOT_FEATURES
+                .iter()
+                .any(|feature| has_feature(feature))
+        };
         self.mapping.device == "flash"
             && self.interface_types.iter().any(|i| i == "uefi")
-            && self
-                .features
-                .iter()
-                .all(|f| !EXCLUDED_FEATURES.contains(&f.as_str()))
+            && !EXCLUDED_FEATURES.iter().any(|feature| has_feature(feature))
+            && secure_boot_matches
             && self.targets.iter().any(|target| {
                 target.architecture == arch.as_canonical_str()
                     && target.machines.iter().any(|m| m.contains(machine))
@@ -60,10 +70,22 @@ impl QemuFirmwareDescriptor {
     }
 
     pub fn build_code_path(&self, prefix: &Path) -> PathBuf {
-        let code = Path::new(&self.mapping.executable.filename);
-        match code.components().position(|c| c.as_os_str() == "share") {
-            Some(index) => prefix.join(code.components().skip(index).collect::<PathBuf>()),
-            None => code.to_path_buf(),
+        Self::build_path(prefix, &self.mapping.executable.filename)
+    }
+
+    /// Template of the variable store, which every instance gets a copy of
+    pub fn build_nvram_template_path(&self, prefix: &Path) -> Option<PathBuf> {
+        self.mapping
+            .nvram_template
+            .as_ref()
+            .map(|template| Self::build_path(prefix, &template.filename))
+    }
+
+    fn build_path(prefix: &Path, file: &str) -> PathBuf {
+        let file = Path::new(file);
+        match file.components().position(|c| c.as_os_str() == "share") {
+            Some(index) => prefix.join(file.components().skip(index).collect::<PathBuf>()),
+            None => file.to_path_buf(),
         }
     }
 }
@@ -105,8 +127,9 @@ mod tests {
         let plain =
             QemuFirmwareDescriptor::parse(&build_descriptor("x86_64", "pc-q35-8.0", "/c", ""))
                 .unwrap();
-        assert!(plain.matches(Arch::AMD64));
-        assert!(!plain.matches(Arch::ARM64));
+        assert!(plain.matches(Arch::AMD64, false));
+        assert!(!plain.matches(Arch::ARM64, false));
+        assert!(!plain.matches(Arch::AMD64, true));
     }
 
     #[test]
@@ -118,7 +141,7 @@ mod tests {
             "\"acpi-s3\", \"amd-sev\", \"amd-sev-es\", \"verbose-dynamic\"",
         ))
         .unwrap();
-        assert!(plain.matches(Arch::AMD64));
+        assert!(plain.matches(Arch::AMD64, false));
     }
 
     #[test]
@@ -126,7 +149,7 @@ mod tests {
         let i440fx =
             QemuFirmwareDescriptor::parse(&build_descriptor("x86_64", "pc-i440fx-8.0", "/c", ""))
                 .unwrap();
-        assert!(!i440fx.matches(Arch::AMD64));
+        assert!(!i440fx.matches(Arch::AMD64, false));
 
         let secure = QemuFirmwareDescriptor::parse(&build_descriptor(
             "x86_64",
@@ -135,7 +158,7 @@ mod tests {
             "\"secure-boot\"",
         ))
         .unwrap();
-        assert!(!secure.matches(Arch::AMD64));
+        assert!(!secure.matches(Arch::AMD64, false));
 
         let bios = QemuFirmwareDescriptor::parse(
             r#"{ "interface-types": ["bios"],
@@ -143,7 +166,38 @@ mod tests {
                  "targets": [{ "architecture": "x86_64", "machines": ["pc-q35-8.0"] }] }"#,
         )
         .unwrap();
-        assert!(!bios.matches(Arch::AMD64));
+        assert!(!bios.matches(Arch::AMD64, false));
+    }
+
+    #[test]
+    fn test_matches_secure_boot_with_enrolled_keys() {
+        let secure = QemuFirmwareDescriptor::parse(&build_descriptor(
+            "x86_64",
+            "pc-q35-8.0",
+            "/c",
+            "\"enrolled-keys\", \"requires-smm\", \"secure-boot\"",
+        ))
+        .unwrap();
+        assert!(secure.matches(Arch::AMD64, true));
+        assert!(!secure.matches(Arch::AMD64, false));
+
+        let no_keys = QemuFirmwareDescriptor::parse(&build_descriptor(
+            "x86_64",
+            "pc-q35-8.0",
+            "/c",
+            "\"requires-smm\", \"secure-boot\"",
+        ))
+        .unwrap();
+        assert!(!no_keys.matches(Arch::AMD64, true));
+    }
+
+    #[test]
+    fn test_build_nvram_template_path_reads_the_template() {
+        let descriptor = build_descriptor_with_code("/usr/share/OVMF/OVMF_CODE_4M.fd");
+        assert_eq!(
+            descriptor.build_nvram_template_path(Path::new("/opt/qemu")),
+            Some(PathBuf::from("/x/VARS.fd"))
+        );
     }
 
     #[test]
@@ -156,7 +210,10 @@ mod tests {
                 &format!("\"{feature}\""),
             ))
             .unwrap();
-            assert!(!descriptor.matches(Arch::AMD64), "{feature} should reject");
+            assert!(
+                !descriptor.matches(Arch::AMD64, false),
+                "{feature} should reject"
+            );
         }
     }
 
@@ -1,16 +1,23 @@
 use crate::error::{Error, Result};
-use crate::models::{DataSize, Environment, Instance};
+use crate::models::{DataSize, Environment, ExportFormat, Instance};
 use crate::platform::System;
 use crate::qemu::QemuPathBuilder;
 use crate::util::SystemCommand;
 use serde::{Deserialize, Serialize};
 
+/// QEMU object id of the passphrase that unlocks an encrypted disk
+pub const DISK_SECRET_ID: &str = "disk-secret";
+/// Dirty bitmap that tracks the writes since the latest backup
+pub const BACKUP_BITMAP: &str = "cubic-backup";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImageInfo {
     #[serde(alias = "actual-size")]
     pub actual_size: u64,
     #[serde(alias = "virtual-size")]
     pub virtual_size: u64,
+    #[serde(default)]
+    pub format: String,
 }
 
 pub struct QemuImg<'a> {
@@ -39,13 +46,17 @@ impl<'a> QemuImg<'a> {
     // an image the tool cannot read, leaves the caller with what it already
     // knows about the disk.
     pub fn get_image_info(&self, env: &Environment, instance: &Instance) -> Option<ImageInfo> {
+        self.get_file_info(&env.get_instance_image_file(&instance.name))
+    }
+
+    pub fn get_file_info(&self, path: &str) -> Option<ImageInfo> {
         let mut command = self.command();
         command
             .arg("info")
             .arg("--force-share")
             .arg("--output")
             .arg("json")
-            .arg(env.get_instance_image_file(&instance.name));
+            .arg(path);
 
         self.system
             .run_command(&command)
@@ -79,6 +90,74 @@ impl<'a> QemuImg<'a> {
             .map_err(Self::map_error)
     }
 
+    /// Converts an image of another format, such as a VMDK, to qcow2
+    pub fn import(&self, src: &str, format: &str, dst: &str) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("convert")
+            .arg("-f")
+            .arg(format)
+            .arg("-O")
+            .arg("qcow2")
+            .arg(src)
+            .arg(dst);
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    pub fn create(&self, image: &str, size: u64) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("create")
+            .arg("-f")
+            .arg("qcow2")
+            .arg(image)
+            .arg(size.to_string());
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    // QEMU resolves a relative backing file from the directory of the image
+    pub fn create_overlay(&self, image: &str, backing: &str, size: u64) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("create")
+            .arg("-f")
+            .arg("qcow2")
+            .arg("-b")
+            .arg(backing)
+            .arg("-F")
+            .arg("qcow2")
+            .arg(image)
+            .arg(size.to_string());
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    // Starts the bitmap over, so it tracks the writes from now on
+    pub fn reset_bitmap(&self, image: &str, bitmap: &str) -> Result<()> {
+        let mut command = self.command();
+        command.arg("bitmap").arg("--remove").arg(image).arg(bitmap);
+        // Fails when there is no bitmap yet, which is just as good
+        self.system.run_command(&command).ok();
+
+        let mut command = self.command();
+        command.arg("bitmap").arg("--add").arg(image).arg(bitmap);
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
     pub fn resize(&self, image: &str, size: u64) -> Result<()> {
         let mut command = self.command();
         command.arg("resize").arg(image).arg(size.to_string());
@@ -88,6 +167,126 @@ impl<'a> QemuImg<'a> {
             .map(|_| ())
             .map_err(Self::map_error)
     }
+
+    // The passphrase is read from a file, so it never shows up in a process
+    // listing
+    fn secret_object(secret_file: &str) -> String {
+        format!("secret,id={DISK_SECRET_ID},file={secret_file}")
+    }
+
+    pub fn convert_encrypted(&self, src: &str, dst: &str, secret_file: &str) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("convert")
+            .arg("--object")
+            .arg(Self::secret_object(secret_file))
+            .arg("-f")
+            .arg("qcow2")
+            .arg("-O")
+            .arg("qcow2")
+            .arg("-o")
+            .arg(format!(
+                "encrypt.format=luks,encrypt.key-secret={DISK_SECRET_ID}"
+            ))
+            .arg(src)
+            .arg(dst);
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    pub fn resize_encrypted(&self, image: &str, size: u64, secret_file: &str) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("resize")
+            .arg("--object")
+            .arg(Self::secret_object(secret_file))
+            .arg("--image-opts")
+            .arg(format!(
+                "driver=qcow2,file.filename={image},encrypt.key-secret={DISK_SECRET_ID}"
+            ))
+            .arg(size.to_string());
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    // Cuts off the end of the image. Whatever the guest stored there is gone.
+    pub fn shrink(&self, image: &str, size: u64) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("resize")
+            .arg("--shrink")
+            .arg(image)
+            .arg(size.to_string());
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    /// Copies the first `size` bytes the guest sees on the disk into a raw
+    /// file, so its partition table can be read without booting it
+    pub fn read_head(&self, image: &str, dst: &str, size: u64) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("dd")
+            .arg("-f")
+            .arg("qcow2")
+            .arg("-O")
+            .arg("raw")
+            .arg(format!("bs={size}"))
+            .arg("count=1")
+            .arg(format!("if={image}"))
+            .arg(format!("of={dst}"));
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(Self::map_error)
+    }
+
+    /// Writes the image in a format other hypervisors read, reporting the
+    /// share done from 0.0 to 1.0 along the way.
+    pub fn export(
+        &self,
+        src: &str,
+        dst: &str,
+        format: ExportFormat,
+        on_progress: &mut dyn FnMut(f64),
+    ) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("convert")
+            .arg("-p")
+            .arg("-f")
+            .arg("qcow2")
+            .arg("-O")
+            .arg(format.get_image_format());
+        if let Some(options) = format.get_image_options() {
+            command.arg("-o").arg(options);
+        }
+        command.arg(src).arg(dst);
+
+        self.system
+            .run_command_streaming(&command, &mut |line| {
+                if let Some(progress) = Self::parse_progress(line) {
+                    on_progress(progress);
+                }
+            })
+            .map_err(Self::map_error)
+    }
+
+    // qemu-img -p redraws a line such as `    (42.00/100%)`
+    fn parse_progress(line: &str) -> Option<f64> {
+        let percent = line.trim().strip_prefix('(')?.split_once('/')?.0;
+        percent.parse::<f64>().ok().map(|percent| percent / 100.0)
+    }
 }
 
 #[cfg(test)]
@@ -184,6 +383,91 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_shrink_allows_qemu_img_to_cut_the_image() {
+        let system = SystemMock::new().add_command_output(
+            "qemu-img resize --shrink /data/machines/test/image 1024",
+            b"",
+        );
+
+        QemuImg::new(&system)
+            .shrink("/data/machines/test/image", 1024)
+            .unwrap();
+
+        assert_eq!(
+            system.get_executed_commands(),
+            vec!["qemu-img resize --shrink /data/machines/test/image 1024"]
+        );
+    }
+
+    #[test]
+    fn test_read_head_copies_the_start_of_the_disk() {
+        let system = SystemMock::new().add_command_output(
+            "qemu-img dd -f qcow2 -O raw bs=1048576 count=1 if=/data/machines/test/image of=/data/machines/test/disk_head",
+            b"",
+        );
+
+        QemuImg::new(&system)
+            .read_head(
+                "/data/machines/test/image",
+                "/data/machines/test/disk_head",
+                1048576,
+            )
+            .unwrap();
+
+        assert_eq!(system.get_executed_commands().len(), 1);
+    }
+
+    #[test]
+    fn test_convert_encrypted_keeps_the_passphrase_off_the_command_line() {
+        let system = SystemMock::new().add_command_output(
+            "qemu-img convert --object secret,id=disk-secret,file=/data/disk_secret -f qcow2 -O qcow2 -o encrypt.format=luks,encrypt.key-secret=disk-secret /cache/image /data/machine.img",
+            b"",
+        );
+
+        QemuImg::new(&system)
+            .convert_encrypted("/cache/image", "/data/machine.img", "/data/disk_secret")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_resize_encrypted_unlocks_the_image() {
+        let system = SystemMock::new().add_command_output(
+            "qemu-img resize --object secret,id=disk-secret,file=/data/disk_secret --image-opts driver=qcow2,file.filename=/data/machine.img,encrypt.key-secret=disk-secret 2048",
+            b"",
+        );
+
+        QemuImg::new(&system)
+            .resize_encrypted("/data/machine.img", 2048, "/data/disk_secret")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_create_overlay_names_the_backing_file() {
+        let system = SystemMock::new().add_command_output(
+            "qemu-img create -f qcow2 -b 1-full.qcow2 -F qcow2 /backup/test/2-inc.qcow2 1024",
+            b"",
+        );
+
+        QemuImg::new(&system)
+            .create_overlay("/backup/test/2-inc.qcow2", "1-full.qcow2", 1024)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_reset_bitmap_adds_the_bitmap_without_an_old_one() {
+        let system = SystemMock::new()
+            .add_failing_command(
+                "qemu-img bitmap --remove /data/machine.img cubic-backup",
+                "not found",
+            )
+            .add_command_output("qemu-img bitmap --add /data/machine.img cubic-backup", b"");
+
+        QemuImg::new(&system)
+            .reset_bitmap("/data/machine.img", BACKUP_BITMAP)
+            .unwrap();
+    }
+
     #[test]
     fn test_convert_reports_the_failure_of_qemu_img() {
         let system = SystemMock::new().add_failing_command(
@@ -223,6 +507,24 @@ mod tests {
 
         let result: ImageInfo = serde_json::from_str(input).unwrap();
         assert_eq!(result.actual_size, 200704);
+        assert_eq!(result.format, "qcow2");
+    }
+
+    #[test]
+    fn test_create_makes_a_qcow2_image_of_the_size() {
+        let system = SystemMock::new().add_command_output(
+            "qemu-img create -f qcow2 /data/machines/test/disk-data.qcow2 1024",
+            b"",
+        );
+
+        QemuImg::new(&system)
+            .create("/data/machines/test/disk-data.qcow2", 1024)
+            .unwrap();
+
+        assert_eq!(
+            system.get_executed_commands(),
+            vec!["qemu-img create -f qcow2 /data/machines/test/disk-data.qcow2 1024"]
+        );
     }
 
     #[test]
@@ -243,4 +545,27 @@ mod tests {
             Error::SystemCommandFailed(..)
         ));
     }
+
+    #[test]
+    fn test_parse_progress() {
+        assert_eq!(QemuImg::parse_progress("    (42.00/100%)"), Some(0.42));
+        assert_eq!(QemuImg::parse_progress("unrelated"), None);
+    }
+
+    #[test]
+    fn test_export_reports_the_progress() {
+        let system = SystemMock::new().add_command_output(
+            "qemu-img convert -p -f qcow2 -O vmdk -o subformat=streamOptimized /src.img /dst.vmdk",
+            b"    (0.00/100%)\r    (50.00/100%)\r    (100.00/100%)\r\n",
+        );
+
+        let mut progress = Vec::new();
+        QemuImg::new(&system)
+            .export("/src.img", "/dst.vmdk", ExportFormat::Ova, &mut |value| {
+                progress.push(value)
+            })
+            .unwrap();
+
+        assert_eq!(progress, vec![0.0, 0.5, 1.0]);
+    }
 }
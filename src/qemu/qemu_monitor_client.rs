@@ -1,24 +1,55 @@
 use crate::error::{Error, Result};
-use crate::models::{Environment, Instance, InstanceCertPaths, PortForward};
+use crate::models::{
+    Disk, DiskSettings, Environment, Instance, InstanceCertPaths, KeyCombo, PortForward,
+};
 use crate::platform::ReadWrite;
-use crate::qemu::{NETDEV_ID, QmpMessage, TlsClient};
+use crate::qemu::{
+    BACKUP_BITMAP, HOTPLUG_PORT_PREFIX, IOTHREAD_ID, NETDEV_ID, QmpError, QmpEvent, QmpMessage,
+    TlsClient,
+};
 use serde_json::{Value, json};
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+/// Size that hot-added memory comes in multiples of, which QEMU demands of a DIMM
+pub const MEMORY_HOTPLUG_ALIGNMENT: u64 = 2 * 1024 * 1024;
 const QMP_TIMEOUT: Duration = Duration::from_millis(100);
+// How long the guest may take to let go of a device before it is unplugged
+const UNPLUG_TIMEOUT: Duration = Duration::from_secs(10);
+// How long a command may take to reply, which is far beyond a single read
+// since some commands (e.g. dumps) need a moment.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+// Node the backup image is opened as while a backup job writes to it
+const BACKUP_NODE: &str = "backup-target";
+const BACKUP_JOB: &str = "backup";
+// How long a backup job may run, which copies the whole disk at worst
+const BACKUP_TIMEOUT: Duration = Duration::from_secs(60 * 60);
 
 pub struct QemuMonitorClient {
     counter: u64,
     stream: BufReader<Box<dyn ReadWrite>>,
+    // Start of a message whose read timed out before its line ended
+    line: Vec<u8>,
+    // Events that arrived while waiting for a command reply
+    events: VecDeque<QmpEvent>,
 }
 
 impl QemuMonitorClient {
     pub fn new(env: &Environment, instance: &Instance) -> Result<Self> {
-        let port = instance
-            .monitor_port
-            .ok_or_else(|| Error::InstanceNotRunning(instance.name.clone()))?;
+        Self::connect(env, instance, instance.monitor_port)
+    }
+
+    // Connects to the second monitor, which only serves event listeners. A
+    // QMP socket takes one client at a time, so a long-running listener on
+    // the main monitor would lock out every other command.
+    pub fn new_event_listener(env: &Environment, instance: &Instance) -> Result<Self> {
+        Self::connect(env, instance, instance.events_port)
+    }
+
+    fn connect(env: &Environment, instance: &Instance, port: Option<u16>) -> Result<Self> {
+        let port = port.ok_or_else(|| Error::InstanceNotRunning(instance.name.clone()))?;
         let instance_dir = PathBuf::from(env.get_instance_dir2(&instance.name));
         let certs = InstanceCertPaths::load(&instance_dir);
         let mut stream = TlsClient::new(&certs)?.connect(port)?;
@@ -33,6 +64,8 @@ impl QemuMonitorClient {
         let mut client = QemuMonitorClient {
             counter: 0,
             stream: BufReader::new(Box::new(stream)),
+            line: Vec::new(),
+            events: VecDeque::new(),
         };
         client.init()?;
         Ok(client)
@@ -42,54 +75,502 @@ impl QemuMonitorClient {
         self.execute("system_powerdown")
     }
 
-    pub fn add_hostfwd(&mut self, fwd: &PortForward) -> Result<()> {
-        let output = self.run_hmp_command(&format!("hostfwd_add {NETDEV_ID} {}", fwd.to_qemu()))?;
-        if output.is_empty() {
-            Ok(())
+    // Ends QEMU right away without involving the guest, like pulling the plug.
+    pub fn quit(&mut self) -> Result<()> {
+        self.execute("quit")
+    }
+
+    // Reads messages until the named event arrives or the timeout expires.
+    // Returns whether the event was seen.
+    pub fn wait_for_event(&mut self, event: &str, timeout: Duration) -> Result<bool> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self
+                .next_event(deadline - Instant::now())?
+                .is_some_and(|next| next.event == event)
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // Returns the next event QEMU reported, or None if none arrived before
+    // the timeout. Fails once QEMU has closed the monitor.
+    pub fn next_event(&mut self, timeout: Duration) -> Result<Option<QmpEvent>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            if let Some(QmpMessage::Event(event)) = self.try_recv()? {
+                self.events.push_back(event);
+            }
+        }
+    }
+
+    // Sets the memory the guest may use, which cannot exceed the boot size
+    pub fn set_balloon(&mut self, size: u64) -> Result<()> {
+        self.execute_raw("balloon", json!({ "value": size }))
+            .map(|_| ())
+    }
+
+    // Replaces the password of the VNC server, which takes 8 characters at most
+    pub fn set_vnc_password(&mut self, password: &str) -> Result<()> {
+        self.execute_raw(
+            "set_password",
+            json!({ "protocol": "vnc", "password": password }),
+        )
+        .map(|_| ())
+    }
+
+    // QEMU writes the file itself, as a PPM image
+    pub fn screendump(&mut self, path: &Path) -> Result<()> {
+        self.execute_raw("screendump", json!({ "filename": path }))
+            .map(|_| ())
+    }
+
+    // Presses the keys together and releases them again
+    pub fn send_keys(&mut self, combo: &KeyCombo) -> Result<()> {
+        let keys = combo
+            .get_keys()
+            .iter()
+            .map(|key| json!({ "type": "qcode", "data": key }))
+            .collect::<Vec<_>>();
+        self.execute_raw("send-key", json!({ "keys": keys }))
+            .map(|_| ())
+    }
+
+    // Sends a serial line break, which e.g. Linux reads as the start of a
+    // magic SysRq sequence
+    pub fn send_break(&mut self, chardev: &str) -> Result<()> {
+        self.execute_raw("chardev-send-break", json!({ "id": chardev }))
+            .map(|_| ())
+    }
+
+    // Restarts the guest right away without involving it, like the reset button
+    pub fn reset(&mut self) -> Result<()> {
+        self.execute("system_reset")
+    }
+
+    pub fn is_paused(&mut self) -> Result<bool> {
+        let ret = self.execute_raw("query-status", Value::Null)?;
+        Ok(ret["status"].as_str() == Some("paused"))
+    }
+
+    // Freezes the virtual CPUs, while QEMU and its devices keep running
+    pub fn pause(&mut self) -> Result<()> {
+        self.execute("stop")
+    }
+
+    pub fn resume(&mut self) -> Result<()> {
+        self.execute("cont")
+    }
+
+    pub fn get_balloon(&mut self) -> Result<u64> {
+        let ret = self.execute_raw("query-balloon", Value::Null)?;
+        Ok(ret["actual"].as_u64().unwrap_or_default())
+    }
+
+    pub fn get_cpu_count(&mut self) -> Result<u16> {
+        let ret = self.execute_raw("query-cpus-fast", Value::Null)?;
+        Ok(ret.as_array().map_or(0, |cpus| cpus.len() as u16))
+    }
+
+    // Hot-adds `count` vCPUs into the free slots QEMU offers. Fails without
+    // adding any when maxcpus leaves too few slots.
+    pub fn add_cpus(&mut self, instance: &str, count: u16) -> Result<()> {
+        let slots = self.execute_raw("query-hotpluggable-cpus", Value::Null)?;
+        let free_slots = slots
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            // A plugged slot names its CPU object
+            .filter(|(_, slot)| slot.get("qom-path").is_none())
+            .map(|(index, slot)| (index, slot.clone()))
+            .collect::<Vec<_>>();
+        if free_slots.len() < count as usize {
+            return Err(Error::CpuHotplugLimitReached(
+                instance.to_string(),
+                free_slots.len() as u16,
+            ));
+        }
+
+        for (index, slot) in free_slots.iter().take(count as usize) {
+            let mut arguments = slot["props"].clone();
+            arguments["driver"] = slot["type"].clone();
+            arguments["id"] = json!(format!("cpu{index}"));
+            self.execute_raw("device_add", arguments)?;
+        }
+        Ok(())
+    }
+
+    // The memory of the instance including hot-added DIMMs
+    pub fn get_memory_size(&mut self) -> Result<u64> {
+        let ret = self.execute_raw("query-memory-size-summary", Value::Null)?;
+        Ok(ret["base-memory"].as_u64().unwrap_or_default()
+            + ret["plugged-memory"].as_u64().unwrap_or_default())
+    }
+
+    // Hot-adds one DIMM of `size` bytes backed by fresh RAM. The size must be
+    // a multiple of `MEMORY_HOTPLUG_ALIGNMENT`.
+    pub fn add_memory(&mut self, size: u64) -> Result<()> {
+        let index = self
+            .execute_raw("query-memory-devices", Value::Null)?
+            .as_array()
+            .map_or(0, |devices| devices.len());
+        self.execute_raw(
+            "object-add",
+            json!({ "qom-type": "memory-backend-ram", "id": format!("mem{index}"), "size": size }),
+        )?;
+        let result = self.execute_raw(
+            "device_add",
+            json!({ "driver": "pc-dimm", "id": format!("dimm{index}"), "memdev": format!("mem{index}") }),
+        );
+        if result.is_err() {
+            // The backend is useless without its DIMM
+            self.execute_raw("object-del", json!({ "id": format!("mem{index}") }))
+                .ok();
+        }
+        result.map(|_| ())
+    }
+
+    // Attaches a data disk on the first free hotplug port. QEMU only says a
+    // port is taken by refusing the device, so every port gets a try. Any
+    // other refusal ends the attempt.
+    pub fn attach_disk(
+        &mut self,
+        instance: &str,
+        disk: &Disk,
+        path: &str,
+        settings: &DiskSettings,
+    ) -> Result<()> {
+        let id = disk.get_qemu_id();
+        let mut blockdev = json!({
+            "driver": disk.format,
+            "node-name": id,
+            "discard": settings.get_discard().as_str(),
+            "file": { "driver": "file", "filename": path },
+        });
+        if let Some(detect_zeroes) = settings.detect_zeroes {
+            blockdev["detect-zeroes"] = json!(detect_zeroes.as_str());
+        }
+        if let Some(cache) = settings.cache {
+            blockdev["cache"] =
+                json!({ "direct": cache.is_direct(), "no-flush": cache.is_no_flush() });
+        }
+        if let Some(aio) = settings.aio {
+            blockdev["file"]["aio"] = json!(aio.as_str());
+        }
+        // An instance started before the I/O thread was turned on has none,
+        // so its disk runs in the main loop until the next start
+        let iothread = settings.iothread && self.has_object(IOTHREAD_ID)?;
+        self.execute_raw("blockdev-add", blockdev)?;
+
+        let ports = self.execute_raw("qom-list", json!({ "path": "/machine/peripheral" }))?;
+        let ports = ports
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|child| child["name"].as_str())
+            .filter(|name| name.starts_with(HOTPLUG_PORT_PREFIX))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        for port in ports {
+            let mut device = json!({
+                "driver": "virtio-blk-pci",
+                "drive": id,
+                "id": id,
+                "serial": disk.name,
+                "bus": port,
+            });
+            if settings.cache.is_some_and(|cache| !cache.has_write_cache()) {
+                device["write-cache"] = json!("off");
+            }
+            if iothread {
+                device["iothread"] = json!(IOTHREAD_ID);
+            }
+            match self.execute_raw("device_add", device) {
+                Ok(_) => return Ok(()),
+                Err(Error::MonitorCommandFailed(message)) if Self::is_port_taken(&message) => {}
+                Err(error) => {
+                    self.execute_raw("blockdev-del", json!({ "node-name": id }))
+                        .ok();
+                    return Err(error);
+                }
+            }
+        }
+
+        self.execute_raw("blockdev-del", json!({ "node-name": id }))?;
+        Err(Error::NoFreeHotplugPort(instance.to_string()))
+    }
+
+    fn has_object(&mut self, id: &str) -> Result<bool> {
+        let objects = self.execute_raw("qom-list", json!({ "path": "/objects" }))?;
+        Ok(objects
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|child| child["name"].as_str() == Some(id)))
+    }
+
+    // A root port holds one device, and QEMU refuses a second one as a full
+    // bus or as a taken slot depending on the version
+    fn is_port_taken(message: &str) -> bool {
+        message.contains("is full") || message.contains("not available")
+    }
+
+    // Unplugging needs the guest to let go of the device first, which QEMU
+    // confirms with a DEVICE_DELETED event naming the device. Its inner
+    // virtio backend reports one as well, without a name.
+    pub fn detach_disk(&mut self, instance: &str, disk: &Disk) -> Result<()> {
+        let id = disk.get_qemu_id();
+        self.execute_raw("device_del", json!({ "id": id }))?;
+
+        let deadline = Instant::now() + UNPLUG_TIMEOUT;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::DiskBusy(instance.to_string(), disk.name.clone()));
+            }
+            if let Some(event) = self.next_event(deadline - now)?
+                && event.event == "DEVICE_DELETED"
+                && event.data.is_some_and(|data| data["device"] == id.as_str())
+            {
+                break;
+            }
+        }
+
+        self.execute_raw("blockdev-del", json!({ "node-name": id }))
+            .map(|_| ())
+    }
+
+    pub fn has_backup_bitmap(&mut self, device: &str) -> Result<bool> {
+        let blocks = self.execute_raw("query-block", Value::Null)?;
+        Ok(blocks
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|block| block["device"] == device)
+            .flat_map(|block| {
+                block["inserted"]["dirty-bitmaps"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+            })
+            .any(|bitmap| bitmap["name"] == BACKUP_BITMAP))
+    }
+
+    // Copies the drive into an existing image. A full backup starts the
+    // backup bitmap over in the same transaction, so no write slips between
+    // the two. An incremental backup copies what the bitmap marks, and QEMU
+    // clears the bitmap once the copy succeeded.
+    pub fn backup_drive(
+        &mut self,
+        instance: &str,
+        device: &str,
+        target: &str,
+        incremental: bool,
+    ) -> Result<()> {
+        self.execute_raw(
+            "blockdev-add",
+            json!({
+                "driver": "qcow2",
+                "node-name": BACKUP_NODE,
+                "file": { "driver": "file", "filename": target },
+            }),
+        )?;
+
+        let backup = json!({
+            "job-id": BACKUP_JOB,
+            "device": device,
+            "target": BACKUP_NODE,
+            "sync": if incremental { "incremental" } else { "full" },
+        });
+        let result = if incremental {
+            let mut backup = backup;
+            backup["bitmap"] = json!(BACKUP_BITMAP);
+            self.execute_raw("blockdev-backup", backup)
         } else {
-            Err(Error::HostfwdCommandFailed(output))
+            self.execute_raw(
+                "block-dirty-bitmap-remove",
+                json!({ "node": device, "name": BACKUP_BITMAP }),
+            )
+            .ok();
+            self.execute_raw(
+                "transaction",
+                json!({
+                    "actions": [
+                        {
+                            "type": "block-dirty-bitmap-add",
+                            "data": { "node": device, "name": BACKUP_BITMAP, "persistent": true },
+                        },
+                        { "type": "blockdev-backup", "data": backup },
+                    ],
+                }),
+            )
+        }
+        .and_then(|_| self.wait_for_job(instance, BACKUP_JOB));
+
+        let deleted = self.execute_raw("blockdev-del", json!({ "node-name": BACKUP_NODE }));
+        result?;
+        deleted.map(|_| ())
+    }
+
+    // A block job ends with an event that names it, and carries the error
+    // if it failed. A job that outlasts the timeout is cancelled, so the
+    // backup node can be deleted afterwards.
+    fn wait_for_job(&mut self, instance: &str, job: &str) -> Result<()> {
+        let deadline = Instant::now() + BACKUP_TIMEOUT;
+        loop {
+            if Instant::now() >= deadline {
+                self.execute_raw("block-job-cancel", json!({ "device": job, "force": true }))
+                    .ok();
+                self.wait_for_event("BLOCK_JOB_CANCELLED", UNPLUG_TIMEOUT)
+                    .ok();
+                return Err(Error::BackupFailed(
+                    instance.to_string(),
+                    format!(
+                        "the job did not finish within {} minutes",
+                        BACKUP_TIMEOUT.as_secs() / 60
+                    ),
+                ));
+            }
+            let Some(event) = self.next_event(QMP_TIMEOUT)? else {
+                continue;
+            };
+            let data = event.data.unwrap_or_default();
+            if data["device"] != job {
+                continue;
+            }
+            match event.event.as_str() {
+                "BLOCK_JOB_COMPLETED" => {
+                    return match data["error"].as_str() {
+                        Some(error) => {
+                            Err(Error::BackupFailed(instance.to_string(), error.to_string()))
+                        }
+                        None => Ok(()),
+                    };
+                }
+                "BLOCK_JOB_CANCELLED" => {
+                    return Err(Error::BackupFailed(
+                        instance.to_string(),
+                        "the job was cancelled".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Adds a range port by port, since QEMU knows no ranges. A range that
+    // fails halfway is taken back, so it is applied entirely or not at all.
+    pub fn add_hostfwd(&mut self, fwd: &PortForward) -> Result<()> {
+        let rules = fwd.expand();
+        for (applied, rule) in rules.iter().enumerate() {
+            if let Err(error) = self.add_single_hostfwd(rule) {
+                for rule in &rules[..applied] {
+                    self.remove_single_hostfwd(rule).ok();
+                }
+                return Err(error);
+            }
         }
+        Ok(())
     }
 
     pub fn remove_hostfwd(&mut self, fwd: &PortForward) -> Result<()> {
+        let rules = fwd.expand();
+        for (removed, rule) in rules.iter().enumerate() {
+            if let Err(error) = self.remove_single_hostfwd(rule) {
+                for rule in &rules[..removed] {
+                    self.add_single_hostfwd(rule).ok();
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_single_hostfwd(&mut self, rule: &PortForward) -> Result<()> {
+        let output =
+            self.run_hostfwd_command(&format!("hostfwd_add {NETDEV_ID} {}", rule.to_qemu()))?;
+        if !output.is_empty() {
+            return Err(Error::HostfwdCommandFailed(output));
+        }
+        Ok(())
+    }
+
+    fn remove_single_hostfwd(&mut self, rule: &PortForward) -> Result<()> {
         let rule = format!(
             "{}:{}:{}",
-            fwd.get_protocol(),
-            fwd.get_host_ip(),
-            fwd.get_host_port(),
+            rule.get_protocol(),
+            rule.get_host_ip(),
+            rule.get_host_port(),
         );
-        let output = self.run_hmp_command(&format!("hostfwd_remove {NETDEV_ID} {rule}"))?;
+        let output = self.run_hostfwd_command(&format!("hostfwd_remove {NETDEV_ID} {rule}"))?;
         if output.contains("not found") {
-            Err(Error::HostfwdCommandFailed(output))
-        } else {
-            Ok(())
+            return Err(Error::HostfwdCommandFailed(output));
         }
+        Ok(())
     }
 
-    // Drops the greeting, then negotiates capabilities, which QMP demands
-    // before it accepts anything else.
-    fn init(&mut self) -> Result<()> {
-        self.recv()?;
-        self.execute("qmp_capabilities")
+    // Runs any QMP command and returns what it replied, for callers that pass
+    // commands through without knowing them.
+    pub fn execute_raw(&mut self, cmd: &str, arguments: Value) -> Result<Value> {
+        match self.execute_with_args(cmd, arguments)? {
+            QmpMessage::Success { ret, .. } => Ok(ret),
+            QmpMessage::Error { error, .. } => Err(Error::MonitorCommandFailed(format!(
+                "{} ({})",
+                error.desc, error.class
+            ))),
+            _ => Ok(Value::Null),
+        }
     }
 
     // Runs an HMP command line through the QMP passthrough verb, for commands
     // with no native QMP equivalent. Returns the raw text QEMU printed, since
     // what counts as success differs per command.
-    fn run_hmp_command(&mut self, command_line: &str) -> Result<String> {
-        let response = self.execute_with_args(
+    pub fn run_hmp_command(&mut self, command_line: &str) -> Result<String> {
+        self.run_hmp_command_with(command_line, |error| {
+            Error::MonitorCommandFailed(format!("{} ({})", error.desc, error.class))
+        })
+    }
+
+    // Port forwarding reports its own error, which callers tell apart
+    fn run_hostfwd_command(&mut self, command_line: &str) -> Result<String> {
+        self.run_hmp_command_with(command_line, |error| {
+            Error::HostfwdCommandFailed(error.desc)
+        })
+    }
+
+    fn run_hmp_command_with(
+        &mut self,
+        command_line: &str,
+        to_error: impl FnOnce(QmpError) -> Error,
+    ) -> Result<String> {
+        match self.execute_with_args(
             "human-monitor-command",
             json!({ "command-line": command_line }),
-        )?;
-        match response {
+        )? {
             QmpMessage::Success { ret, .. } => {
                 Ok(ret.as_str().unwrap_or_default().trim().to_string())
             }
-            QmpMessage::Error { error, .. } => Err(Error::HostfwdCommandFailed(error.desc)),
+            QmpMessage::Error { error, .. } => Err(to_error(error)),
             _ => Ok(String::new()),
         }
     }
 
+    // Drops the greeting, then negotiates capabilities, which QMP demands
+    // before it accepts anything else.
+    fn init(&mut self) -> Result<()> {
+        self.recv()?;
+        self.execute("qmp_capabilities")
+    }
+
     fn send(&mut self, message: &QmpMessage) -> Result<()> {
         let request = serde_json::to_string(message).map_err(Error::from)?;
         let stream = self.stream.get_mut();
@@ -98,10 +579,40 @@ impl QemuMonitorClient {
     }
 
     fn recv(&mut self) -> Result<QmpMessage> {
-        let mut response = String::new();
-        self.stream.read_line(&mut response).map_err(Error::from)?;
+        loop {
+            if let Some(line) = self.read_line().map_err(Error::from)? {
+                return serde_json::from_slice(&line).map_err(Error::from);
+            }
+        }
+    }
+
+    // Like recv, but a read timeout yields None, so callers can poll for
+    // messages QEMU sends on its own, such as events.
+    fn try_recv(&mut self) -> Result<Option<QmpMessage>> {
+        match self.read_line() {
+            Ok(Some(line)) => serde_json::from_slice(&line).map(Some).map_err(Error::from),
+            Ok(None) => Ok(None),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(Error::from(e)),
+        }
+    }
 
-        serde_json::from_str(&response).map_err(Error::from)
+    // Returns the next complete line, or None while it is still arriving.
+    // What a timed out read got so far stays in `line` for the next call.
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.stream.read_until(b'\n', &mut self.line)? {
+            // QEMU closes the monitor as it exits
+            0 => Err(io::ErrorKind::UnexpectedEof.into()),
+            _ if self.line.ends_with(b"\n") => Ok(Some(std::mem::take(&mut self.line))),
+            _ => Ok(None),
+        }
     }
 
     fn execute_with_args(&mut self, cmd: &str, arguments: Value) -> Result<QmpMessage> {
@@ -114,16 +625,24 @@ impl QemuMonitorClient {
             arguments,
         })?;
 
-        // Events and replies to earlier requests can arrive first, so skip
-        // everything until this request's own id comes back.
+        // Events and replies to earlier requests can arrive first. Keep the
+        // events for next_event and skip everything else until this
+        // request's own id comes back.
+        let deadline = Instant::now() + REPLY_TIMEOUT;
         loop {
-            let response = self.recv()?;
-            match &response {
-                QmpMessage::Success { id, .. } | QmpMessage::Error { id, .. }
+            if Instant::now() >= deadline {
+                return Err(Error::from(io::Error::from(io::ErrorKind::TimedOut)));
+            }
+            let Some(response) = self.try_recv()? else {
+                continue;
+            };
+            match response {
+                QmpMessage::Success { ref id, .. } | QmpMessage::Error { ref id, .. }
                     if *id == request_id =>
                 {
                     return Ok(response);
                 }
+                QmpMessage::Event(event) => self.events.push_back(event),
                 _ => {}
             }
         }
@@ -133,3 +652,58 @@ impl QemuMonitorClient {
         self.execute_with_args(cmd, Value::Null).map(|_| ())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    // Hands out one chunk per read and times out between them, like a
+    // socket that QEMU writes a message to in pieces
+    struct ChunkedStream {
+        chunks: VecDeque<Option<&'static [u8]>>,
+    }
+
+    impl Read for ChunkedStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.chunks.pop_front() {
+                Some(Some(chunk)) => {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    Ok(chunk.len())
+                }
+                Some(None) => Err(io::ErrorKind::WouldBlock.into()),
+                None => Ok(0),
+            }
+        }
+    }
+
+    impl Write for ChunkedStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_next_event_keeps_a_line_cut_by_a_timeout() {
+        let mut client = QemuMonitorClient {
+            counter: 0,
+            stream: BufReader::new(Box::new(ChunkedStream {
+                chunks: VecDeque::from([
+                    Some(&b"{\"event\": \"SHUT"[..]),
+                    None,
+                    Some(&b"DOWN\", \"timestamp\": {\"seconds\": 1, \"microseconds\": 0}}\n"[..]),
+                ]),
+            })),
+            line: Vec::new(),
+            events: VecDeque::new(),
+        };
+
+        let event = client.next_event(Duration::from_secs(1)).unwrap();
+
+        assert_eq!(event.map(|event| event.event), Some("SHUTDOWN".to_string()));
+    }
+}
@@ -1,16 +1,32 @@
 use std::path::Path;
 
 use crate::error::{Error, Result};
-use crate::models::{Arch, PortForward};
+use crate::models::{Arch, Disk, DiskBus, DiskSettings, PortForward};
 use crate::platform::System;
-use crate::qemu::QemuPathBuilder;
-use crate::util::SystemCommand;
+use crate::qemu::{DISK_SECRET_ID, QemuPathBuilder};
+use crate::util::{self, SystemCommand};
 
 pub const NETDEV_ID: &str = "net0";
 pub const SOFTWARE_ACCEL: &str = "tcg";
+// DIMM slots for memory hotplug, each hot-add takes one
+pub const MEMORY_SLOTS: u16 = 8;
+// Root ports are named by this prefix and their index
+pub const HOTPLUG_PORT_PREFIX: &str = "hotplug";
+/// Root ports a start leaves free beyond the disks it boots with, which
+/// bounds how many disks can be hot-added before the next restart
+pub const SPARE_HOTPLUG_PORTS: usize = 4;
+// The one I/O thread all disks share
+pub const IOTHREAD_ID: &str = "io0";
+pub const SYSTEM_DRIVE_ID: &str = "system";
+pub const CDROM_DRIVE_ID: &str = "cdrom";
+// VNC display numbers count from this port
+pub const VNC_BASE_PORT: u16 = 5900;
 
 pub struct QemuSystem {
+    arch: Arch,
     command: SystemCommand,
+    // An installer CD-ROM boots before the system disk
+    system_bootindex: u8,
 }
 
 impl QemuSystem {
@@ -34,7 +50,7 @@ impl QemuSystem {
 
         // Only boot disk
         command.arg("-boot").arg("c");
-        // Disable display
+        // No window on the host, a display goes through VNC
         command.arg("-display").arg("none");
         // Do not create emulated default devices (NIC, VGA, serial, parallel,
         // floppy, CD-ROM, monitor). Every device cubic needs is declared
@@ -49,7 +65,17 @@ impl QemuSystem {
         // Allow memory reclaim via virtio-balloon.
         command.arg("-device").arg("virtio-balloon-pci");
 
-        Ok(QemuSystem { command })
+        Ok(QemuSystem {
+            arch,
+            command,
+            system_bootindex: 0,
+        })
+    }
+
+    // vCPU hotplug rides on ACPI CPU hotplug, which the virt machine of
+    // arm64 does not offer. Memory hotplug works on both machines.
+    pub fn supports_cpu_hotplug(arch: Arch) -> bool {
+        arch == Arch::AMD64
     }
 
     // The CPU model follows from the accelerator. Windows takes named models
@@ -74,12 +100,33 @@ impl QemuSystem {
             .arg(accel);
     }
 
-    pub fn set_cpus(&mut self, cpus: u16) {
-        self.command.arg("-smp").arg(cpus.to_string());
+    // Boots with `cpus` vCPUs and leaves room to hot-add up to `max_cpus`
+    pub fn set_cpus(&mut self, cpus: u16, max_cpus: u16) {
+        if Self::supports_cpu_hotplug(self.arch) {
+            let max_cpus = max_cpus.max(cpus);
+            self.command
+                .arg("-smp")
+                .arg(format!("cpus={cpus},maxcpus={max_cpus}"));
+        } else {
+            self.command.arg("-smp").arg(cpus.to_string());
+        }
+    }
+
+    // Boots with `memory` bytes and leaves room to hot-add DIMMs up to
+    // `max_memory` bytes
+    pub fn set_memory(&mut self, memory: u64, max_memory: u64) {
+        let max_memory = max_memory.max(memory);
+        self.command.arg("-m").arg(format!(
+            "size={memory}B,slots={MEMORY_SLOTS},maxmem={max_memory}B"
+        ));
     }
 
-    pub fn set_memory(&mut self, memory: u64) {
-        self.command.arg("-m").arg(format!("{}B", memory));
+    // Lets the guest report freed pages to the host and shrink the balloon on
+    // its own under memory pressure, so idle guests give memory back.
+    pub fn set_memory_reclaim(&mut self) {
+        self.command
+            .args(["-global", "virtio-balloon-pci.free-page-reporting=on"])
+            .args(["-global", "virtio-balloon-pci.deflate-on-oom=on"]);
     }
 
     pub fn set_monitor(&mut self, port: u16, instance_dir: &Path) {
@@ -98,7 +145,22 @@ impl QemuSystem {
             .args(["-mon", "chardev=qmp,mode=control,pretty=off"]);
     }
 
-    pub fn set_console(&mut self, port: u16, instance_dir: &Path) {
+    // Adds a second monitor for event listeners. It reuses the TLS
+    // credentials of set_monitor, which must be called as well.
+    pub fn set_event_monitor(&mut self, port: u16) {
+        self.command
+            .args([
+                "-chardev",
+                &format!(
+                    "socket,id=qmp-events,host=127.0.0.1,port={port},server=on,wait=off,tls-creds=qmp-tls"
+                ),
+            ])
+            .args(["-mon", "chardev=qmp-events,mode=control,pretty=off"]);
+    }
+
+    // QEMU also writes everything the guest prints to the log file, whether
+    // a client is connected or not
+    pub fn set_console(&mut self, port: u16, instance_dir: &Path, log_file: &Path) {
         let dir = instance_dir.display();
         self.command
             .args([
@@ -107,15 +169,42 @@ impl QemuSystem {
             ])
             .arg("-chardev")
             .arg(format!(
-                "socket,host=127.0.0.1,port={port},server=on,wait=off,id=console,tls-creds=con-tls"
+                "socket,host=127.0.0.1,port={port},server=on,wait=off,id=console,tls-creds=con-tls,logfile={}",
+                log_file.display()
             ))
             .arg("-serial")
             .arg("chardev:console");
     }
 
+    // A graphics card with a USB keyboard and tablet, shown by a VNC server
+    // on localhost. Viewers verify the server against the CA of the instance
+    // and log in with the password `cubic display` sets. Until then the
+    // server refuses every login.
+    pub fn set_display(&mut self, port: u16, instance_dir: &Path) {
+        let gpu = match self.arch {
+            Arch::AMD64 => "VGA",
+            Arch::ARM64 => "virtio-gpu-pci",
+        };
+        let dir = instance_dir.display();
+        self.command
+            .args(["-device", gpu])
+            .args(["-device", "qemu-xhci,id=xhci"])
+            .args(["-device", "usb-kbd,bus=xhci.0"])
+            .args(["-device", "usb-tablet,bus=xhci.0"])
+            .args([
+                "-object",
+                &format!("tls-creds-x509,id=vnc-tls,dir={dir},endpoint=server,verify-peer=no"),
+            ])
+            .arg("-vnc")
+            .arg(format!(
+                "127.0.0.1:{},tls-creds=vnc-tls,password=on",
+                port.saturating_sub(VNC_BASE_PORT)
+            ));
+    }
+
     pub fn set_network(&mut self, hostfwd: &[PortForward], ssh_port: u16, isolate: bool) {
         let mut hostfwd_options = String::new();
-        for fwd in hostfwd {
+        for fwd in hostfwd.iter().flat_map(PortForward::expand) {
             hostfwd_options.push_str(",hostfwd=");
             hostfwd_options.push_str(&fwd.to_qemu());
         }
@@ -136,6 +225,138 @@ impl QemuSystem {
             .arg(format!("if=virtio,format={format},file={path}"));
     }
 
+    pub fn set_iothread(&mut self) {
+        self.command
+            .arg("-object")
+            .arg(format!("iothread,id={IOTHREAD_ID}"));
+    }
+
+    // An encrypted disk gets its passphrase from the file, so --verbose only
+    // shows the path. The system disk boots first, whatever bus it sits on,
+    // unless an installer CD-ROM comes before it.
+    pub fn add_system_drive(
+        &mut self,
+        path: &str,
+        secret_file: Option<&str>,
+        settings: &DiskSettings,
+    ) {
+        let mut drive = format!(
+            "if=none,id={SYSTEM_DRIVE_ID},format=qcow2,file={path},{}",
+            settings.get_drive_options()
+        );
+        if let Some(secret_file) = secret_file {
+            self.command
+                .arg("-object")
+                .arg(format!("secret,id={DISK_SECRET_ID},file={secret_file}"));
+            drive.push_str(&format!(",encrypt.key-secret={DISK_SECRET_ID}"));
+        }
+        self.command.arg("-drive").arg(drive);
+
+        let iothread = Self::get_iothread_option(settings);
+        match settings.get_bus() {
+            DiskBus::VirtioBlk => self.command.arg("-device").arg(format!(
+                "virtio-blk-pci,drive={SYSTEM_DRIVE_ID}{iothread},bootindex={}",
+                self.system_bootindex
+            )),
+            DiskBus::VirtioScsi => self
+                .command
+                .arg("-device")
+                .arg(format!("virtio-scsi-pci,id=scsi0{iothread}"))
+                .arg("-device")
+                .arg(format!(
+                    "scsi-hd,drive={SYSTEM_DRIVE_ID},bus=scsi0.0,bootindex={}",
+                    self.system_bootindex
+                )),
+        };
+    }
+
+    // The q35 machine has an AHCI controller built in, the virt machine of
+    // arm64 has no bus for a CD-ROM but the SCSI controller added here. Call
+    // it before add_system_drive, which then boots second.
+    pub fn add_cdrom(&mut self, path: &str) {
+        self.command.arg("-drive").arg(format!(
+            "if=none,id={CDROM_DRIVE_ID},media=cdrom,format=raw,readonly=on,file={path}"
+        ));
+        match self.arch {
+            Arch::AMD64 => self
+                .command
+                .arg("-device")
+                .arg(format!("ide-cd,drive={CDROM_DRIVE_ID},bootindex=0")),
+            Arch::ARM64 => self
+                .command
+                .arg("-device")
+                .arg("virtio-scsi-pci,id=scsi-cd")
+                .arg("-device")
+                .arg(format!(
+                    "scsi-cd,drive={CDROM_DRIVE_ID},bus=scsi-cd.0,bootindex=0"
+                )),
+        };
+        self.system_bootindex = 1;
+    }
+
+    fn get_iothread_option(settings: &DiskSettings) -> String {
+        if settings.iothread {
+            format!(",iothread={IOTHREAD_ID}")
+        } else {
+            String::new()
+        }
+    }
+
+    // -blockdev knows no cache modes, only the flags they stand for
+    fn get_blockdev_options(settings: &DiskSettings) -> String {
+        let mut options = format!("discard={}", settings.get_discard().as_str());
+        if let Some(detect_zeroes) = settings.detect_zeroes {
+            options.push_str(&format!(",detect-zeroes={}", detect_zeroes.as_str()));
+        }
+        if let Some(cache) = settings.cache {
+            options.push_str(&format!(
+                ",cache.direct={},cache.no-flush={}",
+                util::to_on_off(cache.is_direct()),
+                util::to_on_off(cache.is_no_flush())
+            ));
+        }
+        if let Some(aio) = settings.aio {
+            options.push_str(&format!(",file.aio={}", aio.as_str()));
+        }
+        options
+    }
+
+    // The root bus of q35 and virt takes no hotplug, so every device that may
+    // come or go while running sits behind a root port of its own.
+    pub fn add_hotplug_ports(&mut self, count: usize) {
+        for index in 0..count {
+            self.command.arg("-device").arg(format!(
+                "pcie-root-port,id={HOTPLUG_PORT_PREFIX}{index},chassis={}",
+                index + 1
+            ));
+        }
+    }
+
+    // Adds a data disk on the given hotplug port, so it can be detached
+    // while running. The serial shows up as /dev/disk/by-id/virtio-<name>.
+    // Data disks stay on virtio-blk, since each one needs a port to be
+    // hot-plugged into
+    pub fn add_disk(&mut self, disk: &Disk, path: &str, port: usize, settings: &DiskSettings) {
+        let id = disk.get_qemu_id();
+        let write_cache = match settings.cache {
+            Some(cache) if !cache.has_write_cache() => ",write-cache=off",
+            _ => "",
+        };
+        self.command
+            .arg("-blockdev")
+            .arg(format!(
+                "driver={},node-name={id},file.driver=file,file.filename={path},{}",
+                disk.format,
+                Self::get_blockdev_options(settings)
+            ))
+            .arg("-device")
+            .arg(format!(
+                "virtio-blk-pci,drive={id},id={id},serial={},bus={HOTPLUG_PORT_PREFIX}{port}{write_cache}{}",
+                disk.name,
+                Self::get_iothread_option(settings)
+            ));
+    }
+
     pub fn set_qemu_args(&mut self, args: &str) {
         for arg in args.split(' ') {
             self.command.arg(arg);
@@ -148,6 +369,59 @@ impl QemuSystem {
             .arg(format!("if=pflash,readonly=on,file={}", path.display()));
     }
 
+    /// Maps the variable store next to the firmware code, so boot entries and
+    /// settings survive a restart
+    pub fn set_nvram(&mut self, path: &Path) {
+        self.command
+            .arg("-drive")
+            .arg(format!("if=pflash,format=raw,file={}", path.display()));
+    }
+
+    /// Keeps the guest from writing the variable store around the firmware.
+    /// On amd64 only code in SMM may write the secure flash.
+    pub fn set_secure_boot(&mut self) {
+        if self.arch == Arch::AMD64 {
+            self.command.arg("-machine").arg("smm=on");
+            self.command
+                .arg("-global")
+                .arg("driver=cfi.pflash01,property=secure,value=on");
+        }
+    }
+
+    /// Attaches the TPM that swtpm serves on the socket. The virt machine of
+    /// arm64 has no ISA bus, so it takes the sysbus variant of the device.
+    pub fn set_tpm(&mut self, socket: &Path) {
+        let device = match self.arch {
+            Arch::AMD64 => "tpm-tis",
+            Arch::ARM64 => "tpm-tis-device",
+        };
+        self.command
+            .arg("-chardev")
+            .arg(format!("socket,id=chrtpm,path={}", socket.display()))
+            .arg("-tpmdev")
+            .arg("emulator,id=tpm0,chardev=chrtpm")
+            .arg("-device")
+            .arg(format!("{device},tpmdev=tpm0"));
+    }
+
+    /// Serial console a directly booted kernel should log to
+    pub fn get_kernel_console(arch: Arch) -> &'static str {
+        match arch {
+            Arch::AMD64 => "ttyS0",
+            Arch::ARM64 => "ttyAMA0",
+        }
+    }
+
+    pub fn set_kernel(&mut self, kernel: &str, initrd: Option<&str>, append: Option<&str>) {
+        self.command.arg("-kernel").arg(kernel);
+        if let Some(initrd) = initrd {
+            self.command.arg("-initrd").arg(initrd);
+        }
+        if let Some(append) = append {
+            self.command.arg("-append").arg(append);
+        }
+    }
+
     pub fn set_module_dir(&mut self, dir: &Path) {
         self.command.set_env("QEMU_MODULE_DIR", dir);
     }
@@ -178,6 +452,7 @@ impl QemuSystem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::{DetectZeroes, DiskAio, DiskCache};
     use crate::platform::SystemMock;
 
     #[test]
@@ -223,6 +498,235 @@ mod tests {
         assert!(qemu.command.get_command().contains("virtio-balloon-pci"));
     }
 
+    #[test]
+    fn test_set_memory_reclaim_configures_the_balloon() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_memory_reclaim();
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains("-global virtio-balloon-pci.free-page-reporting=on"));
+        assert!(command.contains("-global virtio-balloon-pci.deflate-on-oom=on"));
+    }
+
+    #[test]
+    fn test_add_system_drive_unmaps_trimmed_blocks() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.add_system_drive("/data/machine.img", None, &DiskSettings::default());
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains(
+            "-drive if=none,id=system,format=qcow2,file=/data/machine.img,discard=unmap"
+        ));
+        assert!(command.contains("-device virtio-blk-pci,drive=system,bootindex=0"));
+    }
+
+    #[test]
+    fn test_set_network_expands_port_ranges() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_network(&["8000-8001:80-81".parse().unwrap()], 2222, false);
+
+        assert!(qemu.command.get_command().contains(
+            "hostfwd=tcp:127.0.0.1:2222-:22,hostfwd=tcp:127.0.0.1:8000-:80,\
+hostfwd=tcp:127.0.0.1:8001-:81"
+        ));
+    }
+
+    #[test]
+    fn test_set_console_logs_to_file() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_console(
+            8002,
+            Path::new("/data/machines/test"),
+            Path::new("/data/machines/test/console.log"),
+        );
+
+        assert!(qemu.command.get_command().contains(
+            "-chardev socket,host=127.0.0.1,port=8002,server=on,wait=off,id=console,\
+tls-creds=con-tls,logfile=/data/machines/test/console.log -serial chardev:console"
+        ));
+    }
+
+    #[test]
+    fn test_set_display_serves_vnc_with_tls_and_password() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::ARM64).unwrap();
+        qemu.set_display(45901, Path::new("/data/machines/test"));
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains("-device virtio-gpu-pci -device qemu-xhci,id=xhci"));
+        assert!(command.contains(
+            "-object tls-creds-x509,id=vnc-tls,dir=/data/machines/test,endpoint=server,verify-peer=no \
+-vnc 127.0.0.1:40001,tls-creds=vnc-tls,password=on"
+        ));
+    }
+
+    #[test]
+    fn test_add_cdrom_boots_before_the_system_drive() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.add_cdrom("/srv/installer.iso");
+        qemu.add_system_drive("/data/machine.img", None, &DiskSettings::default());
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains(
+            "-drive if=none,id=cdrom,media=cdrom,format=raw,readonly=on,file=/srv/installer.iso \
+-device ide-cd,drive=cdrom,bootindex=0"
+        ));
+        assert!(command.contains("-device virtio-blk-pci,drive=system,bootindex=1"));
+    }
+
+    #[test]
+    fn test_add_cdrom_on_arm64_adds_a_scsi_controller() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::ARM64).unwrap();
+        qemu.add_cdrom("/srv/installer.iso");
+
+        assert!(qemu.command.get_command().contains(
+            "-device virtio-scsi-pci,id=scsi-cd -device scsi-cd,drive=cdrom,bus=scsi-cd.0,bootindex=0"
+        ));
+    }
+
+    #[test]
+    fn test_add_system_drive_passes_the_secret_by_file() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.add_system_drive(
+            "/data/machine.img",
+            Some("/data/disk_secret"),
+            &DiskSettings::default(),
+        );
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains("-object secret,id=disk-secret,file=/data/disk_secret"));
+        assert!(command.contains(
+            "-drive if=none,id=system,format=qcow2,file=/data/machine.img,discard=unmap,encrypt.key-secret=disk-secret"
+        ));
+    }
+
+    #[test]
+    fn test_add_system_drive_on_virtio_scsi_with_iothread() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.add_system_drive(
+            "/data/machine.img",
+            None,
+            &DiskSettings {
+                cache: Some(DiskCache::None),
+                aio: Some(DiskAio::IoUring),
+                iothread: true,
+                bus: Some(DiskBus::VirtioScsi),
+                ..DiskSettings::default()
+            },
+        );
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains(
+            "-drive if=none,id=system,format=qcow2,file=/data/machine.img,cache=none,aio=io_uring,discard=unmap"
+        ));
+        assert!(command.contains("-device virtio-scsi-pci,id=scsi0,iothread=io0"));
+        assert!(command.contains("-device scsi-hd,drive=system,bus=scsi0.0,bootindex=0"));
+    }
+
+    #[test]
+    fn test_add_disk_maps_the_cache_mode_to_blockdev_flags() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        let disk = Disk {
+            name: "data".to_string(),
+            format: "qcow2".to_string(),
+            file: None,
+        };
+        qemu.add_disk(
+            &disk,
+            "/data/disk-data.qcow2",
+            0,
+            &DiskSettings {
+                cache: Some(DiskCache::Directsync),
+                aio: Some(DiskAio::Native),
+                detect_zeroes: Some(DetectZeroes::On),
+                iothread: true,
+                ..DiskSettings::default()
+            },
+        );
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains(
+            "file.filename=/data/disk-data.qcow2,discard=unmap,detect-zeroes=on,cache.direct=on,cache.no-flush=off,file.aio=native"
+        ));
+        assert!(command.contains("bus=hotplug0,write-cache=off,iothread=io0"));
+    }
+
+    #[test]
+    fn test_set_cpus_leaves_room_for_hotplug_on_amd64() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_cpus(2, 8);
+
+        assert!(qemu.command.get_command().contains("-smp cpus=2,maxcpus=8"));
+    }
+
+    #[test]
+    fn test_set_cpus_skips_hotplug_on_arm64() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::ARM64).unwrap();
+        qemu.set_cpus(2, 8);
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains("-smp 2"));
+        assert!(!command.contains("maxcpus"));
+    }
+
+    #[test]
+    fn test_set_cpus_never_caps_below_the_boot_count() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_cpus(4, 2);
+
+        assert!(qemu.command.get_command().contains("-smp cpus=4,maxcpus=4"));
+    }
+
+    #[test]
+    fn test_set_memory_leaves_room_for_hotplug() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::ARM64).unwrap();
+        qemu.set_memory(1024, 4096);
+
+        assert!(
+            qemu.command
+                .get_command()
+                .contains("-m size=1024B,slots=8,maxmem=4096B")
+        );
+    }
+
+    #[test]
+    fn test_add_hotplug_ports_gives_every_port_its_own_chassis() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.add_hotplug_ports(2);
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains("-device pcie-root-port,id=hotplug0,chassis=1"));
+        assert!(command.contains("-device pcie-root-port,id=hotplug1,chassis=2"));
+    }
+
+    #[test]
+    fn test_add_disk_puts_the_disk_on_its_port() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        let disk = Disk {
+            name: "data".to_string(),
+            format: "qcow2".to_string(),
+            file: None,
+        };
+        qemu.add_disk(&disk, "/data/disk-data.qcow2", 1, &DiskSettings::default());
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains(
+            "-blockdev driver=qcow2,node-name=disk-data,file.driver=file,file.filename=/data/disk-data.qcow2,discard=unmap"
+        ));
+        assert!(command.contains(
+            "-device virtio-blk-pci,drive=disk-data,id=disk-data,serial=data,bus=hotplug1"
+        ));
+    }
+
     #[test]
     fn test_build_command_names_the_binary_of_the_arch() {
         let command = QemuSystem::from(&SystemMock::new(), Arch::AMD64)
@@ -251,6 +755,81 @@ mod tests {
         assert_eq!(command.matches("-cpu").count(), 1);
     }
 
+    #[test]
+    fn test_set_event_monitor_shares_the_monitor_tls_credentials() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_monitor(8001, Path::new("/data/test"));
+        qemu.set_event_monitor(8002);
+
+        let command = qemu.command.get_command();
+
+        assert_eq!(command.matches("tls-creds-x509,id=qmp-tls").count(), 1);
+        assert!(command.contains("port=8002,server=on,wait=off,tls-creds=qmp-tls"));
+        assert!(command.contains("-mon chardev=qmp-events,mode=control"));
+    }
+
+    #[test]
+    fn test_set_kernel_boots_directly() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_kernel(
+            "/data/test/vmlinuz",
+            Some("/data/test/initrd.img"),
+            Some("root=LABEL=cubic-root rw"),
+        );
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains("-kernel /data/test/vmlinuz -initrd /data/test/initrd.img"));
+        assert!(command.contains("-append root=LABEL=cubic-root rw"));
+    }
+
+    #[test]
+    fn test_set_nvram_maps_writable_vars_after_the_code() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_firmware(Path::new("/usr/share/OVMF/OVMF_CODE_4M.fd"));
+        qemu.set_nvram(Path::new("/data/test/nvram.fd"));
+
+        let command = qemu.command.get_command();
+
+        assert!(command.contains(
+            "-drive if=pflash,readonly=on,file=/usr/share/OVMF/OVMF_CODE_4M.fd -drive if=pflash,format=raw,file=/data/test/nvram.fd"
+        ));
+    }
+
+    #[test]
+    fn test_set_secure_boot_enables_smm_on_amd64() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::AMD64).unwrap();
+        qemu.set_secure_boot();
+
+        let command = qemu.command.get_command();
+
+        assert!(
+            command
+                .contains("-machine smm=on -global driver=cfi.pflash01,property=secure,value=on")
+        );
+    }
+
+    #[test]
+    fn test_set_secure_boot_needs_no_smm_on_arm64() {
+        let mut qemu = QemuSystem::from(&SystemMock::new(), Arch::ARM64).unwrap();
+        qemu.set_secure_boot();
+
+        assert!(!qemu.command.get_command().contains("smm=on"));
+    }
+
+    #[test]
+    fn test_set_tpm_picks_the_device_of_the_arch() {
+        for (arch, device) in [(Arch::AMD64, "tpm-tis"), (Arch::ARM64, "tpm-tis-device")] {
+            let mut qemu = QemuSystem::from(&SystemMock::new(), arch).unwrap();
+            qemu.set_tpm(Path::new("/data/test/tpm/swtpm.sock"));
+
+            assert!(qemu.command.get_command().contains(&format!(
+                "-chardev socket,id=chrtpm,path=/data/test/tpm/swtpm.sock \
+-tpmdev emulator,id=tpm0,chardev=chrtpm -device {device},tpmdev=tpm0"
+            )));
+        }
+    }
+
     #[test]
     fn test_map_error_passes_other_errors_through() {
         assert!(matches!(
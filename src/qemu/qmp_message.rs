@@ -1,12 +1,30 @@
+use crate::util;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct QmpTimestamp {
     seconds: i64,
     microseconds: i64,
 }
 
+impl fmt::Display for QmpTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Prints the microseconds as fraction of the seconds field
+        let utc = util::format_utc(self.seconds);
+        write!(f, "{}.{:06}Z", utc.trim_end_matches('Z'), self.microseconds)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QmpEvent {
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    pub timestamp: QmpTimestamp,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QmpError {
     pub class: String,
@@ -29,12 +47,7 @@ pub enum QmpMessage {
         arguments: Value,
     },
 
-    Event {
-        event: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        data: Option<Value>,
-        timestamp: QmpTimestamp,
-    },
+    Event(QmpEvent),
 
     Success {
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -0,0 +1,155 @@
+use crate::error::{Error, Result};
+use crate::platform::System;
+use crate::qemu::QemuPathBuilder;
+use crate::util::SystemCommand;
+use std::path::{Path, PathBuf};
+
+const SOCKET_FILE: &str = "swtpm.sock";
+const PID_FILE: &str = "swtpm.pid";
+
+/// Software TPM 2.0 of an instance, which keeps its state in a directory of
+/// its own and talks to QEMU over a control socket in there
+pub struct Swtpm<'a> {
+    system: &'a dyn System,
+    state_dir: PathBuf,
+}
+
+impl<'a> Swtpm<'a> {
+    pub fn new(system: &'a dyn System, state_dir: &Path) -> Self {
+        Self {
+            system,
+            state_dir: state_dir.to_path_buf(),
+        }
+    }
+
+    pub fn get_socket(&self) -> PathBuf {
+        self.state_dir.join(SOCKET_FILE)
+    }
+
+    fn get_pid_file(&self) -> PathBuf {
+        self.state_dir.join(PID_FILE)
+    }
+
+    /// Starts swtpm in the background. It returns once the socket is ready,
+    /// and terminates on its own when QEMU closes the connection.
+    pub fn start(&self) -> Result<()> {
+        // A leftover of a crashed instance would hold the socket
+        self.stop()?;
+        self.system.create_dir(&self.state_dir)?;
+
+        let mut command = SystemCommand::new("swtpm");
+        command
+            .set_env("PATH", QemuPathBuilder::new(self.system).build())
+            .arg("socket")
+            .arg("--tpm2")
+            .arg("--tpmstate")
+            .arg(format!("dir={}", self.state_dir.display()))
+            .arg("--ctrl")
+            .arg(format!("type=unixio,path={}", self.get_socket().display()))
+            .arg("--pid")
+            .arg(format!("file={}", self.get_pid_file().display()))
+            .arg("--terminate")
+            .arg("--daemon");
+
+        self.system
+            .run_command(&command)
+            .map(|_| ())
+            .map_err(|error| match error {
+                Error::SystemCommandNotFound(_) => Error::SwtpmNotFound,
+                other => other,
+            })
+    }
+
+    /// Ends swtpm, if it still runs
+    pub fn stop(&self) -> Result<()> {
+        let pid_file = self.get_pid_file();
+        let pid = self
+            .system
+            .read_file_to_string(&pid_file)
+            .ok()
+            .and_then(|pid| pid.trim().parse::<u64>().ok());
+        if let Some(pid) = pid
+            && self.system.exists_process(pid)
+        {
+            match self.system.kill_process(pid) {
+                Err(Error::ProcessNotFound(_)) | Ok(_) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        if self.system.exists_path(&pid_file) {
+            self.system.remove_file(&pid_file)?;
+        }
+        Ok(())
+    }
+
+    /// Files that make up the state of the TPM, without the socket and the
+    /// pid file of a running swtpm
+    pub fn get_state_files(&self) -> Vec<PathBuf> {
+        self.system
+            .read_dir(&self.state_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name != SOCKET_FILE && name != PID_FILE)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{FileSystem, SystemMock};
+
+    const START_COMMAND: &str = "swtpm socket --tpm2 --tpmstate dir=/data/test/tpm \
+--ctrl type=unixio,path=/data/test/tpm/swtpm.sock --pid file=/data/test/tpm/swtpm.pid \
+--terminate --daemon";
+
+    #[test]
+    fn test_start_runs_swtpm_with_state_in_the_instance_dir() {
+        let system = SystemMock::new().add_command_output(START_COMMAND, b"");
+
+        Swtpm::new(&system, Path::new("/data/test/tpm"))
+            .start()
+            .unwrap();
+
+        assert_eq!(system.get_executed_commands(), [START_COMMAND]);
+    }
+
+    #[test]
+    fn test_start_reports_missing_swtpm() {
+        let system = SystemMock::new();
+
+        let result = Swtpm::new(&system, Path::new("/data/test/tpm")).start();
+
+        assert!(matches!(result, Err(Error::SwtpmNotFound)));
+    }
+
+    #[test]
+    fn test_stop_kills_the_running_swtpm() {
+        let system = SystemMock::new()
+            .add_file("/data/test/tpm/swtpm.pid", b"4242\n")
+            .add_process(4242);
+
+        Swtpm::new(&system, Path::new("/data/test/tpm"))
+            .stop()
+            .unwrap();
+
+        assert_eq!(system.get_killed_processes(), [4242]);
+        assert!(!system.exists_path(Path::new("/data/test/tpm/swtpm.pid")));
+    }
+
+    #[test]
+    fn test_get_state_files_skips_socket_and_pid_file() {
+        let system = SystemMock::new()
+            .add_file("/data/test/tpm/tpm2-00.permall", b"")
+            .add_file("/data/test/tpm/swtpm.sock", b"")
+            .add_file("/data/test/tpm/swtpm.pid", b"");
+
+        assert_eq!(
+            Swtpm::new(&system, Path::new("/data/test/tpm")).get_state_files(),
+            [PathBuf::from("/data/test/tpm/tpm2-00.permall")]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod cast;
+mod cast_writer;
+
+pub use cast::{Cast, CastEvent};
+pub use cast_writer::CastWriter;
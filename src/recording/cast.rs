@@ -0,0 +1,116 @@
+use serde_json::Value;
+use std::io;
+
+/// Output of a terminal session at a point in time, in seconds since the
+/// recording began
+#[derive(Debug, PartialEq)]
+pub struct CastEvent {
+    pub time: f64,
+    pub data: String,
+}
+
+/// Terminal session recorded in the asciinema v2 format
+#[derive(Debug, PartialEq)]
+pub struct Cast {
+    pub width: u32,
+    pub height: u32,
+    /// Output events in the order of their time. Input and resize events
+    /// are left out, since replaying them changes nothing on the screen.
+    pub events: Vec<CastEvent>,
+}
+
+impl Cast {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: Value = lines
+            .next()
+            .and_then(|line| serde_json::from_str(line).ok())
+            .ok_or_else(|| Self::invalid("missing header"))?;
+        if header["version"] != 2 {
+            return Err(Self::invalid("not an asciinema v2 recording"));
+        }
+        let get_size = |key: &str| {
+            header[key]
+                .as_u64()
+                .and_then(|size| u32::try_from(size).ok())
+                .ok_or_else(|| Self::invalid(&format!("header lacks the {key}")))
+        };
+        let width = get_size("width")?;
+        let height = get_size("height")?;
+
+        let mut events = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let event: Value = serde_json::from_str(line)
+                .map_err(|_| Self::invalid(&format!("malformed event {}", index + 1)))?;
+            let (Some(time), Some(kind), Some(data)) =
+                (event[0].as_f64(), event[1].as_str(), event[2].as_str())
+            else {
+                return Err(Self::invalid(&format!("malformed event {}", index + 1)));
+            };
+            if kind == "o" {
+                events.push(CastEvent {
+                    time,
+                    data: data.to_string(),
+                });
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            events,
+        })
+    }
+
+    fn invalid(reason: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid recording: {reason}"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cast() {
+        let cast = Cast::parse(
+            "{\"version\": 2, \"width\": 80, \"height\": 24}\n\
+             [0.5, \"o\", \"login: \"]\n\
+             [1.0, \"i\", \"root\"]\n\
+             [1.25, \"o\", \"root\\r\\n\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(cast.width, 80);
+        assert_eq!(cast.height, 24);
+        assert_eq!(
+            cast.events,
+            [
+                CastEvent {
+                    time: 0.5,
+                    data: "login: ".to_string()
+                },
+                CastEvent {
+                    time: 1.25,
+                    data: "root\r\n".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_other_version() {
+        assert!(Cast::parse("{\"version\": 1, \"width\": 80, \"height\": 24}").is_err());
+        assert!(Cast::parse("").is_err());
+    }
+
+    #[test]
+    fn test_reject_malformed_event() {
+        assert!(
+            Cast::parse("{\"version\": 2, \"width\": 80, \"height\": 24}\n[\"o\", 1.0]").is_err()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use serde_json::json;
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Records terminal output in the asciinema v2 format: a JSON header, then a
+/// JSON array per chunk of output with the seconds since the recording began
+pub struct CastWriter {
+    writer: Box<dyn Write>,
+    start: Instant,
+    /// Start of a UTF-8 sequence that the next chunk completes
+    pending: Vec<u8>,
+}
+
+impl CastWriter {
+    pub fn new(mut writer: Box<dyn Write>, width: u32, height: u32) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        writeln!(
+            writer,
+            "{}",
+            json!({ "version": 2, "width": width, "height": height, "timestamp": timestamp })
+        )?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn write_output(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write_output_at(self.start.elapsed(), data)
+    }
+
+    fn write_output_at(&mut self, time: Duration, data: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(data);
+        let end = match std::str::from_utf8(&self.pending) {
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            _ => self.pending.len(),
+        };
+        let rest = self.pending.split_off(end);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = rest;
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        // Microseconds are as precise as asciinema itself records
+        let seconds = (time.as_secs_f64() * 1e6).round() / 1e6;
+        writeln!(self.writer, "{}", json!([seconds, "o", text]))?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{FileSystem, SystemMock};
+    use std::path::Path;
+
+    fn get_lines(system: &SystemMock) -> Vec<String> {
+        String::from_utf8(system.get_written_file("session.cast").unwrap())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_write_header_and_output() {
+        let system = SystemMock::new();
+        let file = system.create_file(Path::new("session.cast")).unwrap();
+        let mut writer = CastWriter::new(file, 80, 24).unwrap();
+
+        writer
+            .write_output_at(Duration::from_millis(1500), b"login: ")
+            .unwrap();
+
+        let lines = get_lines(&system);
+        assert!(lines[0].starts_with(r#"{"height":24,"timestamp":"#));
+        assert!(lines[0].ends_with(r#","version":2,"width":80}"#));
+        assert_eq!(lines[1], r#"[1.5,"o","login: "]"#);
+    }
+
+    #[test]
+    fn test_hold_back_split_utf8_sequence() {
+        let system = SystemMock::new();
+        let file = system.create_file(Path::new("session.cast")).unwrap();
+        let mut writer = CastWriter::new(file, 80, 24).unwrap();
+
+        writer
+            .write_output_at(Duration::from_secs(1), b"gr\xc3")
+            .unwrap();
+        writer
+            .write_output_at(Duration::from_secs(2), b"\xbc\xc3")
+            .unwrap();
+        writer.write_output_at(Duration::from_secs(3), b"").unwrap();
+
+        let lines = get_lines(&system);
+        assert_eq!(&lines[1..], [r#"[1.0,"o","gr"]"#, r#"[2.0,"o","ü"]"#]);
+    }
+}
@@ -0,0 +1,5 @@
+mod png_writer;
+mod ppm_image;
+
+pub use png_writer::PngWriter;
+pub use ppm_image::PpmImage;
@@ -0,0 +1,91 @@
+use crate::screenshot::PpmImage;
+use flate2::Compression;
+use flate2::Crc;
+use flate2::write::ZlibEncoder;
+use std::io::{self, Write};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const BIT_DEPTH: u8 = 8;
+const COLOR_TYPE_RGB: u8 = 2;
+// Each row starts with the filter it was encoded with
+const FILTER_NONE: u8 = 0;
+
+/// Writes an RGB image as PNG, unfiltered and zlib compressed
+pub struct PngWriter;
+
+impl PngWriter {
+    pub fn write(writer: &mut dyn Write, image: &PpmImage) -> io::Result<()> {
+        writer.write_all(SIGNATURE)?;
+
+        let mut header = Vec::new();
+        header.extend(image.width.to_be_bytes());
+        header.extend(image.height.to_be_bytes());
+        // Bit depth, color type, compression, filter and interlace method
+        header.extend([BIT_DEPTH, COLOR_TYPE_RGB, 0, 0, 0]);
+        Self::write_chunk(writer, b"IHDR", &header)?;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let row_size = image.width as usize * 3;
+        if row_size > 0 {
+            for row in image.pixels.chunks(row_size) {
+                encoder.write_all(&[FILTER_NONE])?;
+                encoder.write_all(row)?;
+            }
+        }
+        Self::write_chunk(writer, b"IDAT", &encoder.finish()?)?;
+
+        Self::write_chunk(writer, b"IEND", &[])
+    }
+
+    fn write_chunk(writer: &mut dyn Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+        let mut crc = Crc::new();
+        crc.update(kind);
+        crc.update(data);
+
+        writer.write_all(&(data.len() as u32).to_be_bytes())?;
+        writer.write_all(kind)?;
+        writer.write_all(data)?;
+        writer.write_all(&crc.sum().to_be_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_write() {
+        let image = PpmImage {
+            width: 1,
+            height: 2,
+            pixels: vec![1, 2, 3, 4, 5, 6],
+        };
+        let mut png = Vec::new();
+
+        PngWriter::write(&mut png, &image).unwrap();
+
+        assert_eq!(&png[..8], SIGNATURE);
+        assert_eq!(&png[8..16], b"\x00\x00\x00\x0dIHDR");
+        assert_eq!(
+            &png[16..29],
+            b"\x00\x00\x00\x01\x00\x00\x00\x02\x08\x02\x00\x00\x00"
+        );
+        // The CRC of the IHDR chunk
+        assert_eq!(&png[29..33], b"\x16\xe3\x21\x70");
+
+        let idat_size = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut rows = Vec::new();
+        ZlibDecoder::new(&png[41..41 + idat_size])
+            .read_to_end(&mut rows)
+            .unwrap();
+        assert_eq!(rows, [0, 1, 2, 3, 0, 4, 5, 6]);
+
+        assert_eq!(
+            &png[png.len() - 12..],
+            b"\x00\x00\x00\x00IEND\xae\x42\x60\x82"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::io;
+
+/// RGB image in the binary PPM format (P6) that QEMU writes screen dumps in
+#[derive(Debug, PartialEq)]
+pub struct PpmImage {
+    pub width: u32,
+    pub height: u32,
+    /// Three bytes per pixel, row by row
+    pub pixels: Vec<u8>,
+}
+
+impl PpmImage {
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        // Magic number, width, height and maximum value, each followed by
+        // whitespace, with comments in between
+        while fields.len() < 4 {
+            match data.get(pos) {
+                Some(b'#') => {
+                    while data.get(pos).is_some_and(|c| *c != b'\n') {
+                        pos += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => pos += 1,
+                Some(_) => {
+                    let start = pos;
+                    while data.get(pos).is_some_and(|c| !c.is_ascii_whitespace()) {
+                        pos += 1;
+                    }
+                    fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+                    // A single whitespace ends the header
+                    pos += 1;
+                }
+                None => return Err(Self::invalid("truncated header")),
+            }
+        }
+
+        if fields[0] != "P6" {
+            return Err(Self::invalid("not a binary PPM"));
+        }
+        let parse = |field: &str| {
+            field
+                .parse::<u32>()
+                .map_err(|_| Self::invalid("invalid header"))
+        };
+        let width = parse(&fields[1])?;
+        let height = parse(&fields[2])?;
+        if parse(&fields[3])? != 255 {
+            return Err(Self::invalid("only 8 bits per sample are supported"));
+        }
+
+        let size = width as usize * height as usize * 3;
+        let pixels = data
+            .get(pos..pos + size)
+            .ok_or_else(|| Self::invalid("truncated pixels"))?
+            .to_vec();
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn invalid(reason: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid screen dump: {reason}"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let data = b"P6\n# QEMU screendump\n2 1\n255\n\x01\x02\x03\x04\x05\x06";
+
+        assert_eq!(
+            PpmImage::parse(data).unwrap(),
+            PpmImage {
+                width: 2,
+                height: 1,
+                pixels: vec![1, 2, 3, 4, 5, 6],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated_pixels() {
+        assert!(PpmImage::parse(b"P6 2 1 255 \x01\x02\x03").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_ascii_ppm() {
+        assert!(PpmImage::parse(b"P3 1 1 255 1 2 3").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::commands::Context;
 use crate::error::Error;
 use crate::models::{Instance, TargetInstancePath};
+use crate::recording::CastWriter;
 use crate::ssh::{HostKeyChecker, KeyCheck, SftpPath, SshKeyGenerator};
 use crate::util;
 use crate::view::{ConfirmDialog, Console};
@@ -10,6 +11,7 @@ use russh_sftp::client::SftpSession;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_util::codec::FramedRead;
 use tokio_util::io::StreamReader;
 
@@ -206,6 +208,59 @@ impl<'a> SshClient<'a> {
         Ok(())
     }
 
+    /// Logs into a guest made from a Vagrant box with the insecure key the box
+    /// trusts, and swaps that key for the cubic key. Returns whether the
+    /// session got authenticated on the way.
+    async fn replace_insecure_key(
+        &self,
+        console: &mut Console<'_>,
+        session: &mut russh::client::Handle<ServerKeyHandler>,
+        instance: &mut Instance,
+        user: &str,
+        client_key: &str,
+    ) -> Result<bool, Error> {
+        if !instance
+            .vagrant_box
+            .as_ref()
+            .is_some_and(|vagrant_box| vagrant_box.insecure_key)
+        {
+            return Ok(false);
+        }
+
+        let system = self.context.get_system();
+        let key_file = self.context.get_env().get_vagrant_key_file(&instance.name);
+        if !self
+            .authenticate_with_keys(session, user, std::slice::from_ref(&key_file))
+            .await
+        {
+            console.debug("The guest rejected the Vagrant insecure key");
+            return Ok(false);
+        }
+
+        let pubkey = SshKeyGenerator::new().generate_public_key(system, Path::new(client_key))?;
+        let channel = session
+            .channel_open_session()
+            .await
+            .map_err(|_| Error::SshConnectionFailed(instance.name.clone()))?;
+        self.exec_output(
+            &instance.name,
+            channel,
+            &format!("umask 077 && mkdir -p ~/.ssh && echo '{pubkey}' > ~/.ssh/authorized_keys"),
+        )
+        .await?;
+
+        if let Some(vagrant_box) = &mut instance.vagrant_box {
+            vagrant_box.insecure_key = false;
+        }
+        self.context.get_instance_store().store(instance)?;
+        system.remove_file(Path::new(&key_file)).ok();
+        console.info(&format!(
+            "Replaced the Vagrant insecure key of '{}' with the cubic key",
+            instance.name
+        ));
+        Ok(true)
+    }
+
     /// Reports a host key that does not match the pinned one and asks whether
     /// to trust it from now on.
     fn confirm_new_host_key(
@@ -292,9 +347,15 @@ impl<'a> SshClient<'a> {
             }
         }
 
-        let auth_method = self
-            .authenticate(console, &mut session, user, machine, client_key)
-            .await;
+        let auth_method = if self
+            .replace_insecure_key(console, &mut session, &mut instance, user, client_key)
+            .await?
+        {
+            Ok(AuthMethod::ClientKey)
+        } else {
+            self.authenticate(console, &mut session, user, machine, client_key)
+                .await
+        };
 
         if auth_method? == AuthMethod::Deprecated {
             self.warn_deprecated_auth(console, machine, client_key).ok();
@@ -311,6 +372,7 @@ impl<'a> SshClient<'a> {
         console: &mut Console<'_>,
         instance: &str,
         channel: Channel<russh::client::Msg>,
+        mut recording: Option<CastWriter>,
     ) -> Result<(), Error> {
         let (w, h) = console.get_geometry().unwrap();
 
@@ -367,18 +429,78 @@ impl<'a> SshClient<'a> {
         console.raw_mode();
         let mut stdin = StreamReader::new(FramedRead::new(
             tokio::io::stdin(),
-            util::ShortcutDecoder::new(),
+            util::DetachDecoder::new(),
         ));
-        let mut stdout = tokio::io::stdout();
+        let output = async {
+            let mut stdout = tokio::io::stdout();
+            let mut buf = [0u8; 4096];
+            while let Ok(size) = ssh_reader.read(&mut buf).await {
+                if size == 0 || stdout.write_all(&buf[..size]).await.is_err() {
+                    break;
+                }
+                stdout.flush().await.ok();
+                // A failing recording must not end the session
+                if let Some(writer) = recording.as_mut()
+                    && writer.write_output(&buf[..size]).is_err()
+                {
+                    recording = None;
+                }
+            }
+        };
         tokio::select!(
             _ = tokio::io::copy(&mut stdin, &mut ssh_writer) => {},
-            _ = tokio::io::copy(&mut ssh_reader, &mut stdout) => {},
+            _ = output => {},
             _ = send_geometry_updates(console, &ssh_out) => {},
         );
         console.reset();
         Ok(())
     }
 
+    /// Runs the command without a terminal and returns what it printed.
+    pub async fn output(
+        &self,
+        instance: &str,
+        channel: Channel<russh::client::Msg>,
+    ) -> Result<String, Error> {
+        let cmd = self.cmd.clone().unwrap_or_default();
+        self.exec_output(instance, channel, &cmd).await
+    }
+
+    async fn exec_output(
+        &self,
+        instance: &str,
+        mut channel: Channel<russh::client::Msg>,
+        cmd: &str,
+    ) -> Result<String, Error> {
+        channel
+            .exec(true, cmd)
+            .await
+            .map_err(|_| Error::SshConnectionFailed(instance.to_string()))?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut exit_status = None;
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+                ChannelMsg::ExtendedData { data, .. } => stderr.extend_from_slice(&data),
+                ChannelMsg::ExitStatus {
+                    exit_status: status,
+                } => exit_status = Some(status),
+                _ => {}
+            }
+        }
+
+        if exit_status != Some(0) {
+            return Err(Error::SshCommandFailed(
+                instance.to_string(),
+                String::from_utf8_lossy(&stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&stdout).into_owned())
+    }
+
     async fn open_sftp(
         &self,
         console: &mut Console<'_>,
@@ -3,9 +3,11 @@ mod hex;
 mod shortcut_decoder;
 mod string;
 mod system_command;
+mod time;
 
 pub use async_caller::*;
 pub use hex::*;
 pub use shortcut_decoder::*;
 pub use string::*;
 pub use system_command::*;
+pub use time::*;
@@ -8,12 +8,82 @@ enum State {
     PendingTilde,
 }
 
-/// Decodes the OpenSSH-style detach sequence: Enter, then `~`, then `.`.
-/// Bytes are emitted as soon as they are known not to be part of the
-/// sequence. A `~` at the start of a line is withheld until the next byte
-/// arrives. If that byte is `.`, decoding ends with an error, since the
-/// `Decoder` trait has no other way to end a stream voluntarily while its
-/// source stays open. Neither byte is ever emitted.
+/// Command typed as `~` and a key at the start of a line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EscapeCommand {
+    Detach,
+    Help,
+    Break,
+    Reset,
+    Pause,
+    ToggleLog,
+}
+
+impl EscapeCommand {
+    const ALL: [EscapeCommand; 6] = [
+        EscapeCommand::Detach,
+        EscapeCommand::Help,
+        EscapeCommand::Break,
+        EscapeCommand::Reset,
+        EscapeCommand::Pause,
+        EscapeCommand::ToggleLog,
+    ];
+
+    pub fn from_key(key: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|command| command.get_key() == key)
+    }
+
+    pub fn get_key(&self) -> u8 {
+        match self {
+            EscapeCommand::Detach => b'.',
+            EscapeCommand::Help => b'?',
+            EscapeCommand::Break => b'b',
+            EscapeCommand::Reset => b'r',
+            EscapeCommand::Pause => b'p',
+            EscapeCommand::ToggleLog => b'l',
+        }
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            EscapeCommand::Detach => "detach from the session",
+            EscapeCommand::Help => "show this help",
+            EscapeCommand::Break => "send a break",
+            EscapeCommand::Reset => "reset the VM instance",
+            EscapeCommand::Pause => "pause or resume the VM instance",
+            EscapeCommand::ToggleLog => "start or stop logging the session to a file",
+        }
+    }
+
+    /// Lines that list every command, for `~?`
+    pub fn get_help() -> Vec<String> {
+        let mut lines = vec!["Supported escape sequences:".to_string()];
+        for command in Self::ALL {
+            lines.push(format!(
+                "  ~{} - {}",
+                command.get_key() as char,
+                command.get_description()
+            ));
+        }
+        lines.push("  ~~ - send a literal ~".to_string());
+        lines.push("(Escape sequences are only recognized after a newline.)".to_string());
+        lines
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConsoleInput {
+    Data(Bytes),
+    Escape(EscapeCommand),
+}
+
+/// Decodes OpenSSH-style escape sequences: Enter, then `~`, then the key of
+/// an `EscapeCommand`, which is emitted in place of the two bytes. `~~`
+/// sends a single `~`. Bytes are emitted as soon as they are known not to be
+/// part of a sequence. A `~` at the start of a line is withheld until the
+/// next byte arrives, and replayed along with it if that byte is no command.
 pub struct ShortcutDecoder {
     state: State,
 }
@@ -29,10 +99,10 @@ impl ShortcutDecoder {
 }
 
 impl Decoder for ShortcutDecoder {
-    type Item = Bytes;
+    type Item = ConsoleInput;
     type Error = std::io::Error;
 
-    fn decode(&mut self, src: &mut BytesMut) -> std::io::Result<Option<Bytes>> {
+    fn decode(&mut self, src: &mut BytesMut) -> std::io::Result<Option<ConsoleInput>> {
         let Some(&byte) = src.first() else {
             return Ok(None);
         };
@@ -40,13 +110,21 @@ impl Decoder for ShortcutDecoder {
 
         match self.state {
             State::PendingTilde => {
-                if byte == b'.' {
-                    return Err(std::io::Error::other("shortcut sequence detected"));
+                if let Some(command) = EscapeCommand::from_key(byte) {
+                    self.state = State::Normal {
+                        at_line_start: true,
+                    };
+                    return Ok(Some(ConsoleInput::Escape(command)));
                 }
                 self.state = State::Normal {
                     at_line_start: matches!(byte, b'\r' | b'\n'),
                 };
-                Ok(Some(Bytes::copy_from_slice(&[b'~', byte])))
+                if byte == b'~' {
+                    return Ok(Some(ConsoleInput::Data(Bytes::from_static(b"~"))));
+                }
+                Ok(Some(ConsoleInput::Data(Bytes::copy_from_slice(&[
+                    b'~', byte,
+                ]))))
             }
             State::Normal {
                 at_line_start: true,
@@ -61,12 +139,12 @@ impl Decoder for ShortcutDecoder {
                 self.state = State::Normal {
                     at_line_start: matches!(byte, b'\r' | b'\n'),
                 };
-                Ok(Some(Bytes::copy_from_slice(&[byte])))
+                Ok(Some(ConsoleInput::Data(Bytes::copy_from_slice(&[byte]))))
             }
         }
     }
 
-    fn decode_eof(&mut self, src: &mut BytesMut) -> std::io::Result<Option<Bytes>> {
+    fn decode_eof(&mut self, src: &mut BytesMut) -> std::io::Result<Option<ConsoleInput>> {
         if let Some(item) = self.decode(src)? {
             return Ok(Some(item));
         }
@@ -74,12 +152,54 @@ impl Decoder for ShortcutDecoder {
             self.state = State::Normal {
                 at_line_start: false,
             };
-            return Ok(Some(Bytes::from_static(b"~")));
+            return Ok(Some(ConsoleInput::Data(Bytes::from_static(b"~"))));
         }
         Ok(None)
     }
 }
 
+/// Bytes of a session that knows no escape command but `~.`, e.g. SSH. The
+/// other commands pass through as typed. Detaching ends decoding with an
+/// error, since the `Decoder` trait has no other way to end a stream
+/// voluntarily while its source stays open.
+pub struct DetachDecoder {
+    inner: ShortcutDecoder,
+}
+
+impl DetachDecoder {
+    pub fn new() -> Self {
+        Self {
+            inner: ShortcutDecoder::new(),
+        }
+    }
+
+    fn to_bytes(input: Option<ConsoleInput>) -> std::io::Result<Option<Bytes>> {
+        match input {
+            Some(ConsoleInput::Data(data)) => Ok(Some(data)),
+            Some(ConsoleInput::Escape(EscapeCommand::Detach)) => {
+                Err(std::io::Error::other("shortcut sequence detected"))
+            }
+            Some(ConsoleInput::Escape(command)) => {
+                Ok(Some(Bytes::copy_from_slice(&[b'~', command.get_key()])))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl Decoder for DetachDecoder {
+    type Item = Bytes;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> std::io::Result<Option<Bytes>> {
+        Self::to_bytes(self.inner.decode(src)?)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> std::io::Result<Option<Bytes>> {
+        Self::to_bytes(self.inner.decode_eof(src)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,72 +208,95 @@ mod tests {
     use tokio_util::codec::FramedRead;
     use tokio_util::io::StreamReader;
 
-    fn shortcut_reader<I: tokio::io::AsyncRead>(
+    fn detach_reader<I: tokio::io::AsyncRead>(
         inner: I,
-    ) -> StreamReader<FramedRead<I, ShortcutDecoder>, Bytes> {
-        StreamReader::new(FramedRead::new(inner, ShortcutDecoder::new()))
+    ) -> StreamReader<FramedRead<I, DetachDecoder>, Bytes> {
+        StreamReader::new(FramedRead::new(inner, DetachDecoder::new()))
+    }
+
+    fn data(bytes: &'static [u8]) -> Option<ConsoleInput> {
+        Some(ConsoleInput::Data(Bytes::from_static(bytes)))
+    }
+
+    fn escape(command: EscapeCommand) -> Option<ConsoleInput> {
+        Some(ConsoleInput::Escape(command))
     }
 
     #[test]
     fn detach_on_tilde_dot_at_session_start() {
         let mut decoder = ShortcutDecoder::new();
         let mut src = BytesMut::from(&b"~."[..]);
-        assert!(decoder.decode(&mut src).is_err());
+        assert_eq!(
+            escape(EscapeCommand::Detach),
+            decoder.decode(&mut src).unwrap()
+        );
     }
 
     #[test]
     fn detach_on_tilde_dot_after_newline() {
         let mut decoder = ShortcutDecoder::new();
         let mut src = BytesMut::from(&b"a\n"[..]);
+        assert_eq!(data(b"a"), decoder.decode(&mut src).unwrap());
+        assert_eq!(data(b"\n"), decoder.decode(&mut src).unwrap());
+
+        let mut src = BytesMut::from(&b"~."[..]);
         assert_eq!(
-            Some(Bytes::from_static(b"a")),
-            decoder.decode(&mut src).unwrap()
-        );
-        assert_eq!(
-            Some(Bytes::from_static(b"\n")),
+            escape(EscapeCommand::Detach),
             decoder.decode(&mut src).unwrap()
         );
+    }
 
-        let mut src = BytesMut::from(&b"~."[..]);
-        assert!(decoder.decode(&mut src).is_err());
+    #[test]
+    fn decode_every_escape_command() {
+        for (input, command) in [
+            (&b"~?"[..], EscapeCommand::Help),
+            (b"~b", EscapeCommand::Break),
+            (b"~r", EscapeCommand::Reset),
+            (b"~p", EscapeCommand::Pause),
+            (b"~l", EscapeCommand::ToggleLog),
+        ] {
+            let mut decoder = ShortcutDecoder::new();
+            let mut src = BytesMut::from(input);
+            assert_eq!(escape(command), decoder.decode(&mut src).unwrap());
+        }
     }
 
     #[test]
-    fn no_detach_when_tilde_is_mid_line() {
+    fn keep_decoding_escapes_after_an_escape() {
         let mut decoder = ShortcutDecoder::new();
-        let mut src = BytesMut::from(&b"a~."[..]);
+        let mut src = BytesMut::from(&b"~p~p"[..]);
         assert_eq!(
-            Some(Bytes::from_static(b"a")),
+            escape(EscapeCommand::Pause),
             decoder.decode(&mut src).unwrap()
         );
         assert_eq!(
-            Some(Bytes::from_static(b"~")),
-            decoder.decode(&mut src).unwrap()
-        );
-        assert_eq!(
-            Some(Bytes::from_static(b".")),
+            escape(EscapeCommand::Pause),
             decoder.decode(&mut src).unwrap()
         );
     }
 
     #[test]
-    fn replay_withheld_tilde_when_next_byte_is_not_a_dot() {
+    fn no_detach_when_tilde_is_mid_line() {
+        let mut decoder = ShortcutDecoder::new();
+        let mut src = BytesMut::from(&b"a~."[..]);
+        assert_eq!(data(b"a"), decoder.decode(&mut src).unwrap());
+        assert_eq!(data(b"~"), decoder.decode(&mut src).unwrap());
+        assert_eq!(data(b"."), decoder.decode(&mut src).unwrap());
+    }
+
+    #[test]
+    fn replay_withheld_tilde_when_next_byte_is_no_command() {
         let mut decoder = ShortcutDecoder::new();
         let mut src = BytesMut::from(&b"~x"[..]);
-        assert_eq!(
-            Some(Bytes::copy_from_slice(b"~x")),
-            decoder.decode(&mut src).unwrap()
-        );
+        assert_eq!(data(b"~x"), decoder.decode(&mut src).unwrap());
     }
 
     #[test]
-    fn replay_tilde_on_repeated_tilde() {
+    fn send_single_tilde_on_repeated_tilde() {
         let mut decoder = ShortcutDecoder::new();
-        let mut src = BytesMut::from(&b"~~"[..]);
-        assert_eq!(
-            Some(Bytes::copy_from_slice(b"~~")),
-            decoder.decode(&mut src).unwrap()
-        );
+        let mut src = BytesMut::from(&b"~~."[..]);
+        assert_eq!(data(b"~"), decoder.decode(&mut src).unwrap());
+        assert_eq!(data(b"."), decoder.decode(&mut src).unwrap());
     }
 
     #[test]
@@ -168,10 +311,7 @@ mod tests {
         let mut decoder = ShortcutDecoder::new();
         let mut src = BytesMut::from(&b"~"[..]);
         assert_eq!(None, decoder.decode(&mut src).unwrap());
-        assert_eq!(
-            Some(Bytes::from_static(b"~")),
-            decoder.decode_eof(&mut src).unwrap()
-        );
+        assert_eq!(data(b"~"), decoder.decode_eof(&mut src).unwrap());
         assert_eq!(None, decoder.decode_eof(&mut src).unwrap());
     }
 
@@ -182,11 +322,30 @@ mod tests {
         assert_eq!(None, decoder.decode_eof(&mut src).unwrap());
     }
 
+    #[test]
+    fn help_lists_every_command() {
+        let help = EscapeCommand::get_help();
+        for command in EscapeCommand::ALL {
+            let prefix = format!("  ~{} - ", command.get_key() as char);
+            assert!(help.iter().any(|line| line.starts_with(&prefix)));
+        }
+    }
+
+    #[test]
+    fn detach_decoder_replays_other_escapes() {
+        let mut decoder = DetachDecoder::new();
+        let mut src = BytesMut::from(&b"~r"[..]);
+        assert_eq!(
+            Some(Bytes::from_static(b"~r")),
+            decoder.decode(&mut src).unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn detach_on_enter_tilde_dot_suppresses_both_bytes() {
         let (mut writer, reader) = duplex(64);
         writer.write_all(b"~.").await.unwrap();
-        let mut shortcut = shortcut_reader(reader);
+        let mut shortcut = detach_reader(reader);
 
         let mut buf = [0u8; 8];
         assert!(shortcut.read(&mut buf).await.is_err());
@@ -197,7 +356,7 @@ mod tests {
         let (mut writer, reader) = duplex(64);
         writer.write_all(b"~").await.unwrap();
         drop(writer);
-        let mut shortcut = shortcut_reader(reader);
+        let mut shortcut = detach_reader(reader);
 
         let mut received = Vec::new();
         shortcut.read_to_end(&mut received).await.unwrap();
@@ -208,7 +367,7 @@ mod tests {
     async fn withholds_tilde_until_next_byte_arrives() {
         let (mut writer, reader) = duplex(64);
         writer.write_all(b"~").await.unwrap();
-        let mut shortcut = shortcut_reader(reader);
+        let mut shortcut = detach_reader(reader);
 
         let mut buf = [0u8; 8];
         let read = tokio::time::timeout(Duration::from_millis(50), shortcut.read(&mut buf)).await;
@@ -222,10 +381,10 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn withheld_tilde_replays_once_a_non_dot_byte_arrives() {
+    async fn withheld_tilde_replays_once_a_non_command_byte_arrives() {
         let (mut writer, reader) = duplex(64);
         writer.write_all(b"~").await.unwrap();
-        let mut shortcut = shortcut_reader(reader);
+        let mut shortcut = detach_reader(reader);
 
         let mut buf = [0u8; 8];
         let read = tokio::time::timeout(Duration::from_millis(50), shortcut.read(&mut buf)).await;
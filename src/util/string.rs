@@ -13,6 +13,10 @@ pub fn to_yes_no(condition: bool) -> &'static str {
     if condition { "yes" } else { "no" }
 }
 
+pub fn to_on_off(condition: bool) -> &'static str {
+    if condition { "on" } else { "off" }
+}
+
 pub fn format_or_na<T: ToString>(value: Option<T>) -> String {
     value.map_or_else(|| "n/a".to_string(), |value| value.to_string())
 }
@@ -43,6 +47,12 @@ mod tests {
         assert_eq!(to_yes_no(false), "no");
     }
 
+    #[test]
+    fn test_to_on_off() {
+        assert_eq!(to_on_off(true), "on");
+        assert_eq!(to_on_off(false), "off");
+    }
+
     #[test]
     fn test_format_or_na() {
         assert_eq!(format_or_na(Some(8001)), "8001");
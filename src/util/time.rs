@@ -0,0 +1,50 @@
+// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp
+// (e.g. 2026-01-31T12:00:00Z), without pulling in a date crate.
+pub fn format_utc(secs: i64) -> String {
+    let days = secs.div_euclid(86400);
+    let time = secs.rem_euclid(86400);
+
+    // Converts days since the epoch into a civil date, following Howard
+    // Hinnant's days_from_civil algorithm in reverse.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_epoch() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z")
+    }
+
+    #[test]
+    fn test_format_leap_day() {
+        assert_eq!(format_utc(951782400), "2000-02-29T00:00:00Z")
+    }
+
+    #[test]
+    fn test_format_time_of_day() {
+        assert_eq!(format_utc(1791979199), "2026-10-14T11:59:59Z")
+    }
+
+    #[test]
+    fn test_format_before_epoch() {
+        assert_eq!(format_utc(-1), "1969-12-31T23:59:59Z")
+    }
+}
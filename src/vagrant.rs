@@ -0,0 +1,7 @@
+mod box_unpacker;
+
+pub use box_unpacker::BoxUnpacker;
+
+/// Private key every box trusts out of the box, as published with Vagrant
+/// in keys/vagrant. It is public, which is why the first login replaces it.
+pub const INSECURE_KEY: &str = include_str!("vagrant/insecure_key");
@@ -0,0 +1,105 @@
+use flate2::read::GzDecoder;
+use std::io::{self, BufRead, BufReader, Read};
+use tar::{Archive, EntryType};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Unpacks a Vagrant box, a tar archive that is usually gzip compressed
+#[derive(Default)]
+pub struct BoxUnpacker;
+
+impl BoxUnpacker {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Hands the name and content of every file in the box to `write`.
+    /// Boxes keep their files at the top, so only the file name is passed on,
+    /// which also keeps an entry from reaching outside the target directory.
+    pub fn unpack<R: Read>(
+        &self,
+        reader: R,
+        write: &mut dyn FnMut(&str, &mut dyn Read) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(reader);
+        if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Self::unpack_tar(GzDecoder::new(reader), write)
+        } else {
+            Self::unpack_tar(reader, write)
+        }
+    }
+
+    fn unpack_tar<R: Read>(
+        reader: R,
+        write: &mut dyn FnMut(&str, &mut dyn Read) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut archive = Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.header().entry_type() != EntryType::Regular {
+                continue;
+            }
+
+            let path = entry.path()?;
+            let Some(name) = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+            else {
+                continue;
+            };
+            write(&name, &mut entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tar::{Builder, Header};
+
+    fn build_box() -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for (name, content) in [("./metadata.json", &b"{}"[..]), ("./box.img", &b"disk"[..])] {
+            let mut header = Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn unpack(data: &[u8]) -> Vec<(String, String)> {
+        let mut files = Vec::new();
+        BoxUnpacker::new()
+            .unpack(data, &mut |name, content| {
+                let mut text = String::new();
+                content.read_to_string(&mut text)?;
+                files.push((name.to_string(), text));
+                Ok(())
+            })
+            .unwrap();
+        files
+    }
+
+    #[test]
+    fn test_unpack_tar() {
+        assert_eq!(
+            unpack(&build_box()),
+            vec![
+                ("metadata.json".to_string(), "{}".to_string()),
+                ("box.img".to_string(), "disk".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unpack_gzip_compressed_tar() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        io::Write::write_all(&mut encoder, &build_box()).unwrap();
+
+        assert_eq!(unpack(&encoder.finish().unwrap()).len(), 2);
+    }
+}
@@ -163,6 +163,16 @@ impl<'a> Console<'a> {
         reply
     }
 
+    // Returns None at the end of the input
+    pub fn prompt_line(&mut self, text: &str) -> Option<String> {
+        self.mute();
+        self.system.print(Stream::Stdout, text);
+        self.system.flush(Stream::Stdout);
+        let reply = self.system.read_line();
+        self.unmute();
+        reply
+    }
+
     pub fn prompt_secret(&mut self, text: &str) -> Result<String, ()> {
         self.mute();
         self.system.print(Stream::Stdout, text);